@@ -0,0 +1,73 @@
+use alloc::sync::Arc;
+
+use crate::{cache::get_block_cache, device::BlockDevice, error::Result, BLOCK_SIZE};
+
+const BITS_PER_BLOCK: usize = BLOCK_SIZE * 8;
+
+type BitmapBlock = [u64; BLOCK_SIZE / 8];
+
+/// Allocation bitmap spanning `blocks` blocks from `start_block_id`.
+#[derive(Debug)]
+pub struct Bitmap {
+    start_block_id: usize,
+    blocks: usize,
+}
+
+impl Bitmap {
+    pub fn new(start_block_id: usize, blocks: usize) -> Self {
+        Self {
+            start_block_id,
+            blocks,
+        }
+    }
+
+    /// Sets the first clear bit and returns its index.
+    pub fn alloc(&self, device: &Arc<dyn BlockDevice>) -> Result<Option<usize>> {
+        for block in 0..self.blocks {
+            let bit = get_block_cache(self.start_block_id + block, device.clone())?
+                .lock()
+                .modify(0, |bitmap: &mut BitmapBlock| {
+                    let (index, bits) = bitmap
+                        .iter_mut()
+                        .enumerate()
+                        .find(|(_, bits)| **bits != u64::MAX)?;
+                    let offset = bits.trailing_ones() as usize;
+                    *bits |= 1 << offset;
+                    Some(index * 64 + offset)
+                });
+
+            if let Some(bit) = bit {
+                return Ok(Some(block * BITS_PER_BLOCK + bit));
+            }
+        }
+
+        Ok(None)
+    }
+
+    pub fn dealloc(&self, device: &Arc<dyn BlockDevice>, bit: usize) -> Result<()> {
+        let (block, bit) = (bit / BITS_PER_BLOCK, bit % BITS_PER_BLOCK);
+        get_block_cache(self.start_block_id + block, device.clone())?
+            .lock()
+            .modify(0, |bitmap: &mut BitmapBlock| {
+                assert!(bitmap[bit / 64] & (1 << (bit % 64)) != 0, "double free");
+                bitmap[bit / 64] &= !(1 << (bit % 64));
+            });
+
+        Ok(())
+    }
+
+    pub fn is_set(&self, device: &Arc<dyn BlockDevice>, bit: usize) -> Result<bool> {
+        let (block, bit) = (bit / BITS_PER_BLOCK, bit % BITS_PER_BLOCK);
+        Ok(
+            get_block_cache(self.start_block_id + block, device.clone())?
+                .lock()
+                .read(0, |bitmap: &BitmapBlock| {
+                    bitmap[bit / 64] & (1 << (bit % 64)) != 0
+                }),
+        )
+    }
+
+    pub fn maximum(&self) -> usize {
+        self.blocks * BITS_PER_BLOCK
+    }
+}
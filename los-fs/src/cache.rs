@@ -1,4 +1,5 @@
-use alloc::{collections::vec_deque::VecDeque, sync::Arc};
+use alloc::{collections::vec_deque::VecDeque, sync::Arc, vec::Vec};
+use core::mem;
 use lazy_static::lazy_static;
 use spin::Mutex;
 
@@ -13,17 +14,21 @@ lazy_static! {
         Mutex::new(BlockCacheManager::new());
 }
 
+/// Block contents, aligned so on-disk structures can be viewed in place.
+#[repr(C, align(8))]
+struct BlockData([u8; BLOCK_SIZE]);
+
 pub struct BlockCache {
     id: usize,
-    data: [u8; BLOCK_SIZE],
+    data: BlockData,
     device: Arc<dyn BlockDevice>,
     modified: bool,
 }
 
 impl BlockCache {
     pub fn init(id: usize, device: Arc<dyn BlockDevice>) -> Result<Self> {
-        let mut data = [0u8; BLOCK_SIZE];
-        device.read_block(id, &mut data)?;
+        let mut data = BlockData([0u8; BLOCK_SIZE]);
+        device.read_block(id, &mut data.0)?;
 
         Ok(Self {
             id,
@@ -34,21 +39,60 @@ impl BlockCache {
     }
 
     pub fn get_bytes(&self) -> &[u8] {
-        &self.data
+        &self.data.0
+    }
+
+    fn check_offset<T>(offset: usize) {
+        assert!(offset + mem::size_of::<T>() <= BLOCK_SIZE);
+        assert!(offset.is_multiple_of(mem::align_of::<T>()));
+    }
+
+    /// Views the block at `offset` as a `T`, which must be a plain on-disk
+    /// structure valid for any bit pattern.
+    pub fn get_ref<T>(&self, offset: usize) -> &T {
+        Self::check_offset::<T>(offset);
+        unsafe { &*(self.data.0.as_ptr().add(offset) as *const T) }
+    }
+
+    pub fn get_mut<T>(&mut self, offset: usize) -> &mut T {
+        Self::check_offset::<T>(offset);
+        self.modified = true;
+        unsafe { &mut *(self.data.0.as_mut_ptr().add(offset) as *mut T) }
+    }
+
+    pub fn read<T, V>(&self, offset: usize, f: impl FnOnce(&T) -> V) -> V {
+        f(self.get_ref(offset))
+    }
+
+    pub fn modify<T, V>(&mut self, offset: usize, f: impl FnOnce(&mut T) -> V) -> V {
+        f(self.get_mut(offset))
     }
 
     pub fn sync(&mut self) -> Result<()> {
         if self.modified {
             self.modified = false;
-            self.device.write_block(self.id, &self.data)?;
+            self.device.write_block(self.id, &self.data.0)?;
         }
 
         Ok(())
     }
 }
 
+impl Drop for BlockCache {
+    fn drop(&mut self) {
+        // nobody is left to report a failed write back to
+        let _ = self.sync();
+    }
+}
+
+fn same_device(a: &Arc<dyn BlockDevice>, b: &Arc<dyn BlockDevice>) -> bool {
+    Arc::as_ptr(a) as *const () == Arc::as_ptr(b) as *const ()
+}
+
+type CacheKey = (usize, Arc<dyn BlockDevice>);
+
 pub struct BlockCacheManager {
-    caches: VecDeque<(usize, Arc<Mutex<BlockCache>>)>,
+    caches: VecDeque<(CacheKey, Arc<Mutex<BlockCache>>)>,
 }
 
 impl BlockCacheManager {
@@ -66,8 +110,8 @@ impl BlockCacheManager {
         if let Some(cache) = self
             .caches
             .iter()
-            .find(|(cid, _cache)| *cid == id)
-            .map(|(_id, cache)| cache)
+            .find(|((cid, cdevice), _cache)| *cid == id && same_device(cdevice, &device))
+            .map(|(_key, cache)| cache)
         {
             Ok(cache.clone())
         } else {
@@ -85,10 +129,14 @@ impl BlockCacheManager {
 
             let cache = BlockCache::init(id, device.clone()).map(|v| Arc::new(Mutex::new(v)))?;
 
-            self.caches.push_back((id, cache.clone()));
+            self.caches.push_back(((id, device), cache.clone()));
             Ok(cache)
         }
     }
+
+    fn caches(&self) -> Vec<Arc<Mutex<BlockCache>>> {
+        self.caches.iter().map(|(_, cache)| cache.clone()).collect()
+    }
 }
 
 impl Default for BlockCacheManager {
@@ -100,3 +148,14 @@ impl Default for BlockCacheManager {
 pub fn get_block_cache(id: usize, device: Arc<dyn BlockDevice>) -> Result<Arc<Mutex<BlockCache>>> {
     BLOCK_CACHE_MANAGER.lock().get_block_cache(id, device)
 }
+
+/// Writes every modified cached block back to its device.
+pub fn sync_all() -> Result<()> {
+    // the manager is unlocked first, holders of a block may be waiting on it
+    let caches = BLOCK_CACHE_MANAGER.lock().caches();
+    for cache in caches {
+        cache.lock().sync()?;
+    }
+
+    Ok(())
+}
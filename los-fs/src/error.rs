@@ -5,6 +5,17 @@ pub enum Error {
     ReadBlock(String),
     WriteBlock(String),
     NoFreeCache,
+    InvalidSuperBlock,
+    TooSmall,
+    NoFreeInode,
+    NoFreeBlock,
+    NotFound,
+    AlreadyExists,
+    NotDirectory,
+    IsDirectory,
+    DirectoryNotEmpty,
+    NameTooLong,
+    FileTooLarge,
 }
 
 pub type Result<T> = core::result::Result<T, Error>;
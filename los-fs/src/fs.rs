@@ -0,0 +1,176 @@
+use alloc::sync::Arc;
+use spin::Mutex;
+
+use crate::{
+    bitmap::Bitmap,
+    cache::{get_block_cache, sync_all},
+    device::BlockDevice,
+    error::{Error, Result},
+    layout::{DiskInode, InodeKind, SuperBlock, DISK_INODE_SIZE},
+    vfs::Inode,
+    BLOCK_SIZE,
+};
+
+const INODES_PER_BLOCK: usize = BLOCK_SIZE / DISK_INODE_SIZE;
+const ROOT_INODE_ID: u32 = 0;
+
+type DataBlock = [u8; BLOCK_SIZE];
+
+/// A mounted filesystem. All inodes share it behind one lock, which
+/// serializes every operation on the device.
+pub struct FileSystem {
+    pub(crate) device: Arc<dyn BlockDevice>,
+    inode_bitmap: Bitmap,
+    data_bitmap: Bitmap,
+    inode_area_start: u32,
+    data_area_start: u32,
+    data_area_blocks: u32,
+}
+
+impl FileSystem {
+    /// Formats `total_blocks` blocks of `device` with room for
+    /// `inode_bitmap_blocks * 4096` inodes and creates the root directory.
+    pub fn create(
+        device: Arc<dyn BlockDevice>,
+        total_blocks: u32,
+        inode_bitmap_blocks: u32,
+    ) -> Result<Arc<Mutex<Self>>> {
+        let inode_count = inode_bitmap_blocks as usize * BLOCK_SIZE * 8;
+        let inode_area_blocks = inode_count.div_ceil(INODES_PER_BLOCK) as u32;
+        let meta_blocks = 1 + inode_bitmap_blocks + inode_area_blocks;
+        let remaining = total_blocks
+            .checked_sub(meta_blocks)
+            .filter(|&remaining| remaining > 1)
+            .ok_or(Error::TooSmall)?;
+        // one bitmap block covers itself and 4096 data blocks
+        let data_bitmap_blocks = remaining.div_ceil(BLOCK_SIZE as u32 * 8 + 1);
+        let data_area_blocks = remaining - data_bitmap_blocks;
+
+        let mut fs = Self {
+            device: device.clone(),
+            inode_bitmap: Bitmap::new(1, inode_bitmap_blocks as usize),
+            data_bitmap: Bitmap::new(
+                (1 + inode_bitmap_blocks + inode_area_blocks) as usize,
+                data_bitmap_blocks as usize,
+            ),
+            inode_area_start: 1 + inode_bitmap_blocks,
+            data_area_start: meta_blocks + data_bitmap_blocks,
+            data_area_blocks,
+        };
+
+        for block_id in 0..total_blocks {
+            get_block_cache(block_id as usize, device.clone())?
+                .lock()
+                .modify(0, |data: &mut DataBlock| data.fill(0));
+        }
+
+        get_block_cache(0, device.clone())?
+            .lock()
+            .modify(0, |super_block: &mut SuperBlock| {
+                super_block.init(
+                    total_blocks,
+                    inode_bitmap_blocks,
+                    inode_area_blocks,
+                    data_bitmap_blocks,
+                    data_area_blocks,
+                )
+            });
+
+        let root_inode_id = fs.alloc_inode()?;
+        assert_eq!(root_inode_id, ROOT_INODE_ID);
+        let (block_id, offset) = fs.disk_inode_pos(root_inode_id);
+        get_block_cache(block_id, device)?
+            .lock()
+            .modify(offset, |disk_inode: &mut DiskInode| {
+                disk_inode.init(InodeKind::Directory)
+            });
+        sync_all()?;
+
+        Ok(Arc::new(Mutex::new(fs)))
+    }
+
+    pub fn open(device: Arc<dyn BlockDevice>) -> Result<Arc<Mutex<Self>>> {
+        let super_block = get_block_cache(0, device.clone())?
+            .lock()
+            .read(0, |super_block: &SuperBlock| *super_block);
+        if !super_block.is_valid() {
+            return Err(Error::InvalidSuperBlock);
+        }
+
+        let inode_area_start = 1 + super_block.inode_bitmap_blocks;
+        let data_bitmap_start = inode_area_start + super_block.inode_area_blocks;
+
+        Ok(Arc::new(Mutex::new(Self {
+            device,
+            inode_bitmap: Bitmap::new(1, super_block.inode_bitmap_blocks as usize),
+            data_bitmap: Bitmap::new(
+                data_bitmap_start as usize,
+                super_block.data_bitmap_blocks as usize,
+            ),
+            inode_area_start,
+            data_area_start: data_bitmap_start + super_block.data_bitmap_blocks,
+            data_area_blocks: super_block.data_area_blocks,
+        })))
+    }
+
+    pub fn root_inode(fs: &Arc<Mutex<Self>>) -> Inode {
+        let (block_id, offset) = fs.lock().disk_inode_pos(ROOT_INODE_ID);
+        Inode::new(ROOT_INODE_ID, block_id, offset, fs.clone())
+    }
+
+    /// Block id and offset in it of an inode.
+    pub(crate) fn disk_inode_pos(&self, inode_id: u32) -> (usize, usize) {
+        let inode_id = inode_id as usize;
+        (
+            self.inode_area_start as usize + inode_id / INODES_PER_BLOCK,
+            inode_id % INODES_PER_BLOCK * DISK_INODE_SIZE,
+        )
+    }
+
+    pub(crate) fn alloc_inode(&mut self) -> Result<u32> {
+        let inode_id = self
+            .inode_bitmap
+            .alloc(&self.device)?
+            .ok_or(Error::NoFreeInode)?;
+        Ok(inode_id as u32)
+    }
+
+    pub(crate) fn dealloc_inode(&mut self, inode_id: u32) -> Result<()> {
+        self.inode_bitmap.dealloc(&self.device, inode_id as usize)
+    }
+
+    /// Allocates a zeroed data block and returns its block id.
+    pub(crate) fn alloc_data(&mut self) -> Result<u32> {
+        let bit = self
+            .data_bitmap
+            .alloc(&self.device)?
+            .ok_or(Error::NoFreeBlock)?;
+        // the last bitmap block covers more bits than there are blocks
+        if bit >= self.data_area_blocks as usize {
+            self.data_bitmap.dealloc(&self.device, bit)?;
+            return Err(Error::NoFreeBlock);
+        }
+
+        let block_id = self.data_area_start + bit as u32;
+        get_block_cache(block_id as usize, self.device.clone())?
+            .lock()
+            .modify(0, |data: &mut DataBlock| data.fill(0));
+
+        Ok(block_id)
+    }
+
+    pub(crate) fn dealloc_data(&mut self, block_id: u32) -> Result<()> {
+        self.data_bitmap
+            .dealloc(&self.device, (block_id - self.data_area_start) as usize)
+    }
+
+    pub fn free_blocks(&self) -> Result<usize> {
+        let mut used = 0;
+        for bit in 0..self.data_area_blocks as usize {
+            if self.data_bitmap.is_set(&self.device, bit)? {
+                used += 1;
+            }
+        }
+        Ok(self.data_area_blocks as usize - used)
+    }
+}
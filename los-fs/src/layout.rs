@@ -0,0 +1,386 @@
+use alloc::{sync::Arc, vec::Vec};
+use core::mem;
+
+use crate::{
+    cache::get_block_cache,
+    device::BlockDevice,
+    error::{Error, Result},
+    BLOCK_SIZE,
+};
+
+pub const MAGIC: u32 = 0x4c4f_5346;
+
+const DIRECT_COUNT: usize = 28;
+const INDIRECT_COUNT: usize = BLOCK_SIZE / mem::size_of::<u32>();
+const DIRECT_BOUND: usize = DIRECT_COUNT;
+const INDIRECT1_BOUND: usize = DIRECT_BOUND + INDIRECT_COUNT;
+const INDIRECT2_BOUND: usize = INDIRECT1_BOUND + INDIRECT_COUNT * INDIRECT_COUNT;
+
+pub const MAX_FILE_SIZE: usize = INDIRECT2_BOUND * BLOCK_SIZE;
+
+type IndirectBlock = [u32; INDIRECT_COUNT];
+type DataBlock = [u8; BLOCK_SIZE];
+
+/// Block 0 of the device. The rest of it is laid out as the inode bitmap,
+/// the inode area, the data bitmap and the data area, in that order.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SuperBlock {
+    magic: u32,
+    pub total_blocks: u32,
+    pub inode_bitmap_blocks: u32,
+    pub inode_area_blocks: u32,
+    pub data_bitmap_blocks: u32,
+    pub data_area_blocks: u32,
+}
+
+impl SuperBlock {
+    pub fn init(
+        &mut self,
+        total_blocks: u32,
+        inode_bitmap_blocks: u32,
+        inode_area_blocks: u32,
+        data_bitmap_blocks: u32,
+        data_area_blocks: u32,
+    ) {
+        *self = Self {
+            magic: MAGIC,
+            total_blocks,
+            inode_bitmap_blocks,
+            inode_area_blocks,
+            data_bitmap_blocks,
+            data_area_blocks,
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.magic == MAGIC
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InodeKind {
+    File,
+    Directory,
+}
+
+const KIND_FILE: u32 = 0;
+const KIND_DIRECTORY: u32 = 1;
+
+/// An inode as stored in the inode area. Data blocks are found through 28
+/// direct pointers, then one indirect block and one doubly indirect block.
+/// Block id 0 is the super block, so it never names a data block.
+#[repr(C)]
+pub struct DiskInode {
+    pub size: u32,
+    direct: [u32; DIRECT_COUNT],
+    indirect1: u32,
+    indirect2: u32,
+    kind: u32,
+}
+
+pub const DISK_INODE_SIZE: usize = mem::size_of::<DiskInode>();
+
+impl DiskInode {
+    pub fn init(&mut self, kind: InodeKind) {
+        self.size = 0;
+        self.direct = [0; DIRECT_COUNT];
+        self.indirect1 = 0;
+        self.indirect2 = 0;
+        self.kind = match kind {
+            InodeKind::File => KIND_FILE,
+            InodeKind::Directory => KIND_DIRECTORY,
+        };
+    }
+
+    pub fn kind(&self) -> InodeKind {
+        match self.kind {
+            KIND_DIRECTORY => InodeKind::Directory,
+            _ => InodeKind::File,
+        }
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.kind() == InodeKind::Directory
+    }
+
+    fn data_blocks_of(size: u32) -> usize {
+        (size as usize).div_ceil(BLOCK_SIZE)
+    }
+
+    /// Data blocks plus the index blocks needed to reach them.
+    fn total_blocks_of(size: u32) -> usize {
+        let data_blocks = Self::data_blocks_of(size);
+        let mut total = data_blocks;
+        if data_blocks > DIRECT_BOUND {
+            total += 1;
+        }
+        if data_blocks > INDIRECT1_BOUND {
+            total += 1 + (data_blocks - INDIRECT1_BOUND).div_ceil(INDIRECT_COUNT);
+        }
+        total
+    }
+
+    /// Number of blocks to allocate before growing to `new_size`.
+    pub fn blocks_needed(&self, new_size: u32) -> Result<usize> {
+        if new_size as usize > MAX_FILE_SIZE {
+            return Err(Error::FileTooLarge);
+        }
+
+        Ok(Self::total_blocks_of(new_size).saturating_sub(Self::total_blocks_of(self.size)))
+    }
+
+    fn read_indirect(block_id: u32, index: usize, device: &Arc<dyn BlockDevice>) -> Result<u32> {
+        Ok(get_block_cache(block_id as usize, device.clone())?
+            .lock()
+            .read(0, |block: &IndirectBlock| block[index]))
+    }
+
+    fn write_indirect(
+        block_id: u32,
+        index: usize,
+        value: u32,
+        device: &Arc<dyn BlockDevice>,
+    ) -> Result<()> {
+        get_block_cache(block_id as usize, device.clone())?
+            .lock()
+            .modify(0, |block: &mut IndirectBlock| block[index] = value);
+        Ok(())
+    }
+
+    /// Block id of the `inner_id`-th data block of this inode.
+    pub fn block_id(&self, inner_id: usize, device: &Arc<dyn BlockDevice>) -> Result<u32> {
+        if inner_id < DIRECT_BOUND {
+            Ok(self.direct[inner_id])
+        } else if inner_id < INDIRECT1_BOUND {
+            Self::read_indirect(self.indirect1, inner_id - DIRECT_BOUND, device)
+        } else {
+            let index = inner_id - INDIRECT1_BOUND;
+            let indirect1 = Self::read_indirect(self.indirect2, index / INDIRECT_COUNT, device)?;
+            Self::read_indirect(indirect1, index % INDIRECT_COUNT, device)
+        }
+    }
+
+    fn set_block_id(
+        &mut self,
+        inner_id: usize,
+        block_id: u32,
+        device: &Arc<dyn BlockDevice>,
+    ) -> Result<()> {
+        if inner_id < DIRECT_BOUND {
+            self.direct[inner_id] = block_id;
+            Ok(())
+        } else if inner_id < INDIRECT1_BOUND {
+            Self::write_indirect(self.indirect1, inner_id - DIRECT_BOUND, block_id, device)
+        } else {
+            let index = inner_id - INDIRECT1_BOUND;
+            let indirect1 = Self::read_indirect(self.indirect2, index / INDIRECT_COUNT, device)?;
+            Self::write_indirect(indirect1, index % INDIRECT_COUNT, block_id, device)
+        }
+    }
+
+    /// Grows to `new_size`, taking data and index blocks from `new_blocks`,
+    /// which holds exactly `blocks_needed(new_size)` zeroed blocks.
+    pub fn increase_size(
+        &mut self,
+        new_size: u32,
+        new_blocks: Vec<u32>,
+        device: &Arc<dyn BlockDevice>,
+    ) -> Result<()> {
+        let mut current = Self::data_blocks_of(self.size);
+        let total = Self::data_blocks_of(new_size);
+        self.size = new_size;
+
+        let mut new_blocks = new_blocks.into_iter();
+        let mut next = || new_blocks.next().expect("not enough blocks to grow inode");
+        while current < total {
+            if current == DIRECT_BOUND {
+                self.indirect1 = next();
+            }
+            if current >= INDIRECT1_BOUND {
+                let index = current - INDIRECT1_BOUND;
+                if index == 0 {
+                    self.indirect2 = next();
+                }
+                if index.is_multiple_of(INDIRECT_COUNT) {
+                    Self::write_indirect(self.indirect2, index / INDIRECT_COUNT, next(), device)?;
+                }
+            }
+
+            self.set_block_id(current, next(), device)?;
+            current += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Shrinks to `new_size` and returns the data and index blocks that are
+    /// no longer used.
+    pub fn decrease_size(
+        &mut self,
+        new_size: u32,
+        device: &Arc<dyn BlockDevice>,
+    ) -> Result<Vec<u32>> {
+        let mut current = Self::data_blocks_of(self.size);
+        let total = Self::data_blocks_of(new_size);
+
+        let mut freed = Vec::new();
+        while current > total {
+            current -= 1;
+            freed.push(self.block_id(current, device)?);
+
+            if current == DIRECT_BOUND {
+                freed.push(self.indirect1);
+                self.indirect1 = 0;
+            }
+            if current >= INDIRECT1_BOUND {
+                let index = current - INDIRECT1_BOUND;
+                if index.is_multiple_of(INDIRECT_COUNT) {
+                    freed.push(Self::read_indirect(
+                        self.indirect2,
+                        index / INDIRECT_COUNT,
+                        device,
+                    )?);
+                }
+                if index == 0 {
+                    freed.push(self.indirect2);
+                    self.indirect2 = 0;
+                }
+            }
+
+            if current < DIRECT_BOUND {
+                self.direct[current] = 0;
+            }
+        }
+
+        // growing again must read zeros past the new end
+        let tail = new_size as usize % BLOCK_SIZE;
+        if new_size < self.size && tail != 0 {
+            let block_id = self.block_id(new_size as usize / BLOCK_SIZE, device)?;
+            get_block_cache(block_id as usize, device.clone())?
+                .lock()
+                .modify(0, |data: &mut DataBlock| data[tail..].fill(0));
+        }
+        self.size = self.size.min(new_size);
+
+        Ok(freed)
+    }
+
+    pub fn read_at(
+        &self,
+        offset: usize,
+        buf: &mut [u8],
+        device: &Arc<dyn BlockDevice>,
+    ) -> Result<usize> {
+        let end = (offset + buf.len()).min(self.size as usize);
+        if offset >= end {
+            return Ok(0);
+        }
+
+        let mut start = offset;
+        let mut read = 0;
+        while start < end {
+            let block_end = ((start / BLOCK_SIZE + 1) * BLOCK_SIZE).min(end);
+            let len = block_end - start;
+
+            let block_id = self.block_id(start / BLOCK_SIZE, device)?;
+            get_block_cache(block_id as usize, device.clone())?
+                .lock()
+                .read(0, |data: &DataBlock| {
+                    let src = &data[start % BLOCK_SIZE..start % BLOCK_SIZE + len];
+                    buf[read..read + len].copy_from_slice(src);
+                });
+
+            read += len;
+            start = block_end;
+        }
+
+        Ok(read)
+    }
+
+    /// Writes within the current size, callers grow the inode first.
+    pub fn write_at(
+        &mut self,
+        offset: usize,
+        buf: &[u8],
+        device: &Arc<dyn BlockDevice>,
+    ) -> Result<usize> {
+        let end = (offset + buf.len()).min(self.size as usize);
+        if offset >= end {
+            return Ok(0);
+        }
+
+        let mut start = offset;
+        let mut written = 0;
+        while start < end {
+            let block_end = ((start / BLOCK_SIZE + 1) * BLOCK_SIZE).min(end);
+            let len = block_end - start;
+
+            let block_id = self.block_id(start / BLOCK_SIZE, device)?;
+            get_block_cache(block_id as usize, device.clone())?
+                .lock()
+                .modify(0, |data: &mut DataBlock| {
+                    let dst = &mut data[start % BLOCK_SIZE..start % BLOCK_SIZE + len];
+                    dst.copy_from_slice(&buf[written..written + len]);
+                });
+
+            written += len;
+            start = block_end;
+        }
+
+        Ok(written)
+    }
+}
+
+pub const NAME_LENGTH_LIMIT: usize = 27;
+
+/// A directory is a file holding an array of these.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct DirEntry {
+    name: [u8; NAME_LENGTH_LIMIT + 1],
+    inode_id: u32,
+}
+
+pub const DIRENT_SIZE: usize = mem::size_of::<DirEntry>();
+
+impl DirEntry {
+    pub fn empty() -> Self {
+        Self {
+            name: [0; NAME_LENGTH_LIMIT + 1],
+            inode_id: 0,
+        }
+    }
+
+    pub fn new(name: &str, inode_id: u32) -> Result<Self> {
+        if name.len() > NAME_LENGTH_LIMIT {
+            return Err(Error::NameTooLong);
+        }
+
+        let mut entry = Self::empty();
+        entry.name[..name.len()].copy_from_slice(name.as_bytes());
+        entry.inode_id = inode_id;
+        Ok(entry)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { core::slice::from_raw_parts(self as *const _ as *const u8, DIRENT_SIZE) }
+    }
+
+    pub fn as_bytes_mut(&mut self) -> &mut [u8] {
+        unsafe { core::slice::from_raw_parts_mut(self as *mut _ as *mut u8, DIRENT_SIZE) }
+    }
+
+    pub fn name(&self) -> &str {
+        let len = self
+            .name
+            .iter()
+            .position(|&c| c == 0)
+            .unwrap_or(self.name.len());
+        core::str::from_utf8(&self.name[..len]).unwrap_or_default()
+    }
+
+    pub fn inode_id(&self) -> u32 {
+        self.inode_id
+    }
+}
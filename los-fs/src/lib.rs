@@ -2,9 +2,13 @@
 
 extern crate alloc;
 
+pub mod bitmap;
 pub mod cache;
 pub mod device;
 pub mod error;
+pub mod fs;
+pub mod layout;
+pub mod vfs;
 
 pub const BLOCK_SIZE: usize = 512;
 pub const BLOCK_CACHE_COUNT: usize = 16;
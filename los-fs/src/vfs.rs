@@ -0,0 +1,396 @@
+use alloc::{string::String, sync::Arc, vec::Vec};
+use spin::Mutex;
+
+use crate::{
+    cache::{get_block_cache, sync_all},
+    error::{Error, Result},
+    fs::FileSystem,
+    layout::{DirEntry, DiskInode, InodeKind, DIRENT_SIZE, NAME_LENGTH_LIMIT},
+};
+
+/// Handle to an inode of a mounted filesystem. Handles are not counted, so
+/// unlinking frees the inode even if other handles to it remain.
+#[derive(Clone)]
+pub struct Inode {
+    inode_id: u32,
+    block_id: usize,
+    block_offset: usize,
+    fs: Arc<Mutex<FileSystem>>,
+}
+
+impl Inode {
+    pub(crate) fn new(
+        inode_id: u32,
+        block_id: usize,
+        block_offset: usize,
+        fs: Arc<Mutex<FileSystem>>,
+    ) -> Self {
+        Self {
+            inode_id,
+            block_id,
+            block_offset,
+            fs,
+        }
+    }
+
+    fn read_disk_inode<V>(&self, fs: &FileSystem, f: impl FnOnce(&DiskInode) -> V) -> Result<V> {
+        Ok(get_block_cache(self.block_id, fs.device.clone())?
+            .lock()
+            .read(self.block_offset, f))
+    }
+
+    fn modify_disk_inode<V>(
+        &self,
+        fs: &FileSystem,
+        f: impl FnOnce(&mut DiskInode) -> V,
+    ) -> Result<V> {
+        Ok(get_block_cache(self.block_id, fs.device.clone())?
+            .lock()
+            .modify(self.block_offset, f))
+    }
+
+    fn inode_of(&self, inode_id: u32) -> Inode {
+        let (block_id, offset) = self.fs.lock().disk_inode_pos(inode_id);
+        Inode::new(inode_id, block_id, offset, self.fs.clone())
+    }
+
+    pub fn inode_id(&self) -> u32 {
+        self.inode_id
+    }
+
+    pub fn kind(&self) -> Result<InodeKind> {
+        let fs = self.fs.lock();
+        self.read_disk_inode(&fs, DiskInode::kind)
+    }
+
+    pub fn is_dir(&self) -> Result<bool> {
+        Ok(self.kind()? == InodeKind::Directory)
+    }
+
+    pub fn size(&self) -> Result<usize> {
+        let fs = self.fs.lock();
+        self.read_disk_inode(&fs, |disk_inode| disk_inode.size as usize)
+    }
+
+    /// Copies out the entries of this directory.
+    fn dir_entries(&self, fs: &FileSystem) -> Result<Vec<DirEntry>> {
+        let device = fs.device.clone();
+        self.read_disk_inode(fs, |disk_inode| {
+            if !disk_inode.is_dir() {
+                return Err(Error::NotDirectory);
+            }
+
+            let count = disk_inode.size as usize / DIRENT_SIZE;
+            let mut entries = Vec::with_capacity(count);
+            for i in 0..count {
+                let mut entry = DirEntry::empty();
+                disk_inode.read_at(i * DIRENT_SIZE, entry.as_bytes_mut(), &device)?;
+                entries.push(entry);
+            }
+            Ok(entries)
+        })?
+    }
+
+    fn find_entry(&self, fs: &FileSystem, name: &str) -> Result<Option<(usize, u32)>> {
+        Ok(self
+            .dir_entries(fs)?
+            .iter()
+            .enumerate()
+            .find(|(_, entry)| entry.name() == name)
+            .map(|(index, entry)| (index, entry.inode_id())))
+    }
+
+    /// Finds `name` in this directory.
+    pub fn lookup(&self, name: &str) -> Result<Inode> {
+        let inode_id = {
+            let fs = self.fs.lock();
+            self.find_entry(&fs, name)?.ok_or(Error::NotFound)?.1
+        };
+
+        Ok(self.inode_of(inode_id))
+    }
+
+    /// Names of the entries of this directory.
+    pub fn ls(&self) -> Result<Vec<String>> {
+        let fs = self.fs.lock();
+        Ok(self
+            .dir_entries(&fs)?
+            .iter()
+            .map(|entry| String::from(entry.name()))
+            .collect())
+    }
+
+    /// Resizes to `new_size`, allocating or freeing blocks to match.
+    fn resize(&self, fs: &mut FileSystem, new_size: usize) -> Result<()> {
+        let device = fs.device.clone();
+        let new_size = u32::try_from(new_size).map_err(|_| Error::FileTooLarge)?;
+        let (size, blocks_needed) = self.read_disk_inode(fs, |disk_inode| {
+            Ok::<_, Error>((disk_inode.size, disk_inode.blocks_needed(new_size)?))
+        })??;
+
+        if new_size > size {
+            let mut new_blocks = Vec::with_capacity(blocks_needed);
+            for _ in 0..blocks_needed {
+                match fs.alloc_data() {
+                    Ok(block_id) => new_blocks.push(block_id),
+                    Err(err) => {
+                        for block_id in new_blocks {
+                            fs.dealloc_data(block_id)?;
+                        }
+                        return Err(err);
+                    }
+                }
+            }
+
+            self.modify_disk_inode(fs, |disk_inode| {
+                disk_inode.increase_size(new_size, new_blocks, &device)
+            })??;
+        } else if new_size < size {
+            let freed = self.modify_disk_inode(fs, |disk_inode| {
+                disk_inode.decrease_size(new_size, &device)
+            })??;
+            for block_id in freed {
+                fs.dealloc_data(block_id)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Creates `name` in this directory.
+    pub fn create(&self, name: &str, kind: InodeKind) -> Result<Inode> {
+        let mut fs = self.fs.lock();
+        if self.find_entry(&fs, name)?.is_some() {
+            return Err(Error::AlreadyExists);
+        }
+        if name.len() > NAME_LENGTH_LIMIT {
+            return Err(Error::NameTooLong);
+        }
+
+        let inode_id = fs.alloc_inode()?;
+        let (block_id, offset) = fs.disk_inode_pos(inode_id);
+        get_block_cache(block_id, fs.device.clone())?
+            .lock()
+            .modify(offset, |disk_inode: &mut DiskInode| disk_inode.init(kind));
+
+        let size = self.read_disk_inode(&fs, |disk_inode| disk_inode.size as usize)?;
+        if let Err(err) = self.resize(&mut fs, size + DIRENT_SIZE) {
+            fs.dealloc_inode(inode_id)?;
+            return Err(err);
+        }
+
+        let entry = DirEntry::new(name, inode_id)?;
+        let device = fs.device.clone();
+        self.modify_disk_inode(&fs, |disk_inode| {
+            disk_inode.write_at(size, entry.as_bytes(), &device)
+        })??;
+        sync_all()?;
+
+        Ok(Inode::new(inode_id, block_id, offset, self.fs.clone()))
+    }
+
+    /// Removes `name` from this directory and frees its inode. Directories
+    /// must be empty.
+    pub fn unlink(&self, name: &str) -> Result<()> {
+        let mut fs = self.fs.lock();
+        let (index, inode_id) = self.find_entry(&fs, name)?.ok_or(Error::NotFound)?;
+
+        let target = {
+            let (block_id, offset) = fs.disk_inode_pos(inode_id);
+            Inode::new(inode_id, block_id, offset, self.fs.clone())
+        };
+        let (is_dir, target_size) =
+            target.read_disk_inode(&fs, |disk_inode| (disk_inode.is_dir(), disk_inode.size))?;
+        if is_dir && target_size != 0 {
+            return Err(Error::DirectoryNotEmpty);
+        }
+
+        // move the last entry into the hole and drop the last slot
+        let device = fs.device.clone();
+        let size = self.read_disk_inode(&fs, |disk_inode| disk_inode.size as usize)?;
+        let last = size / DIRENT_SIZE - 1;
+        if index != last {
+            self.modify_disk_inode(&fs, |disk_inode| {
+                let mut entry = DirEntry::empty();
+                disk_inode.read_at(last * DIRENT_SIZE, entry.as_bytes_mut(), &device)?;
+                disk_inode.write_at(index * DIRENT_SIZE, entry.as_bytes(), &device)
+            })??;
+        }
+        self.resize(&mut fs, last * DIRENT_SIZE)?;
+
+        target.resize(&mut fs, 0)?;
+        fs.dealloc_inode(inode_id)?;
+        sync_all()?;
+
+        Ok(())
+    }
+
+    pub fn read_at(&self, offset: usize, buf: &mut [u8]) -> Result<usize> {
+        let fs = self.fs.lock();
+        let device = fs.device.clone();
+        self.read_disk_inode(&fs, |disk_inode| disk_inode.read_at(offset, buf, &device))?
+    }
+
+    /// Writes `buf` at `offset`, growing the file as needed.
+    pub fn write_at(&self, offset: usize, buf: &[u8]) -> Result<usize> {
+        let mut fs = self.fs.lock();
+        let end = offset.checked_add(buf.len()).ok_or(Error::FileTooLarge)?;
+        let size = self.read_disk_inode(&fs, |disk_inode| disk_inode.size as usize)?;
+        if end > size {
+            self.resize(&mut fs, end)?;
+        }
+
+        let device = fs.device.clone();
+        let written =
+            self.modify_disk_inode(&fs, |disk_inode| disk_inode.write_at(offset, buf, &device))??;
+        sync_all()?;
+
+        Ok(written)
+    }
+
+    /// Sets the size to `size`, zero-filling when growing.
+    pub fn truncate(&self, size: usize) -> Result<()> {
+        let mut fs = self.fs.lock();
+        self.resize(&mut fs, size)?;
+        sync_all()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{device::BlockDevice, layout::MAX_FILE_SIZE, BLOCK_SIZE};
+    use alloc::vec;
+
+    struct RamDisk(Mutex<Vec<[u8; BLOCK_SIZE]>>);
+
+    impl BlockDevice for RamDisk {
+        fn read_block(&self, id: usize, data: &mut [u8]) -> Result<()> {
+            data.copy_from_slice(&self.0.lock()[id]);
+            Ok(())
+        }
+
+        fn write_block(&self, id: usize, data: &[u8]) -> Result<()> {
+            self.0.lock()[id].copy_from_slice(data);
+            Ok(())
+        }
+    }
+
+    const TOTAL_BLOCKS: u32 = 8192;
+
+    fn ram_disk() -> Arc<dyn BlockDevice> {
+        Arc::new(RamDisk(Mutex::new(vec![
+            [0; BLOCK_SIZE];
+            TOTAL_BLOCKS as usize
+        ])))
+    }
+
+    fn pattern(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 7 + i / 251) as u8).collect()
+    }
+
+    #[test]
+    fn write_and_read_across_indirect_blocks() {
+        let fs = FileSystem::create(ram_disk(), TOTAL_BLOCKS, 1).unwrap();
+        let root = FileSystem::root_inode(&fs);
+        let file = root.create("big", InodeKind::File).unwrap();
+
+        // past the direct blocks and into the doubly indirect ones
+        let data = pattern(200 * BLOCK_SIZE + 123);
+        assert_eq!(file.write_at(0, &data).unwrap(), data.len());
+        assert_eq!(file.size().unwrap(), data.len());
+
+        let mut buf = vec![0; data.len() + 10];
+        assert_eq!(file.read_at(0, &mut buf).unwrap(), data.len());
+        assert_eq!(&buf[..data.len()], &data[..]);
+
+        let mut buf = [0; 700];
+        assert_eq!(file.read_at(BLOCK_SIZE * 28 - 100, &mut buf).unwrap(), 700);
+        assert_eq!(
+            &buf[..],
+            &data[BLOCK_SIZE * 28 - 100..BLOCK_SIZE * 28 + 600]
+        );
+    }
+
+    #[test]
+    fn truncate_and_unlink_free_blocks() {
+        let fs = FileSystem::create(ram_disk(), TOTAL_BLOCKS, 1).unwrap();
+        let root = FileSystem::root_inode(&fs);
+        let free = fs.lock().free_blocks().unwrap();
+
+        let file = root.create("file", InodeKind::File).unwrap();
+        file.write_at(0, &pattern(300 * BLOCK_SIZE)).unwrap();
+
+        file.truncate(10).unwrap();
+        file.truncate(2 * BLOCK_SIZE).unwrap();
+        let mut buf = vec![0; 2 * BLOCK_SIZE];
+        file.read_at(0, &mut buf).unwrap();
+        assert_eq!(&buf[..10], &pattern(10)[..]);
+        assert!(buf[10..].iter().all(|&c| c == 0));
+
+        root.unlink("file").unwrap();
+        assert!(matches!(root.lookup("file"), Err(Error::NotFound)));
+        assert_eq!(fs.lock().free_blocks().unwrap(), free);
+
+        assert!(matches!(
+            file.truncate(MAX_FILE_SIZE + 1),
+            Err(Error::FileTooLarge)
+        ));
+    }
+
+    #[test]
+    fn directories() {
+        let fs = FileSystem::create(ram_disk(), TOTAL_BLOCKS, 1).unwrap();
+        let root = FileSystem::root_inode(&fs);
+
+        let bin = root.create("bin", InodeKind::Directory).unwrap();
+        for i in 0..40 {
+            bin.create(&alloc::format!("app{i}"), InodeKind::File)
+                .unwrap();
+        }
+        root.create("hello", InodeKind::File).unwrap();
+
+        assert_eq!(root.ls().unwrap(), ["bin", "hello"]);
+        assert_eq!(bin.ls().unwrap().len(), 40);
+        assert!(bin.is_dir().unwrap());
+        assert!(matches!(
+            root.create("bin", InodeKind::File),
+            Err(Error::AlreadyExists)
+        ));
+        assert!(matches!(
+            root.create(&"x".repeat(28), InodeKind::File),
+            Err(Error::NameTooLong)
+        ));
+        assert!(matches!(root.unlink("bin"), Err(Error::DirectoryNotEmpty)));
+
+        bin.unlink("app3").unwrap();
+        let names = bin.ls().unwrap();
+        assert_eq!(names.len(), 39);
+        assert!(!names.iter().any(|name| name == "app3"));
+        assert!(bin.lookup("app39").is_ok());
+    }
+
+    #[test]
+    fn reopen() {
+        let device = ram_disk();
+        {
+            let fs = FileSystem::create(device.clone(), TOTAL_BLOCKS, 1).unwrap();
+            let root = FileSystem::root_inode(&fs);
+            let file = root.create("persist", InodeKind::File).unwrap();
+            file.write_at(0, b"hello los-fs").unwrap();
+        }
+
+        let fs = FileSystem::open(device).unwrap();
+        let file = FileSystem::root_inode(&fs).lookup("persist").unwrap();
+        let mut buf = [0; 12];
+        file.read_at(0, &mut buf).unwrap();
+        assert_eq!(&buf, b"hello los-fs");
+
+        assert!(matches!(
+            FileSystem::open(ram_disk()),
+            Err(Error::InvalidSuperBlock)
+        ));
+    }
+}
@@ -0,0 +1,159 @@
+use core::{arch::asm, mem, slice, str};
+
+use crate::{
+    mm::{MemorySpace, UserPtr},
+    println,
+};
+
+const MAX_DEPTH: usize = 32;
+
+/// Symbol table written by `toolbox kernel symbols` from a previous build of
+/// the kernel. It is linked after everything else, so embedding it does not
+/// move any code.
+#[used]
+#[link_section = ".ksymtab"]
+static KSYMTAB: [u8; include_bytes!("ksymtab.bin").len()] = *include_bytes!("ksymtab.bin");
+
+const SYMTAB_ENTRY_SIZE: usize = 24;
+
+/// Finds the function containing `addr` and the offset into it.
+fn lookup(addr: usize) -> Option<(&'static str, usize)> {
+    extern "C" {
+        fn sksymtab();
+        fn eksymtab();
+    }
+
+    // read through the linker symbols, the table is replaced after the
+    // code is built
+    let symtab = unsafe {
+        slice::from_raw_parts(
+            sksymtab as usize as *const u8,
+            eksymtab as usize - sksymtab as usize,
+        )
+    };
+    let read_u64 = |offset: usize| -> Option<u64> {
+        Some(u64::from_le_bytes(
+            symtab.get(offset..offset + 8)?.try_into().ok()?,
+        ))
+    };
+    let read_u32 = |offset: usize| -> Option<u32> {
+        Some(u32::from_le_bytes(
+            symtab.get(offset..offset + 4)?.try_into().ok()?,
+        ))
+    };
+
+    let count = read_u64(0)? as usize;
+    let names_offset = 8 + count * SYMTAB_ENTRY_SIZE;
+
+    // entries are sorted by address, find the last one starting at or
+    // below addr
+    let (mut low, mut high) = (0, count);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if read_u64(8 + mid * SYMTAB_ENTRY_SIZE)? as usize <= addr {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+
+    let entry = 8 + low.checked_sub(1)? * SYMTAB_ENTRY_SIZE;
+    let start = read_u64(entry)? as usize;
+    let size = read_u64(entry + 8)? as usize;
+    if addr >= start + size {
+        return None;
+    }
+
+    let name_offset = names_offset + read_u32(entry + 16)? as usize;
+    let name_len = read_u32(entry + 20)? as usize;
+    let name = str::from_utf8(symtab.get(name_offset..name_offset + name_len)?).ok()?;
+
+    Some((name, addr - start))
+}
+
+/// Walks the frame pointer chain from `fp`, calling `f` with each return
+/// address. A frame keeps the return address at `fp - 8` and the caller's
+/// frame pointer at `fp - 16`; the chain ends at a zero frame pointer, which
+/// is what boot code and new tasks start with.
+fn walk(fp: usize, mut read: impl FnMut(usize) -> Option<usize>, mut f: impl FnMut(usize)) {
+    let mut fp = fp;
+    for _ in 1..MAX_DEPTH {
+        if fp == 0 || fp & (mem::size_of::<usize>() - 1) != 0 {
+            break;
+        }
+
+        let (Some(ra), Some(caller_fp)) = (read(fp - 8), read(fp - 16)) else {
+            break;
+        };
+        if ra == 0 {
+            break;
+        }
+        f(ra);
+
+        // stacks grow down, anything else is a broken chain
+        if caller_fp <= fp {
+            break;
+        }
+        fp = caller_fp;
+    }
+}
+
+fn print_kernel_frame(depth: usize, addr: usize) {
+    match lookup(addr) {
+        Some((name, offset)) => {
+            println!("  #{:<2} {:#x} {}+{:#x}", depth, addr, name, offset);
+        }
+        None => {
+            println!("  #{:<2} {:#x}", depth, addr);
+        }
+    }
+}
+
+/// Prints the kernel backtrace from `pc` and the frame pointer `fp`.
+pub fn print(pc: usize, fp: usize) {
+    println!("backtrace:");
+    print_kernel_frame(0, pc);
+
+    let mut depth = 1;
+    walk(
+        fp,
+        |addr| Some(unsafe { *(addr as *const usize) }),
+        |ra| {
+            print_kernel_frame(depth, ra);
+            depth += 1;
+        },
+    );
+}
+
+/// Prints the backtrace of the caller.
+#[inline(never)]
+pub fn print_current() {
+    let (pc, fp): (usize, usize);
+    unsafe {
+        asm!(
+            "auipc {pc}, 0",
+            "mv {fp}, s0",
+            pc = out(reg) pc,
+            fp = out(reg) fp,
+        );
+    }
+
+    print(pc, fp);
+}
+
+/// Prints the backtrace of a user task. User programs are not symbolized,
+/// use `addr2line -e <program>` on the addresses.
+pub fn print_user(pc: usize, fp: usize, mem_space: &mut MemorySpace) {
+    println!("user backtrace:");
+    println!("  #0  {:#x}", pc);
+
+    let mut depth = 1;
+    walk(
+        fp,
+        |addr| UserPtr::from(addr as *const usize).read(mem_space).ok(),
+        |ra| {
+            println!("  #{:<2} {:#x}", depth, ra);
+            depth += 1;
+        },
+    );
+}
@@ -1,8 +1,20 @@
-pub const KERNEL_HEAP_SIZE: usize = 1 << 20;
+pub const KERNEL_HEAP_SIZE: usize = 1 << 23;
 pub const USER_STACK_SIZE: usize = 1 << 16;
 pub const KERNEL_STACK_SIZE: usize = 1 << 16;
 pub const GUARD_PAGE_COUNT: usize = 1;
 
 pub const MAX_PID: usize = 1 << 16;
+pub const MAX_THREADS: usize = 64;
+pub const MAX_FD_COUNT: usize = 128;
 
 pub const INIT_PROC_NAME: &str = "init";
+pub const MAX_USER_STR_LEN: usize = 1 << 12;
+pub const MAX_EXEC_ARGS: usize = 64;
+pub const MAX_EXEC_ARGS_SIZE: usize = USER_STACK_SIZE / 4;
+/// Programs are read whole into the kernel heap before loading.
+pub const MAX_EXEC_FILE_SIZE: usize = 1 << 21;
+
+pub const MAX_HARTS: usize = 8;
+pub const BOOT_STACK_SIZE: usize = 1 << 18;
+/// Per-hart stack for reporting a kernel stack overflow, a power of two.
+pub const KERNEL_TRAP_STACK_SIZE: usize = 1 << 14;
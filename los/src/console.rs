@@ -1,21 +1,40 @@
 use core::fmt::Write;
 
-use crate::sbi;
+use spin::Mutex;
+
+use crate::{drivers::uart, sbi};
 
 struct Stdout;
 
+fn put_byte(c: u8) {
+    if uart::is_ready() {
+        uart::write_byte(c);
+    } else {
+        sbi::console_write_byte(c as usize);
+    }
+}
+
 impl Write for Stdout {
     fn write_str(&mut self, s: &str) -> core::fmt::Result {
         for &c in s.as_bytes() {
-            sbi::console_write_byte(c as usize);
+            put_byte(c);
         }
 
         Ok(())
     }
 }
 
+static STDOUT: Mutex<Stdout> = Mutex::new(Stdout);
+
 pub fn print(args: core::fmt::Arguments) {
-    Stdout.write_fmt(args).unwrap();
+    STDOUT.lock().write_fmt(args).unwrap();
+}
+
+pub fn write_bytes(bytes: &[u8]) {
+    let _stdout = STDOUT.lock();
+    for &c in bytes {
+        put_byte(c);
+    }
 }
 
 #[macro_export]
@@ -1,6 +1,10 @@
 use core::ops::Range;
 
-use alloc::format;
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
 use dtb_walker::{Dtb, HeaderError, Property, WalkOperation};
 use lazy_static::lazy_static;
 use spin::Mutex;
@@ -13,6 +17,47 @@ lazy_static! {
 pub struct DeviceInfo {
     pub memory: Range<usize>,
     pub cpu_time_base_freq: usize,
+    pub harts: Vec<usize>,
+    pub plic: Option<PlicInfo>,
+    /// Device nodes under `/soc`, in device tree order.
+    pub devices: Vec<DeviceNode>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PlicInfo {
+    pub reg: Range<usize>,
+    pub ndev: usize,
+    /// The S-mode context of each hart, as `(hartid, context)`.
+    pub contexts: Vec<(usize, usize)>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DeviceNode {
+    pub name: String,
+    pub reg: Range<usize>,
+    pub interrupts: Vec<usize>,
+}
+
+impl DeviceInfo {
+    /// Device nodes whose name starts with `prefix`, like `serial@`.
+    pub fn devices_named<'a>(&'a self, prefix: &'a str) -> impl Iterator<Item = &'a DeviceNode> {
+        self.devices
+            .iter()
+            .filter(move |device| device.name.starts_with(prefix))
+    }
+}
+
+/// Supervisor external interrupt, as listed in `interrupts-extended`.
+const IRQ_S_EXT: u32 = 9;
+
+fn is_plic(name: &str) -> bool {
+    name.starts_with("plic@") || name.starts_with("interrupt-controller@")
+}
+
+fn be_u32s(value: &[u8]) -> impl Iterator<Item = u32> + '_ {
+    value
+        .chunks_exact(4)
+        .map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
 }
 
 pub fn init(device_tree_pa: usize) {
@@ -27,16 +72,86 @@ pub fn init(device_tree_pa: usize) {
     .map_err(|e| format!("verify header failed: {e:?}"))
     .unwrap();
 
+    // (phandle, irq) pairs of the PLIC, one for each context
+    let mut plic_interrupts = Vec::new();
+
     dtb.walk(|path, obj| match obj {
         dtb_walker::DtbObj::SubNode { name } => {
             let name = core::str::from_utf8(name).unwrap();
+            if name == "soc" {
+                return WalkOperation::StepInto;
+            }
+
+            if path.last() == b"soc" {
+                let mut info = DEVICE_INFO.lock();
+                if is_plic(name) {
+                    info.plic = Some(PlicInfo::default());
+                }
+                info.devices.push(DeviceNode {
+                    name: name.to_string(),
+                    ..DeviceNode::default()
+                });
+                return WalkOperation::StepInto;
+            }
+
             if !name.starts_with("memory") && !name.starts_with("cpu") {
                 return WalkOperation::StepOver;
             }
+
+            if let Some(hartid) = name.strip_prefix("cpu@") {
+                if let Ok(hartid) = usize::from_str_radix(hartid, 16) {
+                    DEVICE_INFO.lock().harts.push(hartid);
+                }
+            }
             WalkOperation::StepInto
         }
         dtb_walker::DtbObj::Property(mut property) => {
             let name = core::str::from_utf8(path.last()).unwrap();
+
+            let mut info = DEVICE_INFO.lock();
+            let is_device = info
+                .devices
+                .last()
+                .is_some_and(|device| device.name == name);
+            if is_device {
+                match &mut property {
+                    Property::Reg(reg) => {
+                        let reg = reg.next().unwrap_or_default();
+                        if is_plic(name) {
+                            if let Some(plic) = info.plic.as_mut() {
+                                plic.reg = reg.clone();
+                            }
+                        }
+                        if let Some(device) = info.devices.last_mut() {
+                            device.reg = reg;
+                        }
+                    }
+                    Property::General { name: prop, value } => {
+                        match prop.as_str().unwrap_or_default() {
+                            "interrupts" => {
+                                if let Some(device) = info.devices.last_mut() {
+                                    device.interrupts =
+                                        be_u32s(value).map(|irq| irq as usize).collect();
+                                }
+                            }
+                            "riscv,ndev" if is_plic(name) => {
+                                if let Some(plic) = info.plic.as_mut() {
+                                    plic.ndev = be_u32s(value).next().unwrap_or(0) as usize;
+                                }
+                            }
+                            "interrupts-extended" if is_plic(name) => {
+                                plic_interrupts = be_u32s(value).collect();
+                            }
+                            _ => {}
+                        }
+                    }
+                    _ => {}
+                }
+
+                return WalkOperation::StepOver;
+            }
+            drop(info);
+
             if name.starts_with("memory") {
                 if let Property::Reg(reg) = &mut property {
                     let mut info = DEVICE_INFO.lock();
@@ -63,6 +178,30 @@ pub fn init(device_tree_pa: usize) {
             WalkOperation::StepOver
         }
     });
+
+    // each hart's interrupt controller appears in the PLIC's list in cpu
+    // order, so the n-th distinct phandle belongs to the n-th hart
+    let mut info = DEVICE_INFO.lock();
+    let harts = info.harts.clone();
+    if let Some(plic) = info.plic.as_mut() {
+        let mut phandles = Vec::new();
+        for (context, pair) in plic_interrupts.chunks_exact(2).enumerate() {
+            let (phandle, irq) = (pair[0], pair[1]);
+            let index = match phandles.iter().position(|&p| p == phandle) {
+                Some(index) => index,
+                None => {
+                    phandles.push(phandle);
+                    phandles.len() - 1
+                }
+            };
+
+            if irq == IRQ_S_EXT {
+                if let Some(&hartid) = harts.get(index) {
+                    plic.contexts.push((hartid, context));
+                }
+            }
+        }
+    }
 }
 
 pub fn get_device_info() -> DeviceInfo {
@@ -0,0 +1,13 @@
+pub mod plic;
+pub mod uart;
+pub mod virtio_blk;
+
+pub fn init() {
+    plic::init();
+    uart::init();
+    virtio_blk::init();
+}
+
+pub fn init_hart() {
+    plic::init_hart();
+}
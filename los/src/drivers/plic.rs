@@ -0,0 +1,167 @@
+use alloc::{collections::btree_map::BTreeMap, format, sync::Arc};
+use core::ops::Range;
+use lazy_static::lazy_static;
+use riscv::register::sie;
+use spin::Mutex;
+
+use crate::{config::MAX_HARTS, device_tree, error, hart, mm, println};
+
+const PRIORITY_OFFSET: usize = 0;
+const ENABLE_OFFSET: usize = 0x2000;
+const ENABLE_STRIDE: usize = 0x80;
+const CONTEXT_OFFSET: usize = 0x20_0000;
+const CONTEXT_STRIDE: usize = 0x1000;
+const THRESHOLD: usize = 0;
+const CLAIM_COMPLETE: usize = 4;
+
+pub type IrqHandler = Arc<dyn Fn() + Send + Sync>;
+
+lazy_static! {
+    static ref PLIC: Option<Plic> = Plic::from_device_tree();
+    static ref HANDLERS: Mutex<BTreeMap<usize, IrqHandler>> = Mutex::new(BTreeMap::new());
+}
+
+/// The platform-level interrupt controller, routing device interrupts to
+/// the S-mode context of each hart.
+struct Plic {
+    reg: Range<usize>,
+    ndev: usize,
+    contexts: [Option<usize>; MAX_HARTS],
+}
+
+impl Plic {
+    fn from_device_tree() -> Option<Self> {
+        let plic = device_tree::get_device_info().plic?;
+
+        let mut contexts = [None; MAX_HARTS];
+        for (hartid, context) in plic.contexts {
+            if let Some(slot) = contexts.get_mut(hartid) {
+                *slot = Some(context);
+            }
+        }
+
+        Some(Self {
+            reg: plic.reg,
+            ndev: plic.ndev,
+            contexts,
+        })
+    }
+
+    fn reg(&self, offset: usize) -> *mut u32 {
+        (self.reg.start + offset) as *mut u32
+    }
+
+    fn set_priority(&self, irq: usize, priority: u32) {
+        unsafe { self.reg(PRIORITY_OFFSET + irq * 4).write_volatile(priority) };
+    }
+
+    fn enable(&self, context: usize, irq: usize) {
+        let reg = self.reg(ENABLE_OFFSET + context * ENABLE_STRIDE + irq / 32 * 4);
+        unsafe { reg.write_volatile(reg.read_volatile() | 1 << (irq % 32)) };
+    }
+
+    fn set_threshold(&self, context: usize, threshold: u32) {
+        let reg = self.reg(CONTEXT_OFFSET + context * CONTEXT_STRIDE + THRESHOLD);
+        unsafe { reg.write_volatile(threshold) };
+    }
+
+    fn claim(&self, context: usize) -> usize {
+        let reg = self.reg(CONTEXT_OFFSET + context * CONTEXT_STRIDE + CLAIM_COMPLETE);
+        unsafe { reg.read_volatile() as usize }
+    }
+
+    fn complete(&self, context: usize, irq: usize) {
+        let reg = self.reg(CONTEXT_OFFSET + context * CONTEXT_STRIDE + CLAIM_COMPLETE);
+        unsafe { reg.write_volatile(irq as u32) };
+    }
+
+    fn current_context(&self) -> Option<usize> {
+        self.contexts.get(hart::hart_id()).copied().flatten()
+    }
+}
+
+/// Maps the PLIC registers and sets up the boot hart.
+pub fn init() {
+    let Some(plic) = PLIC.as_ref() else {
+        println!("[PLIC] not found in device tree, external interrupts disabled");
+        return;
+    };
+
+    mm::map_mmio(&plic.reg).expect("map plic registers must succeed");
+    println!("[PLIC] {:#x}, {} sources", plic.reg.start, plic.ndev);
+
+    init_hart();
+}
+
+/// Lets interrupts of any priority through to this hart's S-mode context.
+pub fn init_hart() {
+    let Some(plic) = PLIC.as_ref() else {
+        return;
+    };
+    let Some(context) = plic.current_context() else {
+        println!("[PLIC] no S-mode context for hart {}", hart::hart_id());
+        return;
+    };
+
+    plic.set_threshold(context, 0);
+    unsafe { sie::set_sext() };
+}
+
+/// Attaches `handler` to the interrupt source `irq` and enables the source
+/// on every hart. The handler runs in the trap handler with interrupts
+/// disabled, so it must not block.
+pub fn register_irq(irq: usize, handler: impl Fn() + Send + Sync + 'static) -> error::Result<()> {
+    let plic = PLIC
+        .as_ref()
+        .ok_or(error::KernelError::Irq("no plic".into()))?;
+    if irq == 0 || irq > plic.ndev {
+        return Err(error::KernelError::Irq(format!(
+            "irq {irq} out of range 1..={}",
+            plic.ndev
+        )));
+    }
+
+    let mut handlers = HANDLERS.lock();
+    if handlers.contains_key(&irq) {
+        return Err(error::KernelError::Irq(format!(
+            "irq {irq} already registered"
+        )));
+    }
+    handlers.insert(irq, Arc::new(handler));
+
+    plic.set_priority(irq, 1);
+    plic.contexts
+        .iter()
+        .flatten()
+        .for_each(|&context| plic.enable(context, irq));
+
+    Ok(())
+}
+
+/// Claims pending interrupts of this hart, runs their handlers and signals
+/// completion.
+pub fn handle_interrupt() {
+    let Some(plic) = PLIC.as_ref() else {
+        return;
+    };
+    let Some(context) = plic.current_context() else {
+        return;
+    };
+
+    loop {
+        let irq = plic.claim(context);
+        if irq == 0 {
+            break;
+        }
+
+        let handler = HANDLERS.lock().get(&irq).cloned();
+        match handler {
+            Some(handler) => handler(),
+            None => {
+                println!("[PLIC] unhandled irq {}", irq);
+            }
+        }
+
+        plic.complete(context, irq);
+    }
+}
@@ -0,0 +1,173 @@
+use alloc::{collections::vec_deque::VecDeque, string::ToString};
+use core::{
+    ops::Range,
+    sync::atomic::{AtomicBool, Ordering},
+};
+use lazy_static::lazy_static;
+use spin::Mutex;
+
+use crate::{device_tree, error, mm, println, task::wait_queue::WaitQueue};
+
+use super::plic;
+
+const RBR: usize = 0;
+const THR: usize = 0;
+const IER: usize = 1;
+const FCR: usize = 2;
+const LCR: usize = 3;
+const MCR: usize = 4;
+const LSR: usize = 5;
+
+const IER_RX_AVAILABLE: u8 = 1 << 0;
+const FCR_ENABLE_AND_CLEAR: u8 = 0b111;
+const LCR_8N1: u8 = 0b11;
+const MCR_DTR_RTS_OUT2: u8 = 0b1011;
+const LSR_DATA_READY: u8 = 1 << 0;
+const LSR_THR_EMPTY: u8 = 1 << 5;
+
+const RX_BUF_SIZE: usize = 1 << 12;
+
+lazy_static! {
+    static ref UART: Option<Ns16550> = device_tree::get_device_info()
+        .devices_named("serial@")
+        .next()
+        .map(|device| Ns16550 {
+            reg: device.reg.clone(),
+            irq: device.interrupts.first().copied(),
+        });
+}
+
+/// Set once the registers are mapped, console output goes through SBI
+/// before that.
+static READY: AtomicBool = AtomicBool::new(false);
+/// Set once input interrupts are routed to `handle_irq`.
+static RX_IRQ: AtomicBool = AtomicBool::new(false);
+
+static RX_BUFFER: Mutex<VecDeque<u8>> = Mutex::new(VecDeque::new());
+/// Readers of the console wait here for input.
+static RX_READABLE: WaitQueue = WaitQueue::new();
+
+struct Ns16550 {
+    reg: Range<usize>,
+    irq: Option<usize>,
+}
+
+impl Ns16550 {
+    fn read_reg(&self, reg: usize) -> u8 {
+        unsafe { ((self.reg.start + reg) as *const u8).read_volatile() }
+    }
+
+    fn write_reg(&self, reg: usize, value: u8) {
+        unsafe { ((self.reg.start + reg) as *mut u8).write_volatile(value) };
+    }
+
+    fn init(&self) {
+        self.write_reg(IER, 0);
+        self.write_reg(FCR, FCR_ENABLE_AND_CLEAR);
+        self.write_reg(LCR, LCR_8N1);
+        self.write_reg(MCR, MCR_DTR_RTS_OUT2);
+        self.write_reg(IER, IER_RX_AVAILABLE);
+    }
+
+    fn put(&self, c: u8) {
+        while self.read_reg(LSR) & LSR_THR_EMPTY == 0 {
+            core::hint::spin_loop();
+        }
+        self.write_reg(THR, c);
+    }
+
+    fn get(&self) -> Option<u8> {
+        if self.read_reg(LSR) & LSR_DATA_READY == 0 {
+            return None;
+        }
+
+        Some(self.read_reg(RBR))
+    }
+}
+
+/// Maps the UART from `/soc/serial` and feeds its input interrupt into the
+/// receive buffer. Without one, the console keeps using SBI.
+pub fn init() {
+    let Some(uart) = UART.as_ref() else {
+        println!("[UART] not found in device tree, using SBI console");
+        return;
+    };
+
+    mm::map_mmio(&uart.reg).expect("map uart registers must succeed");
+    uart.init();
+
+    match uart.irq {
+        Some(irq) => match plic::register_irq(irq, handle_irq) {
+            Ok(()) => RX_IRQ.store(true, Ordering::SeqCst),
+            Err(err) => {
+                println!("[UART] register irq {} failed: {}", irq, err);
+            }
+        },
+        None => {
+            println!("[UART] no interrupt, polling SBI for input");
+        }
+    }
+
+    READY.store(true, Ordering::SeqCst);
+    println!("[UART] {:#x}", uart.reg.start);
+}
+
+pub fn is_ready() -> bool {
+    READY.load(Ordering::SeqCst)
+}
+
+/// Whether input arrives through `read`.
+pub fn has_rx_irq() -> bool {
+    RX_IRQ.load(Ordering::SeqCst)
+}
+
+pub fn write_byte(c: u8) {
+    if let Some(uart) = UART.as_ref() {
+        uart.put(c);
+    }
+}
+
+/// Blocks until input is available and reads as much of it as fits in
+/// `buf`.
+pub fn read(buf: &mut [u8]) -> error::Result<usize> {
+    if buf.is_empty() {
+        return Ok(0);
+    }
+
+    // drain under the same lock as the check, so a concurrent reader can
+    // not empty the buffer in between and leave this one with 0 (EOF)
+    let mut read_len = 0;
+    let completed = RX_READABLE.wait_until(|| {
+        let mut rx_buffer = RX_BUFFER.lock();
+        read_len = buf.len().min(rx_buffer.len());
+        for (dst, src) in buf.iter_mut().zip(rx_buffer.drain(..read_len)) {
+            *dst = src;
+        }
+        read_len > 0
+    });
+    if !completed {
+        return Err(error::KernelError::Interrupted(
+            "read interrupted by signal".to_string(),
+        ));
+    }
+
+    Ok(read_len)
+}
+
+fn handle_irq() {
+    let Some(uart) = UART.as_ref() else {
+        return;
+    };
+
+    {
+        let mut rx_buffer = RX_BUFFER.lock();
+        while let Some(c) = uart.get() {
+            // input is dropped while nobody reads it
+            if rx_buffer.len() < RX_BUF_SIZE {
+                rx_buffer.push_back(c);
+            }
+        }
+    }
+
+    RX_READABLE.wake_all();
+}
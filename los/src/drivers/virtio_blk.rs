@@ -0,0 +1,427 @@
+use alloc::{format, sync::Arc};
+use core::{
+    hint,
+    mem::size_of,
+    ptr,
+    sync::atomic::{fence, Ordering},
+};
+use los_fs::{device::BlockDevice, BLOCK_SIZE};
+use spin::Mutex;
+
+use crate::{
+    device_tree,
+    mm::{self, address::PAGE_SIZE, ContiguousFrames},
+    println,
+    task::{processor, wait_queue::WaitQueue},
+};
+
+use super::plic;
+
+const MAGIC_VALUE: usize = 0x000;
+const VERSION: usize = 0x004;
+const DEVICE_ID: usize = 0x008;
+const DEVICE_FEATURES_SEL: usize = 0x014;
+const DRIVER_FEATURES: usize = 0x020;
+const DRIVER_FEATURES_SEL: usize = 0x024;
+const GUEST_PAGE_SIZE: usize = 0x028;
+const QUEUE_SEL: usize = 0x030;
+const QUEUE_NUM_MAX: usize = 0x034;
+const QUEUE_NUM: usize = 0x038;
+const QUEUE_ALIGN: usize = 0x03c;
+const QUEUE_PFN: usize = 0x040;
+const QUEUE_READY: usize = 0x044;
+const QUEUE_NOTIFY: usize = 0x050;
+const INTERRUPT_STATUS: usize = 0x060;
+const INTERRUPT_ACK: usize = 0x064;
+const STATUS: usize = 0x070;
+const QUEUE_DESC_LOW: usize = 0x080;
+const QUEUE_DESC_HIGH: usize = 0x084;
+const QUEUE_DRIVER_LOW: usize = 0x090;
+const QUEUE_DRIVER_HIGH: usize = 0x094;
+const QUEUE_DEVICE_LOW: usize = 0x0a0;
+const QUEUE_DEVICE_HIGH: usize = 0x0a4;
+const CONFIG_CAPACITY: usize = 0x100;
+
+const VIRTIO_MAGIC: u32 = 0x7472_6976;
+const VIRTIO_ID_BLOCK: u32 = 2;
+const VERSION_LEGACY: u32 = 1;
+const VERSION_MODERN: u32 = 2;
+/// VIRTIO_F_VERSION_1, bit 32 of the feature bits.
+const FEATURE_VERSION_1: u32 = 1 << 0;
+
+const STATUS_ACKNOWLEDGE: u32 = 1;
+const STATUS_DRIVER: u32 = 2;
+const STATUS_DRIVER_OK: u32 = 4;
+const STATUS_FEATURES_OK: u32 = 8;
+
+const DESC_F_NEXT: u16 = 1;
+const DESC_F_WRITE: u16 = 2;
+
+const BLK_T_IN: u32 = 0;
+const BLK_T_OUT: u32 = 1;
+const BLK_S_OK: u8 = 0;
+
+const QUEUE_SIZE: usize = 16;
+/// Every request takes a header, a data and a status descriptor.
+const SLOTS: usize = QUEUE_SIZE / 3;
+const SECTOR_SIZE: usize = 512;
+
+// one page of descriptors and the available ring, one of the used ring,
+// one of data buffers and one of request headers and statuses. The used
+// ring is page aligned as the legacy interface wants.
+const QUEUE_PAGES: usize = 4;
+const AVAIL_OFFSET: usize = QUEUE_SIZE * size_of::<Descriptor>();
+const USED_OFFSET: usize = PAGE_SIZE;
+const DATA_OFFSET: usize = 2 * PAGE_SIZE;
+const HEADER_OFFSET: usize = 3 * PAGE_SIZE;
+const HEADER_STRIDE: usize = 32;
+const STATUS_OFFSET: usize = size_of::<BlkReqHeader>();
+
+#[repr(C)]
+struct Descriptor {
+    addr: u64,
+    len: u32,
+    flags: u16,
+    next: u16,
+}
+
+#[repr(C)]
+struct BlkReqHeader {
+    kind: u32,
+    reserved: u32,
+    sector: u64,
+}
+
+#[repr(C)]
+struct UsedElem {
+    id: u32,
+    len: u32,
+}
+
+static BLOCK_DEVICE: Mutex<Option<Arc<VirtioBlk>>> = Mutex::new(None);
+
+struct Inner {
+    free: [bool; SLOTS],
+    done: [bool; SLOTS],
+    avail_idx: u16,
+    last_used_idx: u16,
+}
+
+/// A virtio block device on the MMIO transport. Requests go through a
+/// single virtqueue, and data is bounced through buffers the driver owns,
+/// since callers' buffers may live on a task's kernel stack, which is not
+/// identity mapped.
+pub struct VirtioBlk {
+    regs: usize,
+    queue: ContiguousFrames,
+    capacity: u64,
+    inner: Mutex<Inner>,
+    /// Requests wait here for a free slot or for their completion.
+    wait_queue: WaitQueue,
+}
+
+impl VirtioBlk {
+    fn read_reg(&self, offset: usize) -> u32 {
+        unsafe { ((self.regs + offset) as *const u32).read_volatile() }
+    }
+
+    fn write_reg(&self, offset: usize, value: u32) {
+        unsafe { ((self.regs + offset) as *mut u32).write_volatile(value) };
+    }
+
+    /// Queue memory is identity mapped, so addresses are the same for the
+    /// kernel and the device.
+    fn queue_addr(&self, offset: usize) -> usize {
+        usize::from(mm::address::PhysAddr::from(self.queue.ppn)) + offset
+    }
+
+    fn probe(regs: usize) -> Option<u32> {
+        let read = |offset: usize| unsafe { ((regs + offset) as *const u32).read_volatile() };
+        if read(MAGIC_VALUE) != VIRTIO_MAGIC || read(DEVICE_ID) != VIRTIO_ID_BLOCK {
+            return None;
+        }
+
+        Some(read(VERSION))
+    }
+
+    fn new(regs: usize, version: u32) -> Result<Self, &'static str> {
+        let queue = mm::alloc_contiguous(QUEUE_PAGES).ok_or("no frames for virtqueue")?;
+        let mut blk = Self {
+            regs,
+            queue,
+            capacity: 0,
+            inner: Mutex::new(Inner {
+                free: [true; SLOTS],
+                done: [false; SLOTS],
+                avail_idx: 0,
+                last_used_idx: 0,
+            }),
+            wait_queue: WaitQueue::new(),
+        };
+
+        blk.write_reg(STATUS, 0);
+        let mut status = STATUS_ACKNOWLEDGE | STATUS_DRIVER;
+        blk.write_reg(STATUS, status);
+
+        // no optional features
+        blk.write_reg(DEVICE_FEATURES_SEL, 0);
+        blk.write_reg(DRIVER_FEATURES_SEL, 0);
+        blk.write_reg(DRIVER_FEATURES, 0);
+        if version == VERSION_MODERN {
+            blk.write_reg(DRIVER_FEATURES_SEL, 1);
+            blk.write_reg(DRIVER_FEATURES, FEATURE_VERSION_1);
+
+            status |= STATUS_FEATURES_OK;
+            blk.write_reg(STATUS, status);
+            if blk.read_reg(STATUS) & STATUS_FEATURES_OK == 0 {
+                return Err("features not accepted");
+            }
+        } else {
+            blk.write_reg(GUEST_PAGE_SIZE, PAGE_SIZE as u32);
+        }
+
+        blk.write_reg(QUEUE_SEL, 0);
+        let queue_num_max = blk.read_reg(QUEUE_NUM_MAX) as usize;
+        if queue_num_max < QUEUE_SIZE {
+            return Err("virtqueue too small");
+        }
+        blk.write_reg(QUEUE_NUM, QUEUE_SIZE as u32);
+
+        if version == VERSION_MODERN {
+            let set_addr = |low: usize, high: usize, addr: usize| {
+                blk.write_reg(low, addr as u32);
+                blk.write_reg(high, (addr >> 32) as u32);
+            };
+            set_addr(QUEUE_DESC_LOW, QUEUE_DESC_HIGH, blk.queue_addr(0));
+            set_addr(
+                QUEUE_DRIVER_LOW,
+                QUEUE_DRIVER_HIGH,
+                blk.queue_addr(AVAIL_OFFSET),
+            );
+            set_addr(
+                QUEUE_DEVICE_LOW,
+                QUEUE_DEVICE_HIGH,
+                blk.queue_addr(USED_OFFSET),
+            );
+            blk.write_reg(QUEUE_READY, 1);
+        } else {
+            blk.write_reg(QUEUE_ALIGN, PAGE_SIZE as u32);
+            blk.write_reg(QUEUE_PFN, blk.queue.ppn.0 as u32);
+        }
+
+        status |= STATUS_DRIVER_OK;
+        blk.write_reg(STATUS, status);
+
+        let capacity_low = blk.read_reg(CONFIG_CAPACITY) as u64;
+        let capacity_high = blk.read_reg(CONFIG_CAPACITY + 4) as u64;
+        blk.capacity = capacity_high << 32 | capacity_low;
+
+        Ok(blk)
+    }
+
+    /// Waits until `condition` holds. Tasks sleep until the interrupt
+    /// handler wakes them, boot code before the first task polls.
+    fn wait(&self, mut condition: impl FnMut(&mut Inner) -> bool) {
+        let mut check = || {
+            let mut inner = self.inner.lock();
+            self.collect_used(&mut inner);
+            condition(&mut inner)
+        };
+
+        if processor::has_current_task() {
+            self.wait_queue.wait_until_uninterruptible(check);
+        } else {
+            while !check() {
+                hint::spin_loop();
+            }
+        }
+    }
+
+    /// Marks the requests the device has finished as done.
+    fn collect_used(&self, inner: &mut Inner) {
+        let used = self.queue_addr(USED_OFFSET);
+        loop {
+            let used_idx = unsafe { ((used + 2) as *const u16).read_volatile() };
+            fence(Ordering::SeqCst);
+            if inner.last_used_idx == used_idx {
+                break;
+            }
+
+            let elem =
+                (used + 4 + (inner.last_used_idx as usize % QUEUE_SIZE) * 8) as *const UsedElem;
+            let head = unsafe { ptr::addr_of!((*elem).id).read_volatile() } as usize;
+            inner.done[head / 3] = true;
+            inner.last_used_idx = inner.last_used_idx.wrapping_add(1);
+        }
+    }
+
+    fn request(&self, id: usize, buf: *mut u8, write: bool) -> Result<(), u8> {
+        let mut slot = 0;
+        self.wait(|inner| match inner.free.iter().position(|&free| free) {
+            Some(free) => {
+                inner.free[free] = false;
+                slot = free;
+                true
+            }
+            None => false,
+        });
+
+        let header_addr = self.queue_addr(HEADER_OFFSET + slot * HEADER_STRIDE);
+        let status_addr = header_addr + STATUS_OFFSET;
+        let data_addr = self.queue_addr(DATA_OFFSET + slot * BLOCK_SIZE);
+        unsafe {
+            (header_addr as *mut BlkReqHeader).write_volatile(BlkReqHeader {
+                kind: if write { BLK_T_OUT } else { BLK_T_IN },
+                reserved: 0,
+                sector: (id * BLOCK_SIZE / SECTOR_SIZE) as u64,
+            });
+            (status_addr as *mut u8).write_volatile(u8::MAX);
+            if write {
+                ptr::copy_nonoverlapping(buf, data_addr as *mut u8, BLOCK_SIZE);
+            }
+        }
+
+        let head = slot * 3;
+        let descs = [
+            (header_addr, size_of::<BlkReqHeader>(), DESC_F_NEXT),
+            (
+                data_addr,
+                BLOCK_SIZE,
+                DESC_F_NEXT | if write { 0 } else { DESC_F_WRITE },
+            ),
+            (status_addr, 1, DESC_F_WRITE),
+        ];
+        for (i, (addr, len, flags)) in descs.into_iter().enumerate() {
+            let desc =
+                (self.queue_addr(0) + (head + i) * size_of::<Descriptor>()) as *mut Descriptor;
+            unsafe {
+                desc.write_volatile(Descriptor {
+                    addr: addr as u64,
+                    len: len as u32,
+                    flags,
+                    next: (head + i + 1) as u16,
+                })
+            };
+        }
+
+        {
+            let mut inner = self.inner.lock();
+            let avail = self.queue_addr(AVAIL_OFFSET);
+            let entry = avail + 4 + (inner.avail_idx as usize % QUEUE_SIZE) * 2;
+            unsafe { (entry as *mut u16).write_volatile(head as u16) };
+            fence(Ordering::SeqCst);
+            inner.avail_idx = inner.avail_idx.wrapping_add(1);
+            unsafe { ((avail + 2) as *mut u16).write_volatile(inner.avail_idx) };
+            fence(Ordering::SeqCst);
+            self.write_reg(QUEUE_NOTIFY, 0);
+        }
+
+        self.wait(|inner| inner.done[slot]);
+
+        let status = unsafe { (status_addr as *const u8).read_volatile() };
+        if !write {
+            unsafe { ptr::copy_nonoverlapping(data_addr as *const u8, buf, BLOCK_SIZE) };
+        }
+
+        {
+            let mut inner = self.inner.lock();
+            inner.done[slot] = false;
+            inner.free[slot] = true;
+        }
+        self.wait_queue.wake_all();
+
+        if status != BLK_S_OK {
+            return Err(status);
+        }
+        Ok(())
+    }
+
+    fn handle_irq(&self) {
+        let status = self.read_reg(INTERRUPT_STATUS);
+        self.write_reg(INTERRUPT_ACK, status);
+
+        self.collect_used(&mut self.inner.lock());
+        self.wait_queue.wake_all();
+    }
+
+    fn check_block(&self, id: usize, len: usize) -> los_fs::error::Result<()> {
+        if len != BLOCK_SIZE || ((id + 1) * BLOCK_SIZE / SECTOR_SIZE) as u64 > self.capacity {
+            return Err(los_fs::error::Error::ReadBlock(format!(
+                "bad block {id}, len {len}, capacity {} sectors",
+                self.capacity
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+impl BlockDevice for VirtioBlk {
+    fn read_block(&self, id: usize, data: &mut [u8]) -> los_fs::error::Result<()> {
+        self.check_block(id, data.len())?;
+        self.request(id, data.as_mut_ptr(), false)
+            .map_err(|status| {
+                los_fs::error::Error::ReadBlock(format!("read block {id} failed: {status}"))
+            })
+    }
+
+    fn write_block(&self, id: usize, data: &[u8]) -> los_fs::error::Result<()> {
+        self.check_block(id, data.len())?;
+        self.request(id, data.as_ptr() as *mut u8, true)
+            .map_err(|status| {
+                los_fs::error::Error::WriteBlock(format!("write block {id} failed: {status}"))
+            })
+    }
+}
+
+/// Sets up the first virtio block device in `/soc/virtio_mmio@*`.
+pub fn init() {
+    let info = device_tree::get_device_info();
+    for device in info.devices_named("virtio_mmio@") {
+        mm::map_mmio(&device.reg).expect("map virtio registers must succeed");
+        let Some(version) = VirtioBlk::probe(device.reg.start) else {
+            continue;
+        };
+        if version != VERSION_LEGACY && version != VERSION_MODERN {
+            println!("[VIRTIO] {}: unknown version {}", device.name, version);
+            continue;
+        }
+
+        let blk = match VirtioBlk::new(device.reg.start, version) {
+            Ok(blk) => Arc::new(blk),
+            Err(err) => {
+                println!("[VIRTIO] {}: init failed: {}", device.name, err);
+                continue;
+            }
+        };
+
+        let Some(&irq) = device.interrupts.first() else {
+            println!("[VIRTIO] {}: no interrupt", device.name);
+            continue;
+        };
+        let handler_blk = blk.clone();
+        if let Err(err) = plic::register_irq(irq, move || handler_blk.handle_irq()) {
+            println!(
+                "[VIRTIO] {}: register irq {} failed: {}",
+                device.name, irq, err
+            );
+            continue;
+        }
+
+        println!(
+            "[VIRTIO] block device at {:#x}, {} sectors",
+            device.reg.start, blk.capacity
+        );
+        *BLOCK_DEVICE.lock() = Some(blk);
+        return;
+    }
+
+    println!("[VIRTIO] no block device found");
+}
+
+pub fn block_device() -> Option<Arc<dyn BlockDevice>> {
+    BLOCK_DEVICE
+        .lock()
+        .clone()
+        .map(|blk| blk as Arc<dyn BlockDevice>)
+}
@@ -1,12 +1,32 @@
     .section .text.entry
     .globl _start
 _start:
-    la sp, boot_stack_top
+    # a0: hartid, a1: device tree pa
+    call _set_boot_stack
     call rust_main
 
+    .globl _start_secondary
+_start_secondary:
+    # a0: hartid, a1: opaque
+    call _set_boot_stack
+    call rust_main_secondary
+
+_set_boot_stack:
+    mv tp, a0
+    # ends the frame pointer chain for backtraces
+    li fp, 0
+    # every stack sits above its unmapped guard pages
+    la sp, boot_stack_lower_bound
+    addi t0, a0, 1
+    li t1, {guard_size} + {boot_stack_size}
+    mul t0, t0, t1
+    add sp, sp, t0
+    ret
+
     .section .bss.stack
+    .align 12
     .globl boot_stack_lower_bound
     .globl boot_stack_top
 boot_stack_lower_bound:
-    .space 1<<20
-boot_stack_top:
\ No newline at end of file
+    .space ({guard_size} + {boot_stack_size}) * {max_harts}
+boot_stack_top:
@@ -1,11 +1,13 @@
 use core::fmt::Debug;
 
-use alloc::string::String;
+use alloc::{format, string::String};
 
 #[derive(Debug)]
 #[allow(dead_code)]
 pub enum KernelError {
     Common(String),
+    InvalidArgument(String),
+    ArgumentListTooLong(String),
     InvalidSyscallId(String),
     AllocFrame(String),
     CreatePagetable(String),
@@ -25,6 +27,107 @@ pub enum KernelError {
     MapAreaNotFound(String),
     CurrentTaskNotFound(String),
     NoExitedChildTcb(String),
+    NoChildTcb(String),
+    TaskNotFound(String),
+    AllocThreadSlot(String),
+    Signal(String),
+    Interrupted(String),
+    BadFileDescriptor(String),
+    TooManyOpenFiles(String),
+    IllegalSeek(String),
+    BrokenPipe(String),
+    Io(String),
+    PageFault(String),
+    Brk(String),
+    Mmap(String),
+    NoMemory(String),
+    BadAddress(String),
+    Irq(String),
+    NotFound(String),
+    AlreadyExists(String),
+    NotDirectory(String),
+    IsDirectory(String),
+    DirectoryNotEmpty(String),
+    NameTooLong(String),
+    FileTooLarge(String),
+    NoSpace(String),
+    Busy(String),
+    OutOfRange(String),
+}
+
+impl KernelError {
+    /// The errno a syscall failing with this error reports to user space.
+    pub fn errno(&self) -> Errno {
+        match self {
+            KernelError::Common(_)
+            | KernelError::InvalidArgument(_)
+            | KernelError::Signal(_)
+            | KernelError::Mmap(_)
+            | KernelError::Irq(_) => Errno::EINVAL,
+            KernelError::ArgumentListTooLong(_) => Errno::E2BIG,
+            KernelError::InvalidSyscallId(_) => Errno::ENOSYS,
+            KernelError::AllocFrame(_)
+            | KernelError::CreatePagetable(_)
+            | KernelError::CreateMemorySpace(_)
+            | KernelError::PagetableMap(_)
+            | KernelError::MapArea(_)
+            | KernelError::AddMapArea(_)
+            | KernelError::AddAppKernelStackArea(_)
+            | KernelError::MapAreaNotFound(_)
+            | KernelError::Brk(_)
+            | KernelError::NoMemory(_) => Errno::ENOMEM,
+            KernelError::PteNotFound(_)
+            | KernelError::VpnTranslate(_)
+            | KernelError::Translate(_)
+            | KernelError::PageFault(_)
+            | KernelError::BadAddress(_) => Errno::EFAULT,
+            KernelError::ParseELF(_)
+            | KernelError::ELFProgramHeader(_)
+            | KernelError::ELFSegmentData(_) => Errno::ENOEXEC,
+            KernelError::LoadAppELF(_) | KernelError::NotFound(_) => Errno::ENOENT,
+            KernelError::AllocPid(_) | KernelError::AllocThreadSlot(_) => Errno::EAGAIN,
+            KernelError::CurrentTaskNotFound(_) | KernelError::TaskNotFound(_) => Errno::ESRCH,
+            KernelError::NoExitedChildTcb(_) | KernelError::NoChildTcb(_) => Errno::ECHILD,
+            KernelError::Interrupted(_) => Errno::EINTR,
+            KernelError::BadFileDescriptor(_) => Errno::EBADF,
+            KernelError::TooManyOpenFiles(_) => Errno::EMFILE,
+            KernelError::IllegalSeek(_) => Errno::ESPIPE,
+            KernelError::BrokenPipe(_) => Errno::EPIPE,
+            KernelError::Io(_) => Errno::EIO,
+            KernelError::AlreadyExists(_) => Errno::EEXIST,
+            KernelError::NotDirectory(_) => Errno::ENOTDIR,
+            KernelError::IsDirectory(_) => Errno::EISDIR,
+            KernelError::DirectoryNotEmpty(_) => Errno::ENOTEMPTY,
+            KernelError::NameTooLong(_) => Errno::ENAMETOOLONG,
+            KernelError::FileTooLarge(_) => Errno::EFBIG,
+            KernelError::NoSpace(_) => Errno::ENOSPC,
+            KernelError::Busy(_) => Errno::EBUSY,
+            KernelError::OutOfRange(_) => Errno::ERANGE,
+        }
+    }
+}
+
+impl From<los_fs::error::Error> for KernelError {
+    fn from(err: los_fs::error::Error) -> Self {
+        use los_fs::error::Error;
+
+        let msg = format!("{err:?}");
+        match err {
+            Error::NotFound => KernelError::NotFound(msg),
+            Error::AlreadyExists => KernelError::AlreadyExists(msg),
+            Error::NotDirectory => KernelError::NotDirectory(msg),
+            Error::IsDirectory => KernelError::IsDirectory(msg),
+            Error::DirectoryNotEmpty => KernelError::DirectoryNotEmpty(msg),
+            Error::NameTooLong => KernelError::NameTooLong(msg),
+            Error::FileTooLarge => KernelError::FileTooLarge(msg),
+            Error::NoFreeInode | Error::NoFreeBlock => KernelError::NoSpace(msg),
+            Error::ReadBlock(_)
+            | Error::WriteBlock(_)
+            | Error::NoFreeCache
+            | Error::InvalidSuperBlock
+            | Error::TooSmall => KernelError::Io(msg),
+        }
+    }
 }
 
 impl core::error::Error for KernelError {}
@@ -36,3 +139,35 @@ impl core::fmt::Display for KernelError {
 }
 
 pub type Result<T> = core::result::Result<T, KernelError>;
+
+/// Linux errno values. Failing syscalls return them negated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(isize)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum Errno {
+    ENOENT = 2,
+    ESRCH = 3,
+    EINTR = 4,
+    EIO = 5,
+    E2BIG = 7,
+    ENOEXEC = 8,
+    EBADF = 9,
+    ECHILD = 10,
+    EAGAIN = 11,
+    ENOMEM = 12,
+    EFAULT = 14,
+    EBUSY = 16,
+    EEXIST = 17,
+    ENOTDIR = 20,
+    EISDIR = 21,
+    EINVAL = 22,
+    EMFILE = 24,
+    EFBIG = 27,
+    ENOSPC = 28,
+    ESPIPE = 29,
+    EPIPE = 32,
+    ERANGE = 34,
+    ENAMETOOLONG = 36,
+    ENOSYS = 38,
+    ENOTEMPTY = 39,
+}
@@ -0,0 +1,97 @@
+pub mod disk;
+mod fd_table;
+mod inode;
+mod pipe;
+mod stdio;
+
+use crate::error;
+use alloc::string::ToString;
+use bitflags::bitflags;
+use core::fmt::Debug;
+
+pub use fd_table::FdTable;
+pub use pipe::make_pipe;
+pub use stdio::{Stdin, Stdout};
+
+pub const S_IFIFO: u32 = 0o010000;
+pub const S_IFCHR: u32 = 0o020000;
+pub const S_IFDIR: u32 = 0o040000;
+pub const S_IFREG: u32 = 0o100000;
+
+pub const SEEK_SET: usize = 0;
+pub const SEEK_CUR: usize = 1;
+pub const SEEK_END: usize = 2;
+
+bitflags! {
+    /// Linux `open` flags. Read-only is the absence of `WRONLY` and `RDWR`.
+    #[derive(Debug, Clone, Copy)]
+    pub struct OpenFlags: usize {
+        const WRONLY = 1 << 0;
+        const RDWR = 1 << 1;
+        const CREAT = 0o100;
+        const EXCL = 0o200;
+        const TRUNC = 0o1000;
+        const APPEND = 0o2000;
+        const DIRECTORY = 0o200000;
+    }
+}
+
+impl OpenFlags {
+    pub fn readable(&self) -> bool {
+        !self.contains(OpenFlags::WRONLY)
+    }
+
+    pub fn writable(&self) -> bool {
+        self.intersects(OpenFlags::WRONLY | OpenFlags::RDWR)
+    }
+}
+
+/// `struct stat` of riscv64 Linux. There are no owners or timestamps, those
+/// fields stay 0.
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+pub struct Stat {
+    pub dev: u64,
+    pub ino: u64,
+    pub mode: u32,
+    pub nlink: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub rdev: u64,
+    _pad0: u64,
+    pub size: i64,
+    pub blksize: i32,
+    _pad1: i32,
+    pub blocks: i64,
+    pub atime_sec: i64,
+    pub atime_nsec: u64,
+    pub mtime_sec: i64,
+    pub mtime_nsec: u64,
+    pub ctime_sec: i64,
+    pub ctime_nsec: u64,
+    _unused: [u32; 2],
+}
+
+/// An open file as seen through a file descriptor. Reads and writes go
+/// through kernel buffers, a file never touches user memory itself.
+pub trait File: Debug + Send + Sync {
+    fn readable(&self) -> bool;
+    fn writable(&self) -> bool;
+    fn read(&self, buf: &mut [u8]) -> error::Result<usize>;
+    fn write(&self, buf: &[u8]) -> error::Result<usize>;
+    fn stat(&self) -> error::Result<Stat>;
+
+    fn seek(&self, _offset: isize, _whence: usize) -> error::Result<usize> {
+        Err(error::KernelError::IllegalSeek(
+            "file is not seekable".to_string(),
+        ))
+    }
+
+    /// Fills `buf` with `linux_dirent64` records of the next directory
+    /// entries and returns the bytes used, 0 at the end of the directory.
+    fn getdents(&self, _buf: &mut [u8]) -> error::Result<usize> {
+        Err(error::KernelError::NotDirectory(
+            "file is not a directory".to_string(),
+        ))
+    }
+}
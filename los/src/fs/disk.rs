@@ -0,0 +1,221 @@
+use alloc::{format, string::String, vec::Vec};
+use core::{
+    hint,
+    sync::atomic::{AtomicBool, Ordering},
+};
+use lazy_static::lazy_static;
+use los_fs::{fs::FileSystem, layout::InodeKind, vfs::Inode};
+
+use super::{
+    inode::{self, InodeFile},
+    OpenFlags,
+};
+use crate::{
+    drivers::virtio_blk,
+    error, println,
+    task::{processor, wait_queue::WaitQueue},
+};
+
+lazy_static! {
+    static ref ROOT_INODE: Option<Inode> = mount();
+}
+
+/// Set while a task is inside los-fs, see `lock`.
+static FS_BUSY: AtomicBool = AtomicBool::new(false);
+static FS_IDLE: WaitQueue = WaitQueue::new();
+
+fn mount() -> Option<Inode> {
+    let device = virtio_blk::block_device()?;
+    match FileSystem::open(device) {
+        Ok(fs) => Some(FileSystem::root_inode(&fs)),
+        Err(err) => {
+            println!("[FS] mount root filesystem failed: {:?}", err);
+            None
+        }
+    }
+}
+
+/// Mounts the root filesystem from the block device, if there is one.
+pub fn init() {
+    let _fs = lock();
+    match ROOT_INODE.as_ref() {
+        Some(_) => {
+            println!("[FS] root filesystem mounted");
+        }
+        None => {
+            println!("[FS] no root filesystem, programs come from the initramfs");
+        }
+    }
+}
+
+/// Releases the filesystem when dropped.
+pub struct FsGuard;
+
+impl Drop for FsGuard {
+    fn drop(&mut self) {
+        FS_BUSY.store(false, Ordering::Release);
+        FS_IDLE.wake_all();
+    }
+}
+
+/// Gives the caller the filesystem to itself. los-fs keeps spin locks held
+/// across block reads, which sleep, so a second task entering it could spin
+/// on the hart the sleeping one needs. Boot code before the first task
+/// spins instead of sleeping.
+pub fn lock() -> FsGuard {
+    if processor::has_current_task() {
+        FS_IDLE.wait_until_uninterruptible(|| !FS_BUSY.swap(true, Ordering::Acquire));
+    } else {
+        while FS_BUSY.swap(true, Ordering::Acquire) {
+            hint::spin_loop();
+        }
+    }
+
+    FsGuard
+}
+
+/// Resolves `path` against the working directory `cwd` into an absolute
+/// path without `.` and `..` components.
+pub fn absolute_path(cwd: &str, path: &str) -> String {
+    let base = if path.starts_with('/') { "" } else { cwd };
+
+    let mut components = Vec::new();
+    for name in base.split('/').chain(path.split('/')) {
+        match name {
+            "" | "." => {}
+            ".." => {
+                components.pop();
+            }
+            name => components.push(name),
+        }
+    }
+
+    let mut absolute = String::from("/");
+    absolute.push_str(&components.join("/"));
+    absolute
+}
+
+/// Resolves `path` from the root directory, relative paths start at the
+/// root too. The filesystem must be locked.
+fn find(path: &str) -> error::Result<Inode> {
+    let root = ROOT_INODE
+        .as_ref()
+        .ok_or(error::KernelError::NotFound("no root filesystem".into()))?;
+
+    let mut inode = root.clone();
+    for name in path
+        .split('/')
+        .filter(|&name| !name.is_empty() && name != ".")
+    {
+        inode = inode.lookup(name)?;
+    }
+
+    Ok(inode)
+}
+
+/// Splits an absolute path from `absolute_path` into its parent directory
+/// and last name, `None` for the root.
+fn split_last(path: &str) -> Option<(&str, &str)> {
+    path.rsplit_once('/').filter(|(_, name)| !name.is_empty())
+}
+
+/// Reads the whole regular file at `path`, which is at most `max_size`
+/// bytes long. The file is buffered on the kernel heap.
+pub fn read_file(path: &str, max_size: usize) -> error::Result<Vec<u8>> {
+    let _fs = lock();
+    let inode = find(path)?;
+    if inode.is_dir()? {
+        return Err(error::KernelError::IsDirectory(path.into()));
+    }
+
+    let size = inode.size()?;
+    if size > max_size {
+        return Err(error::KernelError::NoMemory(format!(
+            "{path} is {size} bytes, over the {max_size} bytes limit"
+        )));
+    }
+
+    let mut data = Vec::new();
+    data.try_reserve_exact(size).map_err(|_| {
+        error::KernelError::NoMemory(format!("no kernel heap for {size} bytes of {path}"))
+    })?;
+    data.resize(size, 0);
+    let len = inode.read_at(0, &mut data)?;
+    data.truncate(len);
+
+    Ok(data)
+}
+
+/// Names in the root directory, empty without a root filesystem.
+pub fn list_root() -> Vec<String> {
+    let _fs = lock();
+    ROOT_INODE
+        .as_ref()
+        .and_then(|root| root.ls().ok())
+        .unwrap_or_default()
+}
+
+pub fn is_dir(path: &str) -> error::Result<bool> {
+    let _fs = lock();
+    Ok(find(path)?.is_dir()?)
+}
+
+/// Opens the file or directory at the absolute `path`. Directories can only
+/// be opened for reading.
+pub fn open(path: &str, flags: OpenFlags) -> error::Result<InodeFile> {
+    let _fs = lock();
+    let inode = match find(path) {
+        Ok(_) if flags.contains(OpenFlags::CREAT | OpenFlags::EXCL) => {
+            return Err(error::KernelError::AlreadyExists(path.into()));
+        }
+        Ok(inode) => inode,
+        Err(error::KernelError::NotFound(_)) if flags.contains(OpenFlags::CREAT) => {
+            let (parent, name) =
+                split_last(path).ok_or(error::KernelError::AlreadyExists(path.into()))?;
+            find(parent)?.create(name, InodeKind::File)?
+        }
+        Err(err) => return Err(err),
+    };
+
+    let is_dir = inode.is_dir()?;
+    if is_dir && flags.writable() {
+        return Err(error::KernelError::IsDirectory(path.into()));
+    }
+    if !is_dir && flags.contains(OpenFlags::DIRECTORY) {
+        return Err(error::KernelError::NotDirectory(path.into()));
+    }
+    if !is_dir && flags.writable() && flags.contains(OpenFlags::TRUNC) {
+        inode.truncate(0)?;
+    }
+
+    Ok(InodeFile::new(inode, is_dir, flags))
+}
+
+pub fn mkdir(path: &str) -> error::Result<()> {
+    let _fs = lock();
+    let (parent, name) = split_last(path).ok_or(error::KernelError::AlreadyExists(path.into()))?;
+    find(parent)?.create(name, InodeKind::Directory)?;
+
+    Ok(())
+}
+
+/// Removes the file at `path`, or the empty directory with `remove_dir`.
+/// Files that are still open cannot be removed.
+pub fn unlink(path: &str, remove_dir: bool) -> error::Result<()> {
+    let _fs = lock();
+    let (parent, name) = split_last(path).ok_or(error::KernelError::Busy(path.into()))?;
+
+    let target = find(path)?;
+    match (target.is_dir()?, remove_dir) {
+        (true, false) => return Err(error::KernelError::IsDirectory(path.into())),
+        (false, true) => return Err(error::KernelError::NotDirectory(path.into())),
+        _ => {}
+    }
+    if inode::is_open(target.inode_id()) {
+        return Err(error::KernelError::Busy(path.into()));
+    }
+
+    find(parent)?.unlink(name)?;
+
+    Ok(())
+}
@@ -0,0 +1,81 @@
+use super::{File, Stdin, Stdout};
+use crate::{config::MAX_FD_COUNT, error};
+use alloc::{format, sync::Arc, vec, vec::Vec};
+
+#[derive(Debug, Clone, Default)]
+pub struct FdTable {
+    files: Vec<Option<Arc<dyn File>>>,
+}
+
+impl FdTable {
+    /// A table with stdin, stdout and stderr open at fds 0, 1 and 2. Stderr
+    /// is its own open file on the console, so redirecting stdout leaves it
+    /// alone.
+    pub fn with_stdio() -> Self {
+        Self {
+            files: vec![
+                Some(Arc::new(Stdin) as Arc<dyn File>),
+                Some(Arc::new(Stdout)),
+                Some(Arc::new(Stdout)),
+            ],
+        }
+    }
+
+    pub fn get(&self, fd: usize) -> error::Result<Arc<dyn File>> {
+        self.files
+            .get(fd)
+            .and_then(|v| v.clone())
+            .ok_or(error::KernelError::BadFileDescriptor(format!(
+                "fd {fd} is not open"
+            )))
+    }
+
+    /// Installs `file` at the lowest free fd.
+    pub fn insert(&mut self, file: Arc<dyn File>) -> error::Result<usize> {
+        let fd = match self.files.iter().position(Option::is_none) {
+            Some(fd) => fd,
+            None if self.files.len() < MAX_FD_COUNT => {
+                self.files.push(None);
+                self.files.len() - 1
+            }
+            None => {
+                return Err(error::KernelError::TooManyOpenFiles(format!(
+                    "fd table is full: {MAX_FD_COUNT}"
+                )))
+            }
+        };
+
+        self.files[fd] = Some(file);
+
+        Ok(fd)
+    }
+
+    /// Installs `file` at `fd` and returns the file it replaces, which the
+    /// caller should drop after releasing the table.
+    pub fn insert_at(
+        &mut self,
+        fd: usize,
+        file: Arc<dyn File>,
+    ) -> error::Result<Option<Arc<dyn File>>> {
+        if fd >= MAX_FD_COUNT {
+            return Err(error::KernelError::BadFileDescriptor(format!(
+                "fd {fd} exceeds the limit {MAX_FD_COUNT}"
+            )));
+        }
+
+        if fd >= self.files.len() {
+            self.files.resize(fd + 1, None);
+        }
+
+        Ok(self.files[fd].replace(file))
+    }
+
+    pub fn remove(&mut self, fd: usize) -> error::Result<Arc<dyn File>> {
+        self.files
+            .get_mut(fd)
+            .and_then(Option::take)
+            .ok_or(error::KernelError::BadFileDescriptor(format!(
+                "fd {fd} is not open"
+            )))
+    }
+}
@@ -0,0 +1,197 @@
+use super::{disk, File, OpenFlags, Stat, SEEK_CUR, SEEK_END, SEEK_SET, S_IFDIR, S_IFREG};
+use crate::error;
+use alloc::{collections::btree_map::BTreeMap, format, string::ToString};
+use core::fmt::{self, Debug};
+use los_fs::{layout::InodeKind, vfs::Inode, BLOCK_SIZE};
+use spin::Mutex;
+
+/// How many open files refer to each inode. los-fs frees an inode on unlink
+/// whatever handles remain, so unlinking an open file is refused.
+static OPEN_INODES: Mutex<BTreeMap<u32, usize>> = Mutex::new(BTreeMap::new());
+
+pub fn is_open(inode_id: u32) -> bool {
+    OPEN_INODES.lock().contains_key(&inode_id)
+}
+
+const DT_DIR: u8 = 4;
+const DT_REG: u8 = 8;
+/// `d_ino`, `d_off`, `d_reclen` and `d_type` of a `linux_dirent64`, the
+/// name follows.
+const DIRENT64_NAME_OFFSET: usize = 19;
+
+/// A file or directory of the root filesystem opened by path.
+pub struct InodeFile {
+    inode: Inode,
+    is_dir: bool,
+    flags: OpenFlags,
+    /// Bytes into a file, entries into a directory.
+    offset: Mutex<usize>,
+}
+
+impl InodeFile {
+    /// Only `disk::open` creates these, with the filesystem locked.
+    pub fn new(inode: Inode, is_dir: bool, flags: OpenFlags) -> Self {
+        *OPEN_INODES.lock().entry(inode.inode_id()).or_insert(0) += 1;
+
+        Self {
+            inode,
+            is_dir,
+            flags,
+            offset: Mutex::new(0),
+        }
+    }
+}
+
+impl Debug for InodeFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InodeFile")
+            .field("inode_id", &self.inode.inode_id())
+            .field("is_dir", &self.is_dir)
+            .field("flags", &self.flags)
+            .field("offset", &self.offset)
+            .finish()
+    }
+}
+
+// every operation holds the filesystem lock first, so the offset lock is
+// never contended while the disk is read
+
+impl File for InodeFile {
+    fn readable(&self) -> bool {
+        self.flags.readable()
+    }
+
+    fn writable(&self) -> bool {
+        self.flags.writable()
+    }
+
+    fn read(&self, buf: &mut [u8]) -> error::Result<usize> {
+        if self.is_dir {
+            return Err(error::KernelError::IsDirectory(
+                "read a directory".to_string(),
+            ));
+        }
+
+        let _fs = disk::lock();
+        let mut offset = self.offset.lock();
+        let read_len = self.inode.read_at(*offset, buf)?;
+        *offset += read_len;
+
+        Ok(read_len)
+    }
+
+    fn write(&self, buf: &[u8]) -> error::Result<usize> {
+        let _fs = disk::lock();
+        let mut offset = self.offset.lock();
+        if self.flags.contains(OpenFlags::APPEND) {
+            *offset = self.inode.size()?;
+        }
+
+        let written = self.inode.write_at(*offset, buf)?;
+        *offset += written;
+
+        Ok(written)
+    }
+
+    fn stat(&self) -> error::Result<Stat> {
+        let _fs = disk::lock();
+        let mode = match self.inode.kind()? {
+            InodeKind::File => S_IFREG | 0o644,
+            InodeKind::Directory => S_IFDIR | 0o755,
+        };
+
+        let size = self.inode.size()?;
+        Ok(Stat {
+            ino: self.inode.inode_id() as u64,
+            mode,
+            nlink: 1,
+            size: size as i64,
+            blksize: BLOCK_SIZE as i32,
+            blocks: size.div_ceil(BLOCK_SIZE) as i64,
+            ..Stat::default()
+        })
+    }
+
+    fn seek(&self, offset: isize, whence: usize) -> error::Result<usize> {
+        let _fs = disk::lock();
+        let mut current = self.offset.lock();
+        let base = match whence {
+            SEEK_SET => 0,
+            SEEK_CUR => *current,
+            SEEK_END if self.is_dir => self.inode.ls()?.len(),
+            SEEK_END => self.inode.size()?,
+            _ => {
+                return Err(error::KernelError::InvalidArgument(format!(
+                    "invalid whence: {whence}"
+                )))
+            }
+        };
+
+        *current = base
+            .checked_add_signed(offset)
+            .ok_or(error::KernelError::InvalidArgument(format!(
+                "seek to a negative offset: {base} {offset}"
+            )))?;
+
+        Ok(*current)
+    }
+
+    fn getdents(&self, buf: &mut [u8]) -> error::Result<usize> {
+        if !self.is_dir {
+            return Err(error::KernelError::NotDirectory(
+                "file is not a directory".to_string(),
+            ));
+        }
+
+        let _fs = disk::lock();
+        let mut offset = self.offset.lock();
+        let names = self.inode.ls()?;
+
+        let mut used = 0;
+        for name in names.iter().skip(*offset) {
+            let reclen = (DIRENT64_NAME_OFFSET + name.len() + 1).next_multiple_of(8);
+            if used + reclen > buf.len() {
+                break;
+            }
+
+            let child = self.inode.lookup(name)?;
+            let d_type = match child.kind()? {
+                InodeKind::File => DT_REG,
+                InodeKind::Directory => DT_DIR,
+            };
+
+            let record = &mut buf[used..used + reclen];
+            record.fill(0);
+            record[0..8].copy_from_slice(&(child.inode_id() as u64).to_le_bytes());
+            record[8..16].copy_from_slice(&((*offset + 1) as i64).to_le_bytes());
+            record[16..18].copy_from_slice(&(reclen as u16).to_le_bytes());
+            record[18] = d_type;
+            record[DIRENT64_NAME_OFFSET..DIRENT64_NAME_OFFSET + name.len()]
+                .copy_from_slice(name.as_bytes());
+
+            used += reclen;
+            *offset += 1;
+        }
+
+        if used == 0 && *offset < names.len() {
+            return Err(error::KernelError::InvalidArgument(
+                "buffer too small for a directory entry".to_string(),
+            ));
+        }
+
+        Ok(used)
+    }
+}
+
+impl Drop for InodeFile {
+    fn drop(&mut self) {
+        let inode_id = self.inode.inode_id();
+        let mut open_inodes = OPEN_INODES.lock();
+        if let Some(count) = open_inodes.get_mut(&inode_id) {
+            *count -= 1;
+            if *count == 0 {
+                open_inodes.remove(&inode_id);
+            }
+        }
+    }
+}
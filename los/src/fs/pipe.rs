@@ -0,0 +1,200 @@
+use super::{File, Stat, S_IFIFO};
+use crate::{
+    error,
+    task::{processor, signal::SIGPIPE, wait_queue::WaitQueue},
+};
+use alloc::{collections::vec_deque::VecDeque, string::ToString, sync::Arc};
+use spin::Mutex;
+
+const PIPE_BUF_SIZE: usize = 1 << 12;
+
+#[derive(Debug)]
+struct PipeBuffer {
+    data: VecDeque<u8>,
+    read_closed: bool,
+    write_closed: bool,
+}
+
+impl PipeBuffer {
+    fn push(&mut self, buf: &[u8]) -> usize {
+        let len = buf.len().min(PIPE_BUF_SIZE - self.data.len());
+        self.data.extend(&buf[..len]);
+        len
+    }
+
+    fn pop(&mut self, buf: &mut [u8]) -> usize {
+        let len = buf.len().min(self.data.len());
+        for (dst, src) in buf.iter_mut().zip(self.data.drain(..len)) {
+            *dst = src;
+        }
+        len
+    }
+}
+
+#[derive(Debug)]
+struct Pipe {
+    buffer: Mutex<PipeBuffer>,
+    /// Readers wait here for data or for the write end to close.
+    readable: WaitQueue,
+    /// Writers wait here for space or for the read end to close.
+    writable: WaitQueue,
+}
+
+/// Creates a pipe and returns its read end and write end.
+pub fn make_pipe() -> (Arc<PipeReadEnd>, Arc<PipeWriteEnd>) {
+    let pipe = Arc::new(Pipe {
+        buffer: Mutex::new(PipeBuffer {
+            data: VecDeque::with_capacity(PIPE_BUF_SIZE),
+            read_closed: false,
+            write_closed: false,
+        }),
+        readable: WaitQueue::new(),
+        writable: WaitQueue::new(),
+    });
+
+    (
+        Arc::new(PipeReadEnd { pipe: pipe.clone() }),
+        Arc::new(PipeWriteEnd { pipe }),
+    )
+}
+
+fn pipe_stat() -> error::Result<Stat> {
+    Ok(Stat {
+        mode: S_IFIFO | 0o600,
+        nlink: 1,
+        ..Stat::default()
+    })
+}
+
+/// Each end is a single open file shared by every fd that refers to it, so
+/// the end closes when the last of those fds goes away.
+#[derive(Debug)]
+pub struct PipeReadEnd {
+    pipe: Arc<Pipe>,
+}
+
+impl File for PipeReadEnd {
+    fn readable(&self) -> bool {
+        true
+    }
+
+    fn writable(&self) -> bool {
+        false
+    }
+
+    /// Blocks until data is available, returns 0 once the buffer is empty
+    /// and the write end is closed.
+    fn read(&self, buf: &mut [u8]) -> error::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let mut read_len = 0;
+        let completed = self.pipe.readable.wait_until(|| {
+            let mut buffer = self.pipe.buffer.lock();
+            read_len = buffer.pop(buf);
+            read_len > 0 || buffer.write_closed
+        });
+        if !completed {
+            return Err(error::KernelError::Interrupted(
+                "pipe read interrupted by signal".to_string(),
+            ));
+        }
+
+        self.pipe.writable.wake_all();
+
+        Ok(read_len)
+    }
+
+    fn write(&self, _buf: &[u8]) -> error::Result<usize> {
+        Err(error::KernelError::BadFileDescriptor(
+            "read end of pipe is not writable".to_string(),
+        ))
+    }
+
+    fn stat(&self) -> error::Result<Stat> {
+        pipe_stat()
+    }
+}
+
+impl Drop for PipeReadEnd {
+    fn drop(&mut self) {
+        self.pipe.buffer.lock().read_closed = true;
+        self.pipe.writable.wake_all();
+    }
+}
+
+#[derive(Debug)]
+pub struct PipeWriteEnd {
+    pipe: Arc<Pipe>,
+}
+
+impl File for PipeWriteEnd {
+    fn readable(&self) -> bool {
+        false
+    }
+
+    fn writable(&self) -> bool {
+        true
+    }
+
+    fn read(&self, _buf: &mut [u8]) -> error::Result<usize> {
+        Err(error::KernelError::BadFileDescriptor(
+            "write end of pipe is not readable".to_string(),
+        ))
+    }
+
+    /// Blocks until all of `buf` is written. Writing with the read end
+    /// closed raises SIGPIPE.
+    fn write(&self, buf: &[u8]) -> error::Result<usize> {
+        let mut written = 0;
+        while written < buf.len() {
+            let mut broken = false;
+            let completed = self.pipe.writable.wait_until(|| {
+                let mut buffer = self.pipe.buffer.lock();
+                if buffer.read_closed {
+                    broken = true;
+                    return true;
+                }
+
+                let len = buffer.push(&buf[written..]);
+                written += len;
+                len > 0
+            });
+
+            if written > 0 {
+                self.pipe.readable.wake_all();
+            }
+
+            if !completed || broken {
+                if written > 0 {
+                    break;
+                }
+
+                if broken {
+                    processor::raise_current_task_signal(SIGPIPE);
+                    return Err(error::KernelError::BrokenPipe(
+                        "read end of pipe is closed".to_string(),
+                    ));
+                }
+
+                return Err(error::KernelError::Interrupted(
+                    "pipe write interrupted by signal".to_string(),
+                ));
+            }
+        }
+
+        Ok(written)
+    }
+
+    fn stat(&self) -> error::Result<Stat> {
+        pipe_stat()
+    }
+}
+
+impl Drop for PipeWriteEnd {
+    fn drop(&mut self) {
+        self.pipe.buffer.lock().write_closed = true;
+        self.pipe.readable.wake_all();
+    }
+}
@@ -0,0 +1,97 @@
+use super::{File, Stat, S_IFCHR};
+use crate::{
+    console,
+    drivers::uart,
+    error, sbi,
+    timer::{self, TimeSpec},
+};
+use alloc::string::ToString;
+
+const CONSOLE_POLL_INTERVAL: TimeSpec = TimeSpec::from_millis(10);
+
+fn console_stat() -> error::Result<Stat> {
+    Ok(Stat {
+        mode: S_IFCHR | 0o620,
+        nlink: 1,
+        ..Stat::default()
+    })
+}
+
+#[derive(Debug)]
+pub struct Stdin;
+
+impl File for Stdin {
+    fn readable(&self) -> bool {
+        true
+    }
+
+    fn writable(&self) -> bool {
+        false
+    }
+
+    fn read(&self, buf: &mut [u8]) -> error::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        if uart::has_rx_irq() {
+            return uart::read(buf);
+        }
+
+        // no uart input interrupt, poll the SBI console
+        loop {
+            let read_len = sbi::console_read_bytes(buf);
+            if read_len < 0 {
+                return Err(error::KernelError::Io("read console failed".to_string()));
+            }
+            if read_len > 0 {
+                return Ok(read_len as usize);
+            }
+
+            if timer::sleep(CONSOLE_POLL_INTERVAL).is_some() {
+                return Err(error::KernelError::Interrupted(
+                    "read interrupted by signal".to_string(),
+                ));
+            }
+        }
+    }
+
+    fn write(&self, _buf: &[u8]) -> error::Result<usize> {
+        Err(error::KernelError::BadFileDescriptor(
+            "stdin is not writable".to_string(),
+        ))
+    }
+
+    fn stat(&self) -> error::Result<Stat> {
+        console_stat()
+    }
+}
+
+#[derive(Debug)]
+pub struct Stdout;
+
+impl File for Stdout {
+    fn readable(&self) -> bool {
+        false
+    }
+
+    fn writable(&self) -> bool {
+        true
+    }
+
+    fn read(&self, _buf: &mut [u8]) -> error::Result<usize> {
+        Err(error::KernelError::BadFileDescriptor(
+            "stdout is not readable".to_string(),
+        ))
+    }
+
+    fn write(&self, buf: &[u8]) -> error::Result<usize> {
+        console::write_bytes(buf);
+
+        Ok(buf.len())
+    }
+
+    fn stat(&self) -> error::Result<Stat> {
+        console_stat()
+    }
+}
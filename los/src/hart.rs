@@ -0,0 +1,45 @@
+use core::{
+    arch::asm,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{config::MAX_HARTS, device_tree, println, sbi};
+
+static ONLINE_HARTS: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns the id of the running hart, which the boot code keeps in `tp`.
+pub fn hart_id() -> usize {
+    let hartid;
+    unsafe { asm!("mv {}, tp", out(reg) hartid) };
+    hartid
+}
+
+pub fn mark_online() {
+    ONLINE_HARTS.fetch_or(1 << hart_id(), Ordering::SeqCst);
+}
+
+pub fn online_mask() -> usize {
+    ONLINE_HARTS.load(Ordering::SeqCst)
+}
+
+pub fn start_secondary_harts() {
+    extern "C" {
+        fn _start_secondary();
+    }
+
+    let boot_hartid = hart_id();
+    for hartid in device_tree::get_device_info().harts {
+        if hartid == boot_hartid {
+            continue;
+        }
+
+        if hartid >= MAX_HARTS {
+            println!("[HART] skip hart {}, exceeds MAX_HARTS", hartid);
+            continue;
+        }
+
+        if let Err(err) = sbi::hart_start(hartid, _start_secondary as usize, 0) {
+            println!("[HART] start hart {} failed: {}", hartid, err);
+        }
+    }
+}
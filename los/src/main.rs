@@ -8,10 +8,14 @@
 
 extern crate alloc;
 
+mod backtrace;
 mod config;
 mod console;
 mod device_tree;
+mod drivers;
 mod error;
+mod fs;
+mod hart;
 mod mm;
 mod sbi;
 mod syscall;
@@ -23,29 +27,63 @@ use core::{arch::global_asm, panic::PanicInfo};
 
 use task::processor;
 
-global_asm!(include_str!("entry.asm"));
-global_asm!(include_str!("trap.asm"));
+global_asm!(
+    include_str!("entry.asm"),
+    boot_stack_size = const config::BOOT_STACK_SIZE,
+    guard_size = const config::GUARD_PAGE_COUNT * mm::address::PAGE_SIZE,
+    max_harts = const config::MAX_HARTS,
+);
+global_asm!(
+    include_str!("trap.asm"),
+    kernel_trap_stack_size = const config::KERNEL_TRAP_STACK_SIZE,
+    kernel_trap_stack_shift = const config::KERNEL_TRAP_STACK_SIZE.trailing_zeros(),
+    max_harts = const config::MAX_HARTS,
+);
 global_asm!(include_str!("app.asm"));
 
 #[no_mangle]
-extern "C" fn rust_main(_hartid: usize, device_tree_pa: usize) -> ! {
-    #[cfg(test)]
-    {
-        test_main();
-        sbi::shutdown(false);
-    }
-
+extern "C" fn rust_main(hartid: usize, device_tree_pa: usize) -> ! {
     clear_bss();
     device_tree::init(device_tree_pa);
 
     print_kernel_info();
+    assert!(
+        hartid < config::MAX_HARTS,
+        "boot hart {hartid} exceeds MAX_HARTS"
+    );
 
     mm::init();
     trap::init();
+    drivers::init();
+    fs::disk::init();
     timer::init();
     task::init();
+
+    // tests run on the boot hart alone, before any task is scheduled
+    #[cfg(test)]
+    {
+        test_main();
+        sbi::shutdown(false);
+    }
+
     task::print_apps();
 
+    hart::mark_online();
+    hart::start_secondary_harts();
+
+    processor::run_tasks();
+}
+
+#[no_mangle]
+extern "C" fn rust_main_secondary(hartid: usize, _opaque: usize) -> ! {
+    mm::init_hart();
+    trap::init();
+    drivers::init_hart();
+    timer::init_hart();
+
+    hart::mark_online();
+    println!("[HART] hart {} started", hartid);
+
     processor::run_tasks();
 }
 
@@ -62,6 +100,8 @@ fn print_kernel_info() {
         fn ebtstack();
         fn sbss();
         fn ebss();
+        fn sksymtab();
+        fn eksymtab();
         fn ekernel();
     }
 
@@ -77,6 +117,7 @@ fn print_kernel_info() {
     color_print(".data", sdata as usize, edata as usize);
     color_print(".btstack", sbtstack as usize, ebtstack as usize);
     color_print(".bss", sbss as usize, ebss as usize);
+    color_print(".ksymtab", sksymtab as usize, eksymtab as usize);
 }
 
 fn clear_bss() {
@@ -93,6 +134,7 @@ fn clear_bss() {
 #[panic_handler]
 fn panic_handler(panic_info: &PanicInfo) -> ! {
     println!("{}", panic_info);
+    backtrace::print_current();
 
     sbi::shutdown(true);
 }
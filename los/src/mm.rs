@@ -1,5 +1,7 @@
 use crate::device_tree;
 use crate::error;
+use alloc::string::String;
+use core::ops::Range;
 use lazy_static::lazy_static;
 use spin::Mutex;
 
@@ -8,14 +10,24 @@ mod frame_allocator;
 mod heap;
 mod memory_space;
 mod page_table;
+mod tlb;
+mod user_ptr;
 
+pub use frame_allocator::alloc_contiguous;
+#[allow(unused_imports)]
+pub use frame_allocator::free_frames_count;
+pub use frame_allocator::ContiguousFrames;
 #[allow(unused_imports)]
 pub use heap::kernel_heap_stats;
 pub use memory_space::trampoline_va;
 pub use memory_space::trap_context_va;
 pub use memory_space::KernelStack;
+pub use memory_space::MapPermission;
 pub use memory_space::MemorySpace;
-pub use page_table::PageTable;
+pub use memory_space::PageFault;
+pub use user_ptr::read_c_str;
+pub use user_ptr::UserPtr;
+pub use user_ptr::UserSlice;
 
 lazy_static! {
     pub static ref KERNEL_MEMORY_SPACE: Mutex<memory_space::MemorySpace> = {
@@ -32,12 +44,27 @@ pub fn init() {
     KERNEL_MEMORY_SPACE.lock().activate();
 }
 
+pub fn init_hart() {
+    KERNEL_MEMORY_SPACE.lock().activate();
+}
+
+/// Maps device registers identically into the kernel address space.
+pub fn map_mmio(range: &Range<usize>) -> error::Result<()> {
+    KERNEL_MEMORY_SPACE.lock().add_identical_area(
+        range.start.into(),
+        range.end.into(),
+        MapPermission::R | MapPermission::W,
+    )
+}
+
 pub fn kernel_satp() -> usize {
     KERNEL_MEMORY_SPACE.lock().page_table().satp()
 }
 
 pub fn build_app_mem_space(
     elf_data: &[u8],
+    argv: &[String],
+    envp: &[String],
 ) -> error::Result<(memory_space::MemorySpace, usize, usize)> {
-    memory_space::MemorySpace::new_elf(elf_data)
+    memory_space::MemorySpace::new_elf(elf_data, argv, envp)
 }
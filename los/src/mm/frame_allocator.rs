@@ -1,12 +1,14 @@
 use super::address::PhysPageNum;
 use crate::mm::address::PhysAddr;
-use alloc::vec::Vec;
+use alloc::{collections::btree_set::BTreeSet, vec, vec::Vec};
 use core::ops::Range;
 use lazy_static::lazy_static;
 use spin::Mutex;
 
+const MAX_ORDER: usize = 10;
+
 lazy_static! {
-    static ref FRAME_ALLOCATOR: Mutex<StackFrameAllocator> = Mutex::new(StackFrameAllocator::new());
+    static ref FRAME_ALLOCATOR: Mutex<BuddyFrameAllocator> = Mutex::new(BuddyFrameAllocator::new());
 }
 
 pub fn init(mem_range: &Range<usize>) {
@@ -28,11 +30,15 @@ pub fn init(mem_range: &Range<usize>) {
 }
 
 pub fn alloc() -> Option<Frame> {
-    FRAME_ALLOCATOR.lock().alloc().map(Frame::new)
+    FRAME_ALLOCATOR.lock().alloc(0).map(Frame::new)
 }
 
-pub fn dealloc(ppn: PhysPageNum) {
-    FRAME_ALLOCATOR.lock().dealloc(ppn);
+pub fn alloc_contiguous(count: usize) -> Option<ContiguousFrames> {
+    let order = order_of(count)?;
+    FRAME_ALLOCATOR
+        .lock()
+        .alloc(order)
+        .map(|ppn| ContiguousFrames::new(ppn, count, order))
 }
 
 #[allow(dead_code)]
@@ -40,57 +46,113 @@ pub fn free_frames_count() -> usize {
     FRAME_ALLOCATOR.lock().free_frames_count()
 }
 
-pub struct StackFrameAllocator {
-    current: usize,
+fn order_of(count: usize) -> Option<usize> {
+    let order = count.max(1).next_power_of_two().trailing_zeros() as usize;
+    (order <= MAX_ORDER).then_some(order)
+}
+
+pub struct BuddyFrameAllocator {
+    start: usize,
     end: usize,
-    recycled: Vec<usize>,
+    free_lists: Vec<BTreeSet<usize>>,
+    ref_counts: Vec<u16>,
+    free_count: usize,
 }
 
-impl StackFrameAllocator {
+impl BuddyFrameAllocator {
     fn new() -> Self {
         Self {
-            current: 0,
+            start: 0,
             end: 0,
-            recycled: Vec::new(),
+            free_lists: vec![BTreeSet::new(); MAX_ORDER + 1],
+            ref_counts: Vec::new(),
+            free_count: 0,
         }
     }
 
     pub fn init(&mut self, start: PhysPageNum, end: PhysPageNum) {
-        self.current = start.0;
+        self.start = start.0;
         self.end = end.0;
+        self.ref_counts = vec![0; end.0 - start.0];
+
+        let mut ppn = start.0;
+        while ppn < end.0 {
+            let order = (0..=MAX_ORDER)
+                .rev()
+                .find(|order| ppn & ((1 << order) - 1) == 0 && ppn + (1 << order) <= end.0)
+                .expect("order 0 always fits");
+            self.free_lists[order].insert(ppn);
+            self.free_count += 1 << order;
+            ppn += 1 << order;
+        }
     }
 
-    fn alloc(&mut self) -> Option<PhysPageNum> {
-        if let Some(ppn) = self.recycled.pop() {
-            return Some(ppn.into());
-        }
+    fn alloc(&mut self, order: usize) -> Option<PhysPageNum> {
+        let found = (order..=MAX_ORDER).find(|&v| !self.free_lists[v].is_empty())?;
+        let ppn = self.free_lists[found].pop_first()?;
 
-        if self.current == self.end {
-            None
-        } else {
-            let ppn = self.current;
-            self.current += 1;
-            Some(ppn.into())
+        for split in (order..found).rev() {
+            self.free_lists[split].insert(ppn + (1 << split));
         }
+
+        self.free_count -= 1 << order;
+        self.ref_counts[ppn - self.start] = 1;
+
+        Some(ppn.into())
     }
 
-    fn dealloc(&mut self, ppn: PhysPageNum) {
+    fn dealloc(&mut self, ppn: PhysPageNum, order: usize) {
         assert!(
-            ppn.0 < self.current,
+            self.start <= ppn.0 && ppn.0 < self.end,
             "Frame ppn={:#x} has not been allocated!",
             ppn.0
         );
+        assert_eq!(
+            self.ref_counts[ppn.0 - self.start],
+            0,
+            "Frame ppn={:#x} is still referenced!",
+            ppn.0
+        );
+
+        self.free_count += 1 << order;
+
+        let mut ppn = ppn.0;
+        let mut order = order;
+        while order < MAX_ORDER {
+            let buddy = ppn ^ (1 << order);
+            if !self.free_lists[order].remove(&buddy) {
+                break;
+            }
+            ppn = ppn.min(buddy);
+            order += 1;
+        }
+
         assert!(
-            !self.recycled.contains(&ppn.0),
+            self.free_lists[order].insert(ppn),
             "Frame ppn={:#x} has been deallocated!",
-            ppn.0
+            ppn
         );
+    }
 
-        self.recycled.push(ppn.0);
+    fn inc_ref(&mut self, ppn: PhysPageNum) {
+        let count = &mut self.ref_counts[ppn.0 - self.start];
+        assert!(*count > 0, "Frame ppn={:#x} has been deallocated!", ppn.0);
+        *count += 1;
+    }
+
+    fn dec_ref(&mut self, ppn: PhysPageNum) -> usize {
+        let count = &mut self.ref_counts[ppn.0 - self.start];
+        assert!(*count > 0, "Frame ppn={:#x} has been deallocated!", ppn.0);
+        *count -= 1;
+        *count as usize
+    }
+
+    fn ref_count(&self, ppn: PhysPageNum) -> usize {
+        self.ref_counts[ppn.0 - self.start] as usize
     }
 
     pub fn free_frames_count(&self) -> usize {
-        self.recycled.len() + self.end - self.current
+        self.free_count
     }
 }
 
@@ -104,10 +166,54 @@ impl Frame {
         ppn.clear();
         Self { ppn }
     }
+
+    pub fn ref_count(&self) -> usize {
+        FRAME_ALLOCATOR.lock().ref_count(self.ppn)
+    }
+}
+
+impl Clone for Frame {
+    fn clone(&self) -> Self {
+        FRAME_ALLOCATOR.lock().inc_ref(self.ppn);
+        Self { ppn: self.ppn }
+    }
 }
 
 impl Drop for Frame {
     fn drop(&mut self) {
-        dealloc(self.ppn);
+        let mut allocator = FRAME_ALLOCATOR.lock();
+        if allocator.dec_ref(self.ppn) == 0 {
+            allocator.dealloc(self.ppn, 0);
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ContiguousFrames {
+    pub ppn: PhysPageNum,
+    count: usize,
+    order: usize,
+}
+
+#[allow(dead_code)]
+impl ContiguousFrames {
+    fn new(ppn: PhysPageNum, count: usize, order: usize) -> Self {
+        for i in 0..count {
+            PhysPageNum(ppn.0 + i).clear();
+        }
+
+        Self { ppn, count, order }
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+}
+
+impl Drop for ContiguousFrames {
+    fn drop(&mut self) {
+        let mut allocator = FRAME_ALLOCATOR.lock();
+        allocator.dec_ref(self.ppn);
+        allocator.dealloc(self.ppn, self.order);
     }
 }
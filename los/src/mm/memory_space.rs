@@ -2,19 +2,39 @@ use super::{
     address::{PhysPageNum, VPNRange, VirtAddr, VirtPageNum},
     frame_allocator::{self, Frame},
     page_table::{Flags, PageTable},
-    KERNEL_MEMORY_SPACE,
+    tlb, KERNEL_MEMORY_SPACE,
 };
 use crate::{
-    config::{GUARD_PAGE_COUNT, KERNEL_STACK_SIZE, USER_STACK_SIZE},
+    config::{
+        BOOT_STACK_SIZE, GUARD_PAGE_COUNT, KERNEL_STACK_SIZE, MAX_EXEC_ARGS_SIZE, MAX_HARTS,
+        MAX_THREADS, USER_STACK_SIZE,
+    },
     error,
-    mm::address::{PhysAddr, PAGE_SIZE},
+    mm::{
+        address::{PhysAddr, PAGE_SIZE},
+        UserSlice,
+    },
     task::pid::Pid,
 };
-use alloc::{collections::btree_map::BTreeMap, format, string::ToString, vec::Vec};
+use alloc::{
+    collections::{btree_map::BTreeMap, btree_set::BTreeSet},
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
 use bitflags::bitflags;
-use core::{arch::asm, ops::Range};
+use core::{arch::asm, mem, ops::Range};
 use elf::endian::AnyEndian;
-use riscv::register::satp;
+use riscv::register::{satp, time};
+
+const AT_NULL: usize = 0;
+const AT_PHDR: usize = 3;
+const AT_PHENT: usize = 4;
+const AT_PHNUM: usize = 5;
+const AT_PAGESZ: usize = 6;
+const AT_ENTRY: usize = 9;
+const AT_RANDOM: usize = 25;
 
 #[derive(Debug)]
 struct MapArea {
@@ -22,6 +42,9 @@ struct MapArea {
     data_frames: BTreeMap<VirtPageNum, Frame>,
     map_type: MapType,
     map_perm: MapPermission,
+    /// Whether this is the area `brk` grows. Other areas may start where
+    /// the heap starts while it is empty, so it is not found by address.
+    heap: bool,
 }
 
 impl MapArea {
@@ -39,6 +62,7 @@ impl MapArea {
             data_frames,
             map_type,
             map_perm,
+            heap: false,
         }
     }
     fn map(&mut self, page_table: &mut PageTable) -> error::Result<()> {
@@ -59,6 +83,7 @@ impl MapArea {
                         }
                     };
                 }
+                MapType::Lazy => continue,
             };
 
             if let Err(err) = page_table.map(vpn, ppn, self.map_perm.into()) {
@@ -71,7 +96,142 @@ impl MapArea {
         Ok(())
     }
 
-    #[allow(dead_code)]
+    fn contains(&self, vpn: VirtPageNum) -> bool {
+        self.vpn_range.start() <= vpn && vpn < self.vpn_range.end()
+    }
+
+    fn overlaps(&self, vpn_range: VPNRange) -> bool {
+        self.vpn_range.start().max(vpn_range.start()) < self.vpn_range.end().min(vpn_range.end())
+    }
+
+    /// Whether the area is the trap context or part of the user stack of
+    /// the thread in `slot`.
+    fn in_thread_slot(&self, slot: usize) -> bool {
+        let (stack_start_va, stack_end_va) = user_stack_position(slot);
+        let start = self.vpn_range.start();
+        start == trap_context_va(slot).floor_vpn()
+            || (stack_start_va.floor_vpn() <= start && start < stack_end_va.floor_vpn())
+    }
+
+    fn is_mapped(&self, vpn: VirtPageNum) -> bool {
+        self.map_type != MapType::Lazy || self.data_frames.contains_key(&vpn)
+    }
+
+    fn is_shareable(&self) -> bool {
+        matches!(self.map_type, MapType::Framed | MapType::Lazy)
+            && self.map_perm.contains(MapPermission::U)
+    }
+
+    fn shared_perm(&self) -> MapPermission {
+        self.map_perm - MapPermission::W
+    }
+
+    fn page_perm(&self, frame: &Frame) -> MapPermission {
+        if frame.ref_count() > 1 {
+            self.shared_perm()
+        } else {
+            self.map_perm
+        }
+    }
+
+    fn check_access(&self, fault: PageFault) -> error::Result<()> {
+        let required = match fault {
+            PageFault::Load => MapPermission::R,
+            PageFault::Store => MapPermission::W,
+            PageFault::Instruction => MapPermission::X,
+        };
+
+        if !self.map_perm.contains(required) {
+            return Err(error::KernelError::PageFault(format!(
+                "{fault:?} access denied, perm: {:?}",
+                self.map_perm
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn map_lazy_page(&mut self, vpn: VirtPageNum, page_table: &mut PageTable) -> error::Result<()> {
+        let frame = frame_allocator::alloc().ok_or(error::KernelError::AllocFrame(
+            "alloc lazy data frame failed".to_string(),
+        ))?;
+        page_table.map(vpn, frame.ppn, self.map_perm.into())?;
+        self.data_frames.insert(vpn, frame);
+
+        Ok(())
+    }
+
+    fn copy_on_write(&mut self, vpn: VirtPageNum, page_table: &mut PageTable) -> error::Result<()> {
+        let frame = self
+            .data_frames
+            .get_mut(&vpn)
+            .ok_or(error::KernelError::PageFault(format!(
+                "no frame for page: {vpn:?}"
+            )))?;
+
+        if frame.ref_count() > 1 {
+            let mut new_frame = frame_allocator::alloc().ok_or(error::KernelError::AllocFrame(
+                "alloc copy-on-write frame failed".to_string(),
+            ))?;
+            let mut src_ppn = frame.ppn;
+            unsafe {
+                new_frame
+                    .ppn
+                    .get_bytes_array_mut()
+                    .copy_from_slice(src_ppn.get_bytes_array_mut());
+            }
+            *frame = new_frame;
+        }
+
+        page_table.remap(vpn, frame.ppn, self.map_perm.into())?;
+        // threads on other harts may still translate to the shared frame
+        tlb::shootdown(vpn.into(), PAGE_SIZE);
+
+        Ok(())
+    }
+
+    fn resize(&mut self, new_end: VirtPageNum, page_table: &mut PageTable) {
+        assert_eq!(self.map_type, MapType::Lazy);
+
+        let start = self.vpn_range.start();
+        let old_end = self.vpn_range.end();
+        for vpn in VPNRange::new(new_end.max(start), old_end) {
+            if self.data_frames.remove(&vpn).is_some() {
+                page_table.unmap(vpn);
+            }
+        }
+
+        self.vpn_range = VPNRange::new(start, new_end.max(start));
+    }
+
+    fn split_off(&mut self, at: VirtPageNum) -> Self {
+        assert!(self.vpn_range.start() < at && at < self.vpn_range.end());
+
+        let tail = Self {
+            vpn_range: VPNRange::new(at, self.vpn_range.end()),
+            data_frames: self.data_frames.split_off(&at),
+            map_type: self.map_type,
+            map_perm: self.map_perm,
+            heap: false,
+        };
+        self.vpn_range = VPNRange::new(self.vpn_range.start(), at);
+
+        tail
+    }
+
+    fn set_perm(
+        &mut self,
+        map_perm: MapPermission,
+        page_table: &mut PageTable,
+    ) -> error::Result<()> {
+        self.map_perm = map_perm;
+        for (vpn, frame) in self.data_frames.iter() {
+            page_table.remap(*vpn, frame.ppn, self.page_perm(frame).into())?;
+        }
+
+        Ok(())
+    }
+
     fn unmap(&mut self, page_table: &mut PageTable) {
         for vpn in self.vpn_range {
             match self.map_type {
@@ -80,15 +240,28 @@ impl MapArea {
                     self.data_frames.remove(&vpn);
                     page_table.unmap(vpn);
                 }
+                MapType::Lazy => {
+                    if self.data_frames.remove(&vpn).is_some() {
+                        page_table.unmap(vpn);
+                    }
+                }
             }
         }
     }
 }
 
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum PageFault {
+    Load,
+    Store,
+    Instruction,
+}
+
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum MapType {
     Identical,
     Framed,
+    Lazy,
 }
 
 bitflags! {
@@ -111,6 +284,9 @@ impl From<MapPermission> for Flags {
 pub struct MemorySpace {
     l3_page_table: PageTable,
     areas: Vec<MapArea>,
+    heap_bottom: VirtAddr,
+    brk: VirtAddr,
+    thread_slots: BTreeSet<usize>,
 }
 
 impl MemorySpace {
@@ -119,6 +295,9 @@ impl MemorySpace {
             Ok(l3_page_table) => Ok(Self {
                 l3_page_table,
                 areas: Vec::new(),
+                heap_bottom: VirtAddr(0),
+                brk: VirtAddr(0),
+                thread_slots: BTreeSet::new(),
             }),
             Err(err) => Err(error::KernelError::CreatePagetable(format!(
                 "create pagetable for bare memory space failed: {err:?}"
@@ -134,10 +313,13 @@ impl MemorySpace {
             fn erodata();
             fn sdata();
             fn edata();
-            fn sbtstack();
-            fn ebtstack();
+            fn boot_stack_lower_bound();
+            fn kernel_trap_stack_lower_bound();
+            fn kernel_trap_stack_top();
             fn sbss();
             fn ebss();
+            fn sksymtab();
+            fn eksymtab();
             fn ekernel();
         }
 
@@ -172,13 +354,28 @@ impl MemorySpace {
             )
             .expect("add .data area must succeed");
 
+        // the guard pages under each boot stack stay unmapped
+        let guard_size = GUARD_PAGE_COUNT * PAGE_SIZE;
+        for hartid in 0..MAX_HARTS {
+            let stack_bottom = boot_stack_lower_bound as usize
+                + hartid * (guard_size + BOOT_STACK_SIZE)
+                + guard_size;
+            mem_space
+                .add_identical_area(
+                    stack_bottom.into(),
+                    (stack_bottom + BOOT_STACK_SIZE).into(),
+                    MapPermission::R | MapPermission::W,
+                )
+                .expect("add boot stack area must succeed");
+        }
+
         mem_space
             .add_identical_area(
-                (sbtstack as usize).into(),
-                (ebtstack as usize).into(),
+                (kernel_trap_stack_lower_bound as usize).into(),
+                (kernel_trap_stack_top as usize).into(),
                 MapPermission::R | MapPermission::W,
             )
-            .expect("add .bss area must succeed");
+            .expect("add kernel trap stack area must succeed");
 
         mem_space
             .add_identical_area(
@@ -188,6 +385,14 @@ impl MemorySpace {
             )
             .expect("add .bss area must succeed");
 
+        mem_space
+            .add_identical_area(
+                (sksymtab as usize).into(),
+                (eksymtab as usize).into(),
+                MapPermission::R,
+            )
+            .expect("add .ksymtab area must succeed");
+
         mem_space
             .add_identical_area(
                 (ekernel as usize).into(),
@@ -199,28 +404,72 @@ impl MemorySpace {
         mem_space
     }
 
-    pub fn new_elf(elf_data: &[u8]) -> error::Result<(Self, usize, usize)> {
+    /// Builds the address space of a program and lays out its arguments on
+    /// the user stack. Returns the space, the stack pointer, which points at
+    /// argc, and the entry point.
+    pub fn new_elf(
+        elf_data: &[u8],
+        argv: &[String],
+        envp: &[String],
+    ) -> error::Result<(Self, usize, usize)> {
         let mut mem_space = Self::new_bare().map_err(|e| {
             error::KernelError::CreateMemorySpace(format!("create memory space failed: {e:?}"))
         })?;
 
         let mut max_vpn = VirtPageNum(0);
+        let mut phdr_va = 0;
 
         let file = elf::ElfBytes::<AnyEndian>::minimal_parse(elf_data)
             .map_err(|e| error::KernelError::ParseELF(format!("parse elf failed: {e:?}")))?;
+        let ph_offset = file.ehdr.e_phoff;
         for segment in file
             .segments()
             .ok_or(error::KernelError::ELFProgramHeader("".to_string()))?
             .iter()
             .filter(|v| v.p_type == elf::abi::PT_LOAD)
         {
+            let start_va = VirtAddr(segment.p_vaddr as usize);
+            if !start_va.is_page_aligned() {
+                return Err(error::KernelError::ParseELF(format!(
+                    "segment va {:#x} is not page aligned",
+                    segment.p_vaddr
+                )));
+            }
+            if segment.p_filesz > segment.p_memsz {
+                return Err(error::KernelError::ParseELF(format!(
+                    "segment (va: {:#x}) file size {:#x} is over its memory size {:#x}",
+                    segment.p_vaddr, segment.p_filesz, segment.p_memsz
+                )));
+            }
+            let file_end = segment
+                .p_offset
+                .checked_add(segment.p_filesz)
+                .filter(|&end| end <= elf_data.len() as u64)
+                .ok_or_else(|| {
+                    error::KernelError::ParseELF(format!(
+                        "segment (va: {:#x}) data is past the end of the file",
+                        segment.p_vaddr
+                    ))
+                })?;
+            let end_va = segment
+                .p_vaddr
+                .checked_add(segment.p_memsz)
+                .map(|end| VirtAddr(end as usize))
+                .filter(|&end| end <= mmap_base_va())
+                .ok_or_else(|| {
+                    error::KernelError::ParseELF(format!(
+                        "segment (va: {:#x}) ends past the program image space",
+                        segment.p_vaddr
+                    ))
+                })?;
+
             let data = file.segment_data(&segment).map_err(|e| {
                 error::KernelError::ELFSegmentData(format!("read segment data failed: {e:?}"))
             })?;
-            let start_va = VirtAddr(segment.p_vaddr as usize);
-            assert!(start_va.is_page_aligned());
+            if (segment.p_offset..file_end).contains(&ph_offset) {
+                phdr_va = (segment.p_vaddr + ph_offset - segment.p_offset) as usize;
+            }
 
-            let end_va = start_va + segment.p_memsz as usize;
             let mut map_perm = MapPermission::U;
             if segment.p_flags & elf::abi::PF_R != 0 {
                 map_perm |= MapPermission::R;
@@ -250,33 +499,148 @@ impl MemorySpace {
             error::KernelError::AddMapArea(format!("add trampoline map area failed: {e:?}"))
         })?;
 
-        let trap_context_start_va = trap_context_va();
-        mem_space
-            .add_framed_area(
-                trap_context_start_va,
-                trap_context_start_va + PAGE_SIZE,
-                MapPermission::R | MapPermission::W,
-            )
-            .map_err(|e| {
-                error::KernelError::AddMapArea(format!("add trap context map area failed: {e:?}"))
-            })?;
+        let heap_bottom = VirtAddr::from(max_vpn);
+        let mut heap_area = MapArea::new(
+            heap_bottom,
+            heap_bottom,
+            MapType::Lazy,
+            MapPermission::U | MapPermission::R | MapPermission::W,
+        );
+        heap_area.heap = true;
+        mem_space.add_map_area(heap_area).map_err(|e| {
+            error::KernelError::AddMapArea(format!("add user heap map area failed: {e:?}"))
+        })?;
+        mem_space.heap_bottom = heap_bottom;
+        mem_space.brk = heap_bottom;
+
+        let entry = file.ehdr.e_entry as usize;
+        let auxv = [
+            (AT_PHDR, phdr_va),
+            (AT_PHENT, file.ehdr.e_phentsize as usize),
+            (AT_PHNUM, file.ehdr.e_phnum as usize),
+            (AT_PAGESZ, PAGE_SIZE),
+            (AT_ENTRY, entry),
+        ];
+        let user_sp = mem_space.add_thread_areas(0)?;
+        let user_sp = mem_space.push_exec_args(user_sp, argv, envp, &auxv)?;
+
+        Ok((mem_space, user_sp, entry))
+    }
 
-        let user_stack_start_va = VirtPageNum(max_vpn.0 + GUARD_PAGE_COUNT).into();
-        let user_stack_end_va = user_stack_start_va + USER_STACK_SIZE;
-        mem_space
-            .add_framed_area(
-                user_stack_start_va,
-                user_stack_end_va,
-                MapPermission::U | MapPermission::R | MapPermission::W,
-            )
-            .map_err(|e| {
-                error::KernelError::AddMapArea(format!("add user stack map area failed: {e:?}"))
-            })?;
-        Ok((
-            mem_space,
-            user_stack_end_va.into(),
-            file.ehdr.e_entry as usize,
-        ))
+    /// Lays out argc, argv, envp and the auxiliary vector below `user_sp`
+    /// like the Linux ELF loader, with the strings and the AT_RANDOM bytes
+    /// above them. Returns the new stack pointer.
+    fn push_exec_args(
+        &mut self,
+        user_sp: usize,
+        argv: &[String],
+        envp: &[String],
+        auxv: &[(usize, usize)],
+    ) -> error::Result<usize> {
+        const WORD: usize = mem::size_of::<usize>();
+        const RANDOM_LEN: usize = 16;
+
+        let strings_len: usize = argv.iter().chain(envp).map(|v| v.len() + 1).sum();
+        if strings_len > MAX_EXEC_ARGS_SIZE {
+            return Err(error::KernelError::ArgumentListTooLong(format!(
+                "exec arguments take {strings_len} bytes, max: {MAX_EXEC_ARGS_SIZE}"
+            )));
+        }
+
+        let strings_va = user_sp - strings_len;
+        let random_va = (strings_va - RANDOM_LEN) & !(WORD - 1);
+        // argc, argv and envp with their NULL ends, auxv with AT_RANDOM and AT_NULL
+        let word_count = 1 + (argv.len() + 1) + (envp.len() + 1) + (auxv.len() + 2) * 2;
+        let sp = (random_va - word_count * WORD) & !0xf;
+
+        let mut stack = vec![0u8; user_sp - sp];
+        let mut words = Vec::with_capacity(word_count);
+        words.push(argv.len());
+
+        let mut string_va = strings_va;
+        for strings in [argv, envp] {
+            for string in strings {
+                let offset = string_va - sp;
+                stack[offset..offset + string.len()].copy_from_slice(string.as_bytes());
+                words.push(string_va);
+                string_va += string.len() + 1;
+            }
+            words.push(0);
+        }
+
+        for &(key, value) in auxv.iter().chain(&[(AT_RANDOM, random_va), (AT_NULL, 0)]) {
+            words.extend([key, value]);
+        }
+
+        let mut seed = time::read() as u64;
+        for chunk in stack[random_va - sp..random_va - sp + RANDOM_LEN].chunks_mut(8) {
+            // splitmix64, enough to seed user space without a real entropy source
+            seed = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = seed;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            chunk.copy_from_slice(&(z ^ (z >> 31)).to_ne_bytes());
+        }
+
+        for (i, word) in words.iter().enumerate() {
+            stack[i * WORD..(i + 1) * WORD].copy_from_slice(&word.to_ne_bytes());
+        }
+
+        UserSlice::new(sp, stack.len()).copy_to_user(self, &stack)?;
+
+        Ok(sp)
+    }
+
+    /// Reserves a thread slot, mapping its trap context page and its lazy
+    /// user stack. Returns the slot and the top of the user stack.
+    pub fn alloc_thread_slot(&mut self) -> error::Result<(usize, usize)> {
+        let slot = (0..MAX_THREADS)
+            .find(|v| !self.thread_slots.contains(v))
+            .ok_or(error::KernelError::AllocThreadSlot(format!(
+                "no free thread slot, max: {MAX_THREADS}"
+            )))?;
+        let user_sp = self.add_thread_areas(slot)?;
+
+        Ok((slot, user_sp))
+    }
+
+    pub fn free_thread_slot(&mut self, slot: usize) -> error::Result<()> {
+        let (user_stack_start_va, user_stack_end_va) = user_stack_position(slot);
+        self.remove_area_by_start_va(trap_context_va(slot))?;
+        // the stack is an ordinary user area, the thread may have unmapped
+        // or split it, so unmap whatever is left of it
+        self.munmap(
+            user_stack_start_va,
+            user_stack_end_va.0 - user_stack_start_va.0,
+        )?;
+        self.thread_slots.remove(&slot);
+
+        Ok(())
+    }
+
+    fn add_thread_areas(&mut self, slot: usize) -> error::Result<usize> {
+        let trap_context_start_va = trap_context_va(slot);
+        self.add_framed_area(
+            trap_context_start_va,
+            trap_context_start_va + PAGE_SIZE,
+            MapPermission::R | MapPermission::W,
+        )
+        .map_err(|e| {
+            error::KernelError::AddMapArea(format!("add trap context map area failed: {e:?}"))
+        })?;
+
+        let (user_stack_start_va, user_stack_end_va) = user_stack_position(slot);
+        self.add_lazy_area(
+            user_stack_start_va,
+            user_stack_end_va,
+            MapPermission::U | MapPermission::R | MapPermission::W,
+        )
+        .map_err(|e| {
+            error::KernelError::AddMapArea(format!("add user stack map area failed: {e:?}"))
+        })?;
+        self.thread_slots.insert(slot);
+
+        Ok(user_stack_end_va.into())
     }
 
     fn add_trampoline_area(&mut self) -> error::Result<()> {
@@ -315,7 +679,13 @@ impl MemorySpace {
 
     fn add_map_area_with_data(&mut self, map_area: MapArea, data: &[u8]) -> error::Result<()> {
         assert_eq!(map_area.map_type, MapType::Framed);
-        assert!(map_area.vpn_range.memory_size() >= data.len());
+        if map_area.vpn_range.memory_size() < data.len() {
+            return Err(error::KernelError::ParseELF(format!(
+                "{:#x} bytes of data do not fit in the {:#x} bytes area",
+                data.len(),
+                map_area.vpn_range.memory_size()
+            )));
+        }
 
         let vpn_range = map_area.vpn_range;
         self.add_map_area(map_area)?;
@@ -344,6 +714,15 @@ impl MemorySpace {
         self.add_map_area(MapArea::new(start_va, end_va, MapType::Framed, map_perm))
     }
 
+    pub fn add_lazy_area(
+        &mut self,
+        start_va: VirtAddr,
+        end_va: VirtAddr,
+        map_perm: MapPermission,
+    ) -> error::Result<()> {
+        self.add_map_area(MapArea::new(start_va, end_va, MapType::Lazy, map_perm))
+    }
+
     pub fn add_identical_area(
         &mut self,
         start_va: VirtAddr,
@@ -368,7 +747,9 @@ impl MemorySpace {
         let area = self.areas.remove(idx);
 
         for vpn in area.vpn_range {
-            self.l3_page_table.unmap(vpn);
+            if area.is_mapped(vpn) {
+                self.l3_page_table.unmap(vpn);
+            }
         }
 
         Ok(())
@@ -386,18 +767,47 @@ impl MemorySpace {
         &self.l3_page_table
     }
 
-    pub fn fork(&self) -> error::Result<Self> {
+    /// Copies the memory space for a child process whose only thread is the
+    /// one in `slot`. The stacks and trap contexts of other threads are left
+    /// out.
+    pub fn fork(&mut self, slot: usize) -> error::Result<Self> {
         let mut forked_mem_space = Self::new_bare().map_err(|e| {
             error::KernelError::CreateMemorySpace(format!("create memory space failed: {e:?}"))
         })?;
 
         for map_area in self.areas.iter() {
-            let new_map_area = MapArea::new(
+            if self
+                .thread_slots
+                .iter()
+                .any(|&other| other != slot && map_area.in_thread_slot(other))
+            {
+                continue;
+            }
+
+            let mut new_map_area = MapArea::new(
                 map_area.vpn_range.start().into(),
                 map_area.vpn_range.end().into(),
                 map_area.map_type,
                 map_area.map_perm,
             );
+            new_map_area.heap = map_area.heap;
+
+            if map_area.is_shareable() {
+                let shared_perm = map_area.shared_perm();
+                for (vpn, frame) in map_area.data_frames.iter() {
+                    forked_mem_space
+                        .l3_page_table
+                        .map(*vpn, frame.ppn, shared_perm.into())?;
+                    self.l3_page_table
+                        .remap(*vpn, frame.ppn, shared_perm.into())?;
+                    new_map_area.data_frames.insert(*vpn, frame.clone());
+                }
+                // the parent's pages are read-only now, drop the writable
+                // translations still cached
+                unsafe { asm!("sfence.vma") };
+                forked_mem_space.areas.push(new_map_area);
+                continue;
+            }
 
             let vpn_range = new_map_area.vpn_range;
             forked_mem_space.add_map_area(new_map_area).map_err(|err| {
@@ -419,14 +829,343 @@ impl MemorySpace {
         forked_mem_space.add_trampoline_area().map_err(|err| {
             error::KernelError::Common(format!("add trampoline area failed: {err:?}"))
         })?;
+        forked_mem_space.heap_bottom = self.heap_bottom;
+        forked_mem_space.brk = self.brk;
+        forked_mem_space.thread_slots.insert(slot);
+        tlb::shootdown_all();
 
         Ok(forked_mem_space)
     }
 
-    pub fn trap_context_mut_ptr<T>(&mut self) -> *mut T {
+    pub fn handle_page_fault(&mut self, va: VirtAddr, fault: PageFault) -> error::Result<()> {
+        let vpn = va.floor_vpn();
+        let area = self.areas.iter_mut().find(|v| v.contains(vpn)).ok_or(
+            error::KernelError::MapAreaNotFound(format!("map area not found: {:#x}", va.0)),
+        )?;
+
+        area.check_access(fault)?;
+
+        if !area.is_mapped(vpn) {
+            return area.map_lazy_page(vpn, &mut self.l3_page_table);
+        }
+
+        match fault {
+            PageFault::Store => area.copy_on_write(vpn, &mut self.l3_page_table),
+            _ => Err(error::KernelError::PageFault(format!(
+                "unhandled {fault:?} page fault: {:#x}",
+                va.0
+            ))),
+        }
+    }
+
+    pub fn translate_user_vpn(
+        &mut self,
+        vpn: VirtPageNum,
+        fault: PageFault,
+    ) -> error::Result<PhysPageNum> {
+        let area = self
+            .areas
+            .iter()
+            .find(|v| v.contains(vpn) && v.map_perm.contains(MapPermission::U))
+            .ok_or(error::KernelError::BadAddress(format!(
+                "user map area not found: {:#x}",
+                VirtAddr::from(vpn).0
+            )))?;
+        area.check_access(fault)
+            .map_err(|err| error::KernelError::BadAddress(format!("{err:?}")))?;
+
+        let ready = match self.l3_page_table.translate_vpn(vpn) {
+            Ok(pte) => fault != PageFault::Store || pte.is_writable(),
+            Err(_) => false,
+        };
+        if !ready {
+            self.handle_page_fault(vpn.into(), fault)?;
+        }
+
+        let pte = self.l3_page_table.translate_vpn(vpn)?;
+        if !pte.is_user() {
+            return Err(error::KernelError::BadAddress(format!(
+                "page is not user accessible: {:#x}",
+                VirtAddr::from(vpn).0
+            )));
+        }
+
+        Ok(pte.ppn())
+    }
+
+    pub fn brk(&self) -> VirtAddr {
+        self.brk
+    }
+
+    pub fn set_brk(&mut self, new_brk: VirtAddr) -> error::Result<()> {
+        if new_brk < self.heap_bottom {
+            return Err(error::KernelError::Brk(format!(
+                "brk {:#x} below heap bottom {:#x}",
+                new_brk.0, self.heap_bottom.0
+            )));
+        }
+
+        if new_brk > self.heap_limit() {
+            return Err(error::KernelError::Brk(format!(
+                "brk {:#x} above heap limit {:#x}",
+                new_brk.0,
+                self.heap_limit().0
+            )));
+        }
+
+        let heap_start_vpn = self.heap_bottom.floor_vpn();
+        let new_end_vpn = new_brk.ceil_vpn();
+        let new_heap_range = VPNRange::new(heap_start_vpn, new_end_vpn);
+        if self
+            .areas
+            .iter()
+            .any(|v| !v.heap && v.overlaps(new_heap_range))
+        {
+            return Err(error::KernelError::Brk(format!(
+                "brk {:#x} overlaps other map area",
+                new_brk.0
+            )));
+        }
+
+        let heap_area =
+            self.areas
+                .iter_mut()
+                .find(|v| v.heap)
+                .ok_or(error::KernelError::MapAreaNotFound(format!(
+                    "heap map area not found: {:#x}",
+                    self.heap_bottom.0
+                )))?;
+        let old_end_vpn = heap_area.vpn_range.end();
+        heap_area.resize(new_end_vpn, &mut self.l3_page_table);
+        if new_end_vpn < old_end_vpn {
+            tlb::shootdown(
+                new_end_vpn.into(),
+                (old_end_vpn.0 - new_end_vpn.0) * PAGE_SIZE,
+            );
+        }
+        self.brk = new_brk;
+
+        Ok(())
+    }
+
+    pub fn mmap(
+        &mut self,
+        hint: VirtAddr,
+        len: usize,
+        map_perm: MapPermission,
+        fixed: bool,
+    ) -> error::Result<VirtAddr> {
+        if len == 0 || len > user_stack_top_va().0 || !hint.is_page_aligned() {
+            return Err(error::KernelError::Mmap(format!(
+                "invalid mmap range: {:#x} {:#x}",
+                hint.0, len
+            )));
+        }
+
+        let page_count = len.div_ceil(PAGE_SIZE);
+        let in_user_space = hint
+            .0
+            .checked_add(page_count * PAGE_SIZE)
+            .is_some_and(|end| end <= user_stack_top_va().0);
+        let start_va = if fixed {
+            if !in_user_space {
+                return Err(error::KernelError::Mmap(format!(
+                    "mmap range out of user space: {:#x} {:#x}",
+                    hint.0, len
+                )));
+            }
+            if self.overlaps_heap_reserve(hint.floor_vpn(), page_count) {
+                return Err(error::KernelError::Mmap(format!(
+                    "mmap range overlaps the heap: {:#x} {:#x}",
+                    hint.0, len
+                )));
+            }
+            self.munmap(hint, len)?;
+            hint
+        } else if hint.0 != 0 && in_user_space && self.is_free(hint.floor_vpn(), page_count) {
+            hint
+        } else {
+            self.find_free_area(page_count)?
+        };
+
+        let end_va = start_va + page_count * PAGE_SIZE;
+        if end_va > user_stack_top_va() {
+            return Err(error::KernelError::Mmap(format!(
+                "mmap range out of user space: {:#x} {:#x}",
+                start_va.0, end_va.0
+            )));
+        }
+
+        self.add_lazy_area(start_va, end_va, map_perm | MapPermission::U)?;
+
+        Ok(start_va)
+    }
+
+    pub fn munmap(&mut self, start_va: VirtAddr, len: usize) -> error::Result<()> {
+        let vpn_range = self.user_vpn_range(start_va, len)?;
+
+        let mut idx = 0;
+        while idx < self.areas.len() {
+            let area = &self.areas[idx];
+            if !area.map_perm.contains(MapPermission::U) || !area.overlaps(vpn_range) {
+                idx += 1;
+                continue;
+            }
+
+            let mut area = self.areas.remove(idx);
+            if area.vpn_range.start() < vpn_range.start() {
+                let tail = area.split_off(vpn_range.start());
+                self.areas.push(area);
+                area = tail;
+            }
+            if area.vpn_range.end() > vpn_range.end() {
+                let tail = area.split_off(vpn_range.end());
+                self.areas.push(tail);
+            }
+
+            area.unmap(&mut self.l3_page_table);
+        }
+        tlb::shootdown(start_va, len);
+
+        Ok(())
+    }
+
+    pub fn mprotect(
+        &mut self,
+        start_va: VirtAddr,
+        len: usize,
+        map_perm: MapPermission,
+    ) -> error::Result<()> {
+        let vpn_range = self.user_vpn_range(start_va, len)?;
+        let map_perm = map_perm | MapPermission::U;
+
+        // like Linux, fail before changing anything if a page is unmapped
+        if let Some(hole) = self.first_unmapped_vpn(vpn_range) {
+            return Err(error::KernelError::MapAreaNotFound(format!(
+                "mprotect range {:#x} {:#x} is not mapped at {:#x}",
+                start_va.0,
+                len,
+                VirtAddr::from(hole).0
+            )));
+        }
+
+        let mut idx = 0;
+        let mut updated = Vec::new();
+        while idx < self.areas.len() {
+            let area = &self.areas[idx];
+            if !area.map_perm.contains(MapPermission::U) || !area.overlaps(vpn_range) {
+                idx += 1;
+                continue;
+            }
+
+            let mut area = self.areas.remove(idx);
+            if area.vpn_range.start() < vpn_range.start() {
+                let tail = area.split_off(vpn_range.start());
+                self.areas.push(area);
+                area = tail;
+            }
+            if area.vpn_range.end() > vpn_range.end() {
+                let tail = area.split_off(vpn_range.end());
+                self.areas.push(tail);
+            }
+
+            area.set_perm(map_perm, &mut self.l3_page_table)?;
+            updated.push(area);
+        }
+
+        self.areas.extend(updated);
+        tlb::shootdown(start_va, len);
+
+        Ok(())
+    }
+
+    /// The first page in `vpn_range` outside every user area.
+    fn first_unmapped_vpn(&self, vpn_range: VPNRange) -> Option<VirtPageNum> {
+        let mut ranges: Vec<_> = self
+            .areas
+            .iter()
+            .filter(|v| v.map_perm.contains(MapPermission::U) && v.overlaps(vpn_range))
+            .map(|v| (v.vpn_range.start(), v.vpn_range.end()))
+            .collect();
+        ranges.sort();
+
+        let mut covered = vpn_range.start();
+        for (start, end) in ranges {
+            if start > covered {
+                break;
+            }
+            covered = covered.max(end);
+        }
+
+        (covered < vpn_range.end()).then_some(covered)
+    }
+
+    fn user_vpn_range(&self, start_va: VirtAddr, len: usize) -> error::Result<VPNRange> {
+        let end = start_va.0.saturating_add(len);
+        if len == 0 || !start_va.is_page_aligned() || end > user_stack_top_va().0 {
+            return Err(error::KernelError::Mmap(format!(
+                "invalid user range: {:#x} {:#x}",
+                start_va.0, len
+            )));
+        }
+
+        Ok(VPNRange::new(
+            start_va.floor_vpn(),
+            (start_va + len).ceil_vpn(),
+        ))
+    }
+
+    /// The heap may grow up to where mmap starts placing areas.
+    fn heap_limit(&self) -> VirtAddr {
+        mmap_base_va().max(self.heap_bottom)
+    }
+
+    /// Whether the pages overlap the range the heap may grow into, which
+    /// counts as occupied even while the heap is smaller.
+    fn overlaps_heap_reserve(&self, start_vpn: VirtPageNum, page_count: usize) -> bool {
+        let heap_start = self.heap_bottom.floor_vpn();
+        let heap_end = self.heap_limit().ceil_vpn();
+        heap_start < start_vpn.offset(page_count) && start_vpn < heap_end
+    }
+
+    fn is_free(&self, start_vpn: VirtPageNum, page_count: usize) -> bool {
+        let vpn_range = VPNRange::new(start_vpn, start_vpn.offset(page_count));
+        !self.overlaps_heap_reserve(start_vpn, page_count)
+            && !self.areas.iter().any(|v| v.overlaps(vpn_range))
+    }
+
+    fn find_free_area(&self, page_count: usize) -> error::Result<VirtAddr> {
+        let mut ranges: Vec<_> = self
+            .areas
+            .iter()
+            .map(|v| (v.vpn_range.start(), v.vpn_range.end()))
+            .collect();
+        ranges.sort();
+
+        let mut candidate = mmap_base_va().floor_vpn();
+        for (start, end) in ranges {
+            if end <= candidate {
+                continue;
+            }
+            if candidate.offset(page_count) <= start {
+                break;
+            }
+            candidate = end;
+        }
+
+        if candidate.offset(page_count) > user_stack_top_va().floor_vpn() {
+            return Err(error::KernelError::NoMemory(format!(
+                "no free user address space for {page_count} pages"
+            )));
+        }
+
+        Ok(candidate.into())
+    }
+
+    pub fn trap_context_mut_ptr<T>(&self, slot: usize) -> *mut T {
         let mut trap_context_ppn = self
             .page_table()
-            .translate_vpn(trap_context_va().floor_vpn())
+            .translate_vpn(trap_context_va(slot).floor_vpn())
             .expect("translate trap context va must succeed")
             .ppn();
 
@@ -438,12 +1177,26 @@ pub fn trampoline_va() -> VirtAddr {
     VirtAddr::HIGH_HALF_MAX - PAGE_SIZE + 1
 }
 
+fn mmap_base_va() -> VirtAddr {
+    VirtAddr(0x10_0000_0000)
+}
+
+fn user_stack_top_va() -> VirtAddr {
+    VirtAddr::LOW_HALF_MAX + 1
+}
+
 fn kernel_stack_top_va() -> VirtAddr {
     trampoline_va()
 }
 
-pub fn trap_context_va() -> VirtAddr {
-    trampoline_va() - PAGE_SIZE
+pub fn trap_context_va(slot: usize) -> VirtAddr {
+    trampoline_va() - (slot + 1) * PAGE_SIZE
+}
+
+fn user_stack_position(slot: usize) -> (VirtAddr, VirtAddr) {
+    let end_va = user_stack_top_va() - slot * (USER_STACK_SIZE + GUARD_PAGE_COUNT * PAGE_SIZE);
+    let start_va = end_va - USER_STACK_SIZE;
+    (start_va, end_va)
 }
 
 fn kernel_stack_position(pid: usize) -> (VirtAddr, VirtAddr) {
@@ -479,9 +1232,56 @@ impl KernelStack {
 
 impl Drop for KernelStack {
     fn drop(&mut self) {
-        let (start_va, _end_va) = kernel_stack_position(self.pid);
+        let (start_va, end_va) = kernel_stack_position(self.pid);
         if let Err(err) = KERNEL_MEMORY_SPACE.lock().remove_area_by_start_va(start_va) {
             panic!("failed to dealloc kernel stack: {}", err);
         }
+        tlb::shootdown(start_va, end_va.0 - start_va.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test_case]
+    fn test_mmap_out_of_range_hint() {
+        let mut mem_space = MemorySpace::new_bare().expect("create memory space must succeed");
+        let map_perm = MapPermission::R | MapPermission::W;
+        let hint = VirtAddr(0xffff_ffff_ffff_f000);
+
+        // a hint is only a suggestion, an unusable one is ignored
+        let start_va = mem_space
+            .mmap(hint, 2 * PAGE_SIZE, map_perm, false)
+            .expect("mmap must succeed");
+        assert!(start_va + 2 * PAGE_SIZE <= user_stack_top_va());
+
+        assert!(mem_space.mmap(hint, 2 * PAGE_SIZE, map_perm, true).is_err());
+    }
+
+    #[test_case]
+    fn test_mprotect_rejects_holes() {
+        let mut mem_space = MemorySpace::new_bare().expect("create memory space must succeed");
+        let map_perm = MapPermission::R | MapPermission::W;
+        let start_va = mem_space
+            .mmap(VirtAddr(0), 3 * PAGE_SIZE, map_perm, false)
+            .expect("mmap must succeed");
+        mem_space
+            .munmap(start_va + PAGE_SIZE, PAGE_SIZE)
+            .expect("munmap must succeed");
+
+        assert!(mem_space
+            .mprotect(start_va, 3 * PAGE_SIZE, MapPermission::R)
+            .is_err());
+        // nothing changed, the first page is still writable
+        assert!(mem_space
+            .areas
+            .iter()
+            .any(|v| v.vpn_range.start() == start_va.floor_vpn()
+                && v.map_perm.contains(MapPermission::W)));
+
+        mem_space
+            .mprotect(start_va, PAGE_SIZE, MapPermission::R)
+            .expect("mprotect of a mapped page must succeed");
     }
 }
@@ -1,15 +1,12 @@
 use core::fmt::Debug;
-use core::mem;
 
 use alloc::format;
-use alloc::string::String;
 use alloc::vec::Vec;
 use alloc::{string::ToString, vec};
 use bitflags::bitflags;
 
 use crate::error;
 
-use super::address::{PhysAddr, VirtAddr};
 use super::{
     address::{PhysPageNum, VirtPageNum},
     frame_allocator::{self, Frame},
@@ -151,6 +148,17 @@ impl PageTable {
         Ok(())
     }
 
+    pub fn remap(&mut self, vpn: VirtPageNum, ppn: PhysPageNum, flags: Flags) -> error::Result<()> {
+        let pte = self
+            .find_pte_mut(vpn)
+            .ok_or(error::KernelError::PteNotFound(format!(
+                "remap a none page, vpn: {vpn:?}"
+            )))?;
+        unsafe { *pte = PageTableEntry::new(ppn, flags | Flags::V) };
+
+        Ok(())
+    }
+
     pub fn unmap(&mut self, vpn: VirtPageNum) {
         match self.find_pte_mut(vpn) {
             Some(pte) => {
@@ -193,13 +201,6 @@ impl PageTable {
         (8 << 60) | self.root_ppn.0
     }
 
-    pub fn from_satp(satp: usize) -> Self {
-        Self {
-            root_ppn: PhysPageNum::from(satp),
-            dir_frames: Vec::new(),
-        }
-    }
-
     pub fn translate_vpn(&self, vpn: VirtPageNum) -> error::Result<PageTableEntry> {
         self.find_pte_mut(vpn)
             .map(|pte| unsafe { *pte })
@@ -208,80 +209,6 @@ impl PageTable {
             )))
     }
 
-    pub fn translate_va(&self, va: VirtAddr) -> error::Result<PhysAddr> {
-        let pte = self.translate_vpn(va.floor_vpn())?;
-
-        let pa = PhysAddr::from(pte.ppn());
-        Ok(pa.offset(va.page_offset()))
-    }
-
-    pub fn translate_c_str(&self, ptr: VirtAddr) -> error::Result<String> {
-        let mut s = String::new();
-
-        let mut pa = self.translate_va(ptr)?;
-        loop {
-            let c = unsafe { *(pa.0 as *const u8) };
-            if c == 0 {
-                break;
-            }
-            s.push(c as char);
-            pa = pa.offset(1)
-        }
-
-        Ok(s)
-    }
-
-    pub fn translate_bytes(&mut self, ptr: VirtAddr, len: usize) -> error::Result<Vec<&mut [u8]>> {
-        let end_va = ptr + len;
-
-        let mut result: Vec<&'static mut [u8]> = Vec::new();
-
-        let mut addr = ptr;
-        while addr < end_va {
-            let addr_va = addr;
-
-            let page_vpn = addr_va.floor_vpn();
-            let page_ppn = self
-                .translate_vpn(page_vpn)
-                .map_err(|e| {
-                    error::KernelError::Translate(format!("translate start vpn failed: {e:?}"))
-                })?
-                .ppn();
-
-            let page_start_va = VirtAddr::from(page_vpn);
-            let page_end_va = VirtAddr::from(page_vpn.offset(1));
-
-            let chunk_end_va = page_end_va.0.min(end_va.0);
-            let chunk_start_va = addr_va.0;
-            let chunk_page_offset = addr_va.0 - page_start_va.0;
-            let chunk_len = chunk_end_va - chunk_start_va;
-
-            let page_start_pa = PhysAddr::from(page_ppn);
-            let chunk_start_pa = page_start_pa.0 + chunk_page_offset;
-            let chunk =
-                unsafe { core::slice::from_raw_parts_mut(chunk_start_pa as *mut u8, chunk_len) };
-
-            addr = addr + chunk_len;
-
-            result.push(chunk);
-        }
-
-        Ok(result)
-    }
-
-    pub fn translate_write<T>(&self, dst: *mut T, src: &T) -> error::Result<()> {
-        let size = mem::size_of::<T>();
-        let src_ptr = src as *const _ as usize;
-
-        for i in 0..size {
-            let dst_va = dst as usize + i;
-            let dst_pa = self.translate_va(dst_va.into())?;
-            unsafe { *(dst_pa.0 as *mut u8) = *((src_ptr + i) as *mut u8) }
-        }
-
-        Ok(())
-    }
-
     fn fork_dir_pte_frames(
         &mut self,
         src_ppns: &mut [PhysPageNum],
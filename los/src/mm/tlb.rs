@@ -0,0 +1,20 @@
+use core::arch::asm;
+
+use crate::{hart, sbi};
+
+use super::address::VirtAddr;
+
+/// Flushes the translations of `[start_va, start_va + size)` on every online
+/// hart, so no hart keeps using a mapping that has been removed or downgraded.
+pub fn shootdown(start_va: VirtAddr, size: usize) {
+    unsafe { asm!("sfence.vma") };
+
+    let remote_harts = hart::online_mask() & !(1 << hart::hart_id());
+    if remote_harts != 0 {
+        sbi::remote_sfence_vma(remote_harts, start_va.0, size);
+    }
+}
+
+pub fn shootdown_all() {
+    shootdown(VirtAddr(0), usize::MAX);
+}
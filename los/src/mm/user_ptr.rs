@@ -0,0 +1,180 @@
+use core::{marker::PhantomData, mem, mem::MaybeUninit};
+
+use alloc::{format, string::String, vec::Vec};
+
+use crate::error;
+
+use super::{
+    address::{VirtAddr, PAGE_SIZE},
+    memory_space::{MemorySpace, PageFault},
+};
+
+#[derive(Debug, Clone, Copy)]
+pub struct UserSlice {
+    start: VirtAddr,
+    len: usize,
+}
+
+impl UserSlice {
+    pub fn new(ptr: usize, len: usize) -> Self {
+        Self {
+            start: ptr.into(),
+            len,
+        }
+    }
+
+    pub fn copy_to_user(&self, mem_space: &mut MemorySpace, src: &[u8]) -> error::Result<usize> {
+        let len = self.len.min(src.len());
+        let mut copied = 0;
+        Self::new(self.start.0, len).for_each_chunk(mem_space, PageFault::Store, |chunk| {
+            chunk.copy_from_slice(&src[copied..copied + chunk.len()]);
+            copied += chunk.len();
+        })?;
+
+        Ok(len)
+    }
+
+    pub fn copy_from_user(
+        &self,
+        mem_space: &mut MemorySpace,
+        dst: &mut [u8],
+    ) -> error::Result<usize> {
+        let len = self.len.min(dst.len());
+        let mut copied = 0;
+        Self::new(self.start.0, len).for_each_chunk(mem_space, PageFault::Load, |chunk| {
+            dst[copied..copied + chunk.len()].copy_from_slice(chunk);
+            copied += chunk.len();
+        })?;
+
+        Ok(len)
+    }
+
+    fn check_range(&self) -> error::Result<VirtAddr> {
+        if self.len == 0 {
+            return Ok(self.start);
+        }
+
+        match self.start.0.checked_add(self.len) {
+            Some(end) if self.start.0 != 0 && end - 1 <= VirtAddr::LOW_HALF_MAX.0 => Ok(end.into()),
+            _ => Err(error::KernelError::BadAddress(format!(
+                "bad user buffer: {:#x}, len: {:#x}",
+                self.start.0, self.len
+            ))),
+        }
+    }
+
+    fn for_each_chunk(
+        &self,
+        mem_space: &mut MemorySpace,
+        fault: PageFault,
+        mut f: impl FnMut(&mut [u8]),
+    ) -> error::Result<()> {
+        let end = self.check_range()?;
+
+        let mut va = self.start;
+        while va < end {
+            let vpn = va.floor_vpn();
+            let mut ppn = mem_space.translate_user_vpn(vpn, fault)?;
+
+            let offset = va.page_offset();
+            let chunk_len = (PAGE_SIZE - offset).min(end.0 - va.0);
+            let page = unsafe { ppn.get_bytes_array_mut() };
+            f(&mut page[offset..offset + chunk_len]);
+
+            va = va + chunk_len;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct UserPtr<T> {
+    ptr: usize,
+    _marker: PhantomData<*mut T>,
+}
+
+impl<T: Copy> UserPtr<T> {
+    pub fn is_null(&self) -> bool {
+        self.ptr == 0
+    }
+
+    pub fn read(&self, mem_space: &mut MemorySpace) -> error::Result<T> {
+        let mut value = MaybeUninit::<T>::uninit();
+        let bytes = unsafe {
+            core::slice::from_raw_parts_mut(value.as_mut_ptr() as *mut u8, mem::size_of::<T>())
+        };
+        UserSlice::new(self.ptr, bytes.len()).copy_from_user(mem_space, bytes)?;
+
+        Ok(unsafe { value.assume_init() })
+    }
+
+    pub fn write(&self, mem_space: &mut MemorySpace, value: &T) -> error::Result<()> {
+        let bytes = unsafe {
+            core::slice::from_raw_parts(value as *const T as *const u8, mem::size_of::<T>())
+        };
+        UserSlice::new(self.ptr, bytes.len()).copy_to_user(mem_space, bytes)?;
+
+        Ok(())
+    }
+}
+
+impl<T> From<*const T> for UserPtr<T> {
+    fn from(value: *const T) -> Self {
+        Self {
+            ptr: value as usize,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> From<*mut T> for UserPtr<T> {
+    fn from(value: *mut T) -> Self {
+        Self {
+            ptr: value as usize,
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// Reads a NUL-terminated string from user memory, failing if no NUL is found
+/// within `max_len` bytes.
+pub fn read_c_str(
+    mem_space: &mut MemorySpace,
+    ptr: usize,
+    max_len: usize,
+) -> error::Result<String> {
+    let mut bytes = Vec::new();
+
+    let mut va = VirtAddr::from(ptr);
+    loop {
+        let remaining = max_len - bytes.len();
+        if remaining == 0 {
+            return Err(error::KernelError::BadAddress(format!(
+                "user string at {ptr:#x} exceeds {max_len} bytes"
+            )));
+        }
+
+        let chunk_len = (PAGE_SIZE - va.page_offset()).min(remaining);
+        let user_slice = UserSlice::new(va.0, chunk_len);
+        user_slice.check_range()?;
+
+        let mut ppn = mem_space.translate_user_vpn(va.floor_vpn(), PageFault::Load)?;
+        let page = unsafe { ppn.get_bytes_array_mut() };
+        let chunk = &page[va.page_offset()..va.page_offset() + chunk_len];
+
+        match chunk.iter().position(|&c| c == 0) {
+            Some(nul) => {
+                bytes.extend_from_slice(&chunk[..nul]);
+                break;
+            }
+            None => bytes.extend_from_slice(chunk),
+        }
+
+        va = va + chunk_len;
+    }
+
+    String::from_utf8(bytes).map_err(|err| {
+        error::KernelError::BadAddress(format!("user string at {ptr:#x} is not utf-8: {err:?}"))
+    })
+}
@@ -28,3 +28,21 @@ pub fn shutdown(failure: bool) -> ! {
 pub fn set_timer(timer: usize) {
     sbi_rt::set_timer(timer as u64);
 }
+
+pub fn hart_start(hartid: usize, start_addr: usize, opaque: usize) -> Result<(), usize> {
+    let ret = sbi_rt::hart_start(hartid, start_addr, opaque);
+
+    if ret.is_err() {
+        return Err(ret.error);
+    }
+
+    Ok(())
+}
+
+pub fn remote_sfence_vma(hart_mask: usize, start_addr: usize, size: usize) {
+    sbi_rt::remote_sfence_vma(
+        sbi_rt::HartMask::from_mask_base(hart_mask, 0),
+        start_addr,
+        size,
+    );
+}
@@ -1,36 +1,129 @@
 mod fs;
+mod mem;
 mod proc;
+mod signal;
 mod time;
 
-use crate::{println, timer::TimeVal};
-use fs::{sys_read, sys_write};
-use proc::{sys_exec, sys_exit, sys_fork, sys_getpid, sys_sched_yield, sys_wait};
-use time::sys_gettimeofday;
+use alloc::format;
 
+use crate::{
+    error,
+    fs::Stat,
+    task::signal::SignalAction,
+    timer::{TimeSpec, TimeVal},
+};
+use fs::{
+    sys_chdir, sys_close, sys_dup, sys_dup3, sys_fstat, sys_getcwd, sys_getdents64, sys_lseek,
+    sys_mkdirat, sys_openat, sys_pipe2, sys_read, sys_unlinkat, sys_write,
+};
+use mem::{sys_brk, sys_mmap, sys_mprotect, sys_munmap, sys_sbrk};
+use proc::{
+    sys_exec, sys_exit, sys_fork, sys_getpid, sys_getpriority, sys_gettid, sys_sched_yield,
+    sys_setpriority, sys_thread_create, sys_thread_join, sys_wait,
+};
+use signal::{sys_kill, sys_sigaction, sys_sigprocmask, sys_sigreturn};
+use time::{sys_gettimeofday, sys_nanosleep};
+
+pub const SYS_GETCWD: usize = 17;
+pub const SYS_DUP: usize = 23;
+pub const SYS_DUP3: usize = 24;
+pub const SYS_MKDIRAT: usize = 34;
+pub const SYS_UNLINKAT: usize = 35;
+pub const SYS_CHDIR: usize = 49;
+pub const SYS_OPENAT: usize = 56;
+pub const SYS_CLOSE: usize = 57;
+pub const SYS_PIPE2: usize = 59;
+pub const SYS_GETDENTS64: usize = 61;
+pub const SYS_LSEEK: usize = 62;
 pub const SYS_READ: usize = 63;
 pub const SYS_WRITE: usize = 64;
+pub const SYS_FSTAT: usize = 80;
 pub const SYS_EXIT: usize = 93;
+pub const SYS_NANOSLEEP: usize = 101;
 pub const SYS_SCHED_YIELD: usize = 124;
+pub const SYS_KILL: usize = 129;
+pub const SYS_SIGACTION: usize = 134;
+pub const SYS_SIGPROCMASK: usize = 135;
+pub const SYS_SIGRETURN: usize = 139;
+pub const SYS_SETPRIORITY: usize = 140;
+pub const SYS_GETPRIORITY: usize = 141;
 pub const SYS_GETTIMEOFDAY: usize = 169;
 pub const SYS_GETPID: usize = 172;
+pub const SYS_GETTID: usize = 178;
+pub const SYS_BRK: usize = 214;
+pub const SYS_MUNMAP: usize = 215;
 pub const SYS_FORK: usize = 220;
 pub const SYS_EXEC: usize = 221;
+pub const SYS_MMAP: usize = 222;
+pub const SYS_MPROTECT: usize = 226;
 pub const SYS_WAITPID: usize = 260;
 
-pub fn syscall(id: usize, arg0: usize, arg1: usize, arg2: usize) -> usize {
-    match id {
-        SYS_READ => sys_read(arg0, arg1 as *mut u8, arg2) as usize,
-        SYS_WRITE => sys_write(arg0, arg1 as *const u8, arg2) as usize,
+// los specific syscalls
+pub const SYS_SBRK: usize = 1000;
+pub const SYS_THREAD_CREATE: usize = 1001;
+pub const SYS_THREAD_JOIN: usize = 1002;
+
+/// Dispatches a syscall. Failures are returned to user space as a negated
+/// errno, like Linux does.
+pub fn syscall(
+    id: usize,
+    arg0: usize,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+    arg5: usize,
+) -> usize {
+    let result = match id {
+        SYS_GETCWD => sys_getcwd(arg0 as *mut u8, arg1),
+        SYS_DUP => sys_dup(arg0),
+        SYS_DUP3 => sys_dup3(arg0, arg1, arg2),
+        SYS_MKDIRAT => sys_mkdirat(arg0 as isize, arg1 as *const u8, arg2),
+        SYS_UNLINKAT => sys_unlinkat(arg0 as isize, arg1 as *const u8, arg2),
+        SYS_CHDIR => sys_chdir(arg0 as *const u8),
+        SYS_OPENAT => sys_openat(arg0 as isize, arg1 as *const u8, arg2, arg3),
+        SYS_CLOSE => sys_close(arg0),
+        SYS_PIPE2 => sys_pipe2(arg0 as *mut [i32; 2], arg1),
+        SYS_GETDENTS64 => sys_getdents64(arg0, arg1 as *mut u8, arg2),
+        SYS_LSEEK => sys_lseek(arg0, arg1 as isize, arg2),
+        SYS_READ => sys_read(arg0, arg1 as *mut u8, arg2),
+        SYS_WRITE => sys_write(arg0, arg1 as *const u8, arg2),
+        SYS_FSTAT => sys_fstat(arg0, arg1 as *mut Stat),
         SYS_EXIT => sys_exit(arg0 as i32),
-        SYS_SCHED_YIELD => sys_sched_yield() as usize,
-        SYS_GETTIMEOFDAY => sys_gettimeofday(arg0 as *mut TimeVal, arg1) as usize,
-        SYS_GETPID => sys_getpid() as usize,
-        SYS_FORK => sys_fork() as usize,
-        SYS_EXEC => sys_exec(arg0 as *const u8) as usize,
-        SYS_WAITPID => sys_wait(arg0 as isize, arg1 as *mut i32) as usize,
-        _ => {
-            println!("[SYSCALL] parse syscall id failed: {}", id);
-            -1i8 as usize
+        SYS_NANOSLEEP => sys_nanosleep(arg0 as *const TimeSpec, arg1 as *mut TimeSpec),
+        SYS_SCHED_YIELD => sys_sched_yield(),
+        SYS_KILL => sys_kill(arg0 as isize, arg1),
+        SYS_SIGACTION => {
+            sys_sigaction(arg0, arg1 as *const SignalAction, arg2 as *mut SignalAction)
         }
+        SYS_SIGPROCMASK => sys_sigprocmask(arg0, arg1 as *const u64, arg2 as *mut u64),
+        SYS_SIGRETURN => sys_sigreturn(),
+        SYS_SETPRIORITY => sys_setpriority(arg0, arg1, arg2 as isize),
+        SYS_GETPRIORITY => sys_getpriority(arg0, arg1),
+        SYS_GETTIMEOFDAY => sys_gettimeofday(arg0 as *mut TimeVal, arg1),
+        SYS_GETPID => sys_getpid(),
+        SYS_GETTID => sys_gettid(),
+        SYS_BRK => Ok(sys_brk(arg0)),
+        SYS_MUNMAP => sys_munmap(arg0, arg1),
+        SYS_FORK => sys_fork(),
+        SYS_EXEC => sys_exec(
+            arg0 as *const u8,
+            arg1 as *const usize,
+            arg2 as *const usize,
+        ),
+        SYS_MMAP => sys_mmap(arg0, arg1, arg2, arg3, arg4 as isize, arg5),
+        SYS_MPROTECT => sys_mprotect(arg0, arg1, arg2),
+        SYS_WAITPID => sys_wait(arg0 as isize, arg1 as *mut i32, arg2),
+        SYS_SBRK => sys_sbrk(arg0 as isize),
+        SYS_THREAD_CREATE => sys_thread_create(arg0, arg1),
+        SYS_THREAD_JOIN => sys_thread_join(arg0, arg1 as *mut i32),
+        _ => Err(error::KernelError::InvalidSyscallId(format!(
+            "unknown syscall id: {id}"
+        ))),
+    };
+
+    match result {
+        Ok(ret) => ret,
+        Err(err) => (-(err.errno() as isize)) as usize,
     }
 }
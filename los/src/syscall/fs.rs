@@ -1,76 +1,250 @@
-use alloc::vec;
+use alloc::{format, string::String, sync::Arc, vec};
 
-use crate::{mm, println, sbi, task::processor};
-
-const STDIN: usize = 0;
-const STDOUT: usize = 1;
+use crate::{
+    config, error,
+    fs::{self, disk, File, OpenFlags, Stat},
+    mm::{self, UserPtr, UserSlice},
+    task::processor,
+};
 
 const READ_BUF_SIZE: usize = 1 << 10;
+const WRITE_BUF_SIZE: usize = 1 << 10;
 
-pub fn sys_read(fd: usize, user_buf: *mut u8, len: usize) -> isize {
-    match fd {
-        STDIN => {
-            let mut read_buf = vec![0u8; READ_BUF_SIZE.min(len)];
-            let read_len = sbi::console_read_bytes(read_buf.as_mut_slice());
+pub(super) const AT_FDCWD: isize = -100;
+const AT_REMOVEDIR: usize = 0x200;
 
-            assert!(read_len as usize <= len);
+fn get_file(fd: usize) -> error::Result<Arc<dyn File>> {
+    processor::with_current_task_fd_table(|fd_table| fd_table.get(fd))
+}
 
-            if read_len <= 0 {
-                return read_len;
-            }
+pub fn sys_read(fd: usize, user_buf: *mut u8, len: usize) -> error::Result<usize> {
+    let file = get_file(fd)?;
+    if !file.readable() {
+        return Err(error::KernelError::BadFileDescriptor(format!(
+            "fd {fd} is not readable"
+        )));
+    }
 
-            let satp = processor::get_current_task_satp();
-            match mm::PageTable::from_satp(satp).translate_bytes((user_buf as usize).into(), len) {
-                Ok(chunks) => {
-                    let mut read_data = &read_buf[..read_len as usize];
+    let mut read_buf = vec![0u8; READ_BUF_SIZE.min(len)];
+    let read_len = file.read(&mut read_buf)?;
+    if read_len == 0 {
+        return Ok(0);
+    }
 
-                    for chunk in chunks {
-                        let chunk_len = chunk.len();
-                        let read_len = read_data.len();
-                        let len = chunk_len.min(read_len);
+    let user_buf = UserSlice::new(user_buf as usize, read_len);
+    processor::with_current_task_mem_space(|mem_space| {
+        user_buf.copy_to_user(mem_space, &read_buf[..read_len])
+    })?;
 
-                        chunk[..len].copy_from_slice(&read_data[..len]);
-                        read_data = &read_data[len..];
-                    }
-                }
-                Err(err) => {
-                    println!("[FS] translate failed: {:?}", err);
-                    return -1;
+    Ok(read_len)
+}
+
+pub fn sys_write(fd: usize, data: *const u8, len: usize) -> error::Result<usize> {
+    let file = get_file(fd)?;
+    if !file.writable() {
+        return Err(error::KernelError::BadFileDescriptor(format!(
+            "fd {fd} is not writable"
+        )));
+    }
+
+    let mut write_buf = vec![0u8; WRITE_BUF_SIZE.min(len)];
+    let mut written = 0;
+    while written < len {
+        let chunk_len = (len - written).min(write_buf.len());
+        let user_buf = UserSlice::new(data as usize + written, chunk_len);
+        processor::with_current_task_mem_space(|mem_space| {
+            user_buf.copy_from_user(mem_space, &mut write_buf[..chunk_len])
+        })?;
+
+        match file.write(&write_buf[..chunk_len]) {
+            Ok(n) => {
+                written += n;
+                if n < chunk_len {
+                    break;
                 }
             }
-
-            read_len
-        }
-        _ => {
-            panic!("[FS] read from invalid fd: {}", fd);
+            Err(err) if written == 0 => return Err(err),
+            Err(_) => break,
         }
     }
+
+    Ok(written)
 }
 
-pub fn sys_write(fd: usize, data: *const u8, len: usize) -> isize {
-    match fd {
-        STDOUT => {
-            let satp = processor::get_current_task_satp();
-            match mm::PageTable::from_satp(satp).translate_bytes((data as usize).into(), len) {
-                Ok(chunks) => {
-                    let mut len = 0usize;
-                    for chunk in chunks.iter() {
-                        for b in chunk.iter() {
-                            sbi::console_write_byte(*b as usize);
-                        }
-                        len += chunk.len();
-                    }
-
-                    len as isize
-                }
-                Err(err) => {
-                    println!("[FS] translate failed: {:?}", err);
-                    -1
-                }
+/// `flags` may only be 0.
+pub fn sys_pipe2(fds: *mut [i32; 2], flags: usize) -> error::Result<usize> {
+    if flags != 0 {
+        return Err(error::KernelError::InvalidArgument(format!(
+            "unsupported pipe2 flags: {flags:#x}"
+        )));
+    }
+
+    let (read_end, write_end) = fs::make_pipe();
+    let pipe_fds = processor::with_current_task_fd_table(|fd_table| {
+        let read_fd = fd_table.insert(read_end)?;
+        match fd_table.insert(write_end) {
+            Ok(write_fd) => Ok([read_fd as i32, write_fd as i32]),
+            Err(err) => {
+                fd_table.remove(read_fd)?;
+                Err(err)
             }
         }
-        _ => {
-            panic!("[FS] write to inavlid fd: {}", fd);
-        }
+    })?;
+
+    let fds = UserPtr::from(fds);
+    if let Err(err) =
+        processor::with_current_task_mem_space(|mem_space| fds.write(mem_space, &pipe_fds))
+    {
+        processor::with_current_task_fd_table(|fd_table| {
+            pipe_fds.iter().for_each(|&fd| {
+                fd_table.remove(fd as usize).ok();
+            })
+        });
+        return Err(err);
+    }
+
+    Ok(0)
+}
+
+pub fn sys_close(fd: usize) -> error::Result<usize> {
+    processor::with_current_task_fd_table(|fd_table| fd_table.remove(fd))?;
+
+    Ok(0)
+}
+
+pub fn sys_dup(fd: usize) -> error::Result<usize> {
+    processor::with_current_task_fd_table(|fd_table| {
+        let file = fd_table.get(fd)?;
+        fd_table.insert(file)
+    })
+}
+
+/// `flags` may only be 0, there is no close-on-exec yet.
+pub fn sys_dup3(old_fd: usize, new_fd: usize, flags: usize) -> error::Result<usize> {
+    if old_fd == new_fd || flags != 0 {
+        return Err(error::KernelError::InvalidArgument(format!(
+            "invalid dup3 arguments: {old_fd} {new_fd} {flags:#x}"
+        )));
+    }
+
+    processor::with_current_task_fd_table(|fd_table| {
+        let file = fd_table.get(old_fd)?;
+        fd_table.insert_at(new_fd, file)
+    })?;
+
+    Ok(new_fd)
+}
+
+pub fn sys_fstat(fd: usize, stat: *mut Stat) -> error::Result<usize> {
+    let file_stat = get_file(fd)?.stat()?;
+
+    let stat = UserPtr::from(stat);
+    processor::with_current_task_mem_space(|mem_space| stat.write(mem_space, &file_stat))?;
+
+    Ok(0)
+}
+
+pub fn sys_lseek(fd: usize, offset: isize, whence: usize) -> error::Result<usize> {
+    get_file(fd)?.seek(offset, whence)
+}
+
+/// Reads a user path and makes it absolute. Relative paths start at the
+/// working directory, `dirfd` must be `AT_FDCWD` for them.
+pub(super) fn resolve_path(dirfd: isize, path: *const u8) -> error::Result<String> {
+    let path = processor::with_current_task_mem_space(|mem_space| {
+        mm::read_c_str(mem_space, path as usize, config::MAX_USER_STR_LEN)
+    })?;
+    if path.is_empty() {
+        return Err(error::KernelError::NotFound("empty path".into()));
+    }
+    if !path.starts_with('/') && dirfd != AT_FDCWD {
+        return Err(error::KernelError::InvalidArgument(format!(
+            "unsupported dirfd: {dirfd}"
+        )));
+    }
+
+    Ok(disk::absolute_path(&processor::current_task_cwd(), &path))
+}
+
+/// `mode` is ignored, files have no permissions.
+pub fn sys_openat(
+    dirfd: isize,
+    path: *const u8,
+    flags: usize,
+    _mode: usize,
+) -> error::Result<usize> {
+    let flags = OpenFlags::from_bits(flags).ok_or(error::KernelError::InvalidArgument(format!(
+        "unsupported open flags: {flags:#x}"
+    )))?;
+    let path = resolve_path(dirfd, path)?;
+
+    let file = disk::open(&path, flags)?;
+    processor::with_current_task_fd_table(|fd_table| fd_table.insert(Arc::new(file)))
+}
+
+pub fn sys_getdents64(fd: usize, user_buf: *mut u8, len: usize) -> error::Result<usize> {
+    let file = get_file(fd)?;
+
+    let mut dirent_buf = vec![0u8; READ_BUF_SIZE.min(len)];
+    let used = file.getdents(&mut dirent_buf)?;
+    if used == 0 {
+        return Ok(0);
+    }
+
+    let user_buf = UserSlice::new(user_buf as usize, used);
+    processor::with_current_task_mem_space(|mem_space| {
+        user_buf.copy_to_user(mem_space, &dirent_buf[..used])
+    })?;
+
+    Ok(used)
+}
+
+/// `mode` is ignored like for `sys_openat`.
+pub fn sys_mkdirat(dirfd: isize, path: *const u8, _mode: usize) -> error::Result<usize> {
+    let path = resolve_path(dirfd, path)?;
+    disk::mkdir(&path)?;
+
+    Ok(0)
+}
+
+/// `flags` may only be 0 or `AT_REMOVEDIR`.
+pub fn sys_unlinkat(dirfd: isize, path: *const u8, flags: usize) -> error::Result<usize> {
+    if flags & !AT_REMOVEDIR != 0 {
+        return Err(error::KernelError::InvalidArgument(format!(
+            "unsupported unlinkat flags: {flags:#x}"
+        )));
     }
+
+    let path = resolve_path(dirfd, path)?;
+    disk::unlink(&path, flags & AT_REMOVEDIR != 0)?;
+
+    Ok(0)
+}
+
+pub fn sys_chdir(path: *const u8) -> error::Result<usize> {
+    let path = resolve_path(AT_FDCWD, path)?;
+    if !disk::is_dir(&path)? {
+        return Err(error::KernelError::NotDirectory(path));
+    }
+    processor::set_current_task_cwd(path);
+
+    Ok(0)
+}
+
+/// Returns the length of the path including its NUL, like the raw Linux
+/// syscall.
+pub fn sys_getcwd(user_buf: *mut u8, size: usize) -> error::Result<usize> {
+    let mut cwd = processor::current_task_cwd().into_bytes();
+    cwd.push(0);
+    if cwd.len() > size {
+        return Err(error::KernelError::OutOfRange(format!(
+            "cwd needs {} bytes, buffer has {size}",
+            cwd.len()
+        )));
+    }
+
+    let user_buf = UserSlice::new(user_buf as usize, cwd.len());
+    processor::with_current_task_mem_space(|mem_space| user_buf.copy_to_user(mem_space, &cwd))?;
+
+    Ok(cwd.len())
 }
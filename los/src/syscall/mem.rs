@@ -0,0 +1,122 @@
+use bitflags::bitflags;
+
+use alloc::format;
+
+use crate::{
+    error,
+    mm::{address::VirtAddr, MapPermission},
+    task::processor,
+};
+
+bitflags! {
+    #[derive(Debug, Clone, Copy)]
+    struct MmapProt: usize {
+        const READ = 1 << 0;
+        const WRITE = 1 << 1;
+        const EXEC = 1 << 2;
+    }
+}
+
+bitflags! {
+    #[derive(Debug, Clone, Copy)]
+    struct MmapFlags: usize {
+        const SHARED = 1 << 0;
+        const PRIVATE = 1 << 1;
+        const FIXED = 1 << 4;
+        const ANONYMOUS = 1 << 5;
+    }
+}
+
+impl From<MmapProt> for MapPermission {
+    fn from(value: MmapProt) -> Self {
+        let mut map_perm = MapPermission::U;
+        if value.contains(MmapProt::READ) {
+            map_perm |= MapPermission::R;
+        }
+        // a writable page must be readable, W without R is a reserved PTE
+        // encoding
+        if value.contains(MmapProt::WRITE) {
+            map_perm |= MapPermission::R | MapPermission::W;
+        }
+        if value.contains(MmapProt::EXEC) {
+            map_perm |= MapPermission::X;
+        }
+
+        map_perm
+    }
+}
+
+pub fn sys_brk(addr: usize) -> usize {
+    processor::with_current_task_mem_space(|mem_space| {
+        // like Linux, a failed brk just leaves the break where it was
+        if addr != 0 {
+            mem_space.set_brk(VirtAddr(addr)).ok();
+        }
+
+        mem_space.brk().into()
+    })
+}
+
+pub fn sys_sbrk(increment: isize) -> error::Result<usize> {
+    processor::with_current_task_mem_space(|mem_space| {
+        let old_brk: usize = mem_space.brk().into();
+        let new_brk = old_brk
+            .checked_add_signed(increment)
+            .ok_or(error::KernelError::Brk(format!(
+                "sbrk overflow: {old_brk:#x} {increment}"
+            )))?;
+        mem_space.set_brk(VirtAddr(new_brk))?;
+
+        Ok(old_brk)
+    })
+}
+
+pub fn sys_mmap(
+    addr: usize,
+    len: usize,
+    prot: usize,
+    flags: usize,
+    fd: isize,
+    offset: usize,
+) -> error::Result<usize> {
+    let (Some(prot), Some(flags)) = (MmapProt::from_bits(prot), MmapFlags::from_bits(flags)) else {
+        return Err(error::KernelError::InvalidArgument(format!(
+            "invalid mmap prot or flags: {prot:#x} {flags:#x}"
+        )));
+    };
+
+    if !flags.contains(MmapFlags::ANONYMOUS | MmapFlags::PRIVATE) || fd != -1 || offset != 0 {
+        return Err(error::KernelError::InvalidArgument(format!(
+            "mmap only supports private anonymous mapping: {flags:?}"
+        )));
+    }
+
+    processor::with_current_task_mem_space(|mem_space| {
+        mem_space
+            .mmap(
+                VirtAddr(addr),
+                len,
+                prot.into(),
+                flags.contains(MmapFlags::FIXED),
+            )
+            .map(|va| va.0)
+    })
+}
+
+pub fn sys_munmap(addr: usize, len: usize) -> error::Result<usize> {
+    processor::with_current_task_mem_space(|mem_space| mem_space.munmap(VirtAddr(addr), len))?;
+
+    Ok(0)
+}
+
+pub fn sys_mprotect(addr: usize, len: usize, prot: usize) -> error::Result<usize> {
+    let prot = MmapProt::from_bits(prot).ok_or(error::KernelError::InvalidArgument(format!(
+        "invalid mprotect prot: {prot:#x}"
+    )))?;
+
+    processor::with_current_task_mem_space(|mem_space| {
+        mem_space.mprotect(VirtAddr(addr), len, prot.into())
+    })?;
+
+    Ok(0)
+}
@@ -1,5 +1,9 @@
+use alloc::{format, string::String, vec::Vec};
+
+use super::fs::{resolve_path, AT_FDCWD};
 use crate::{
-    mm, println,
+    config, error,
+    mm::{self, UserPtr},
     task::processor::{self, WaitChildArg},
 };
 
@@ -7,64 +11,119 @@ pub fn sys_exit(exit_code: i32) -> ! {
     processor::exit_current_task_and_schedule(exit_code)
 }
 
-pub fn sys_sched_yield() -> isize {
+pub fn sys_sched_yield() -> error::Result<usize> {
     processor::suspend_current_task_and_schedule();
 
-    0
+    Ok(0)
 }
 
-pub fn sys_fork() -> isize {
-    match processor::fork_current_task() {
-        Ok(pid) => pid as isize,
-        Err(err) => {
-            println!("[PROC] sys fork failed: {:?}", err);
-            -1
-        }
-    }
+pub fn sys_fork() -> error::Result<usize> {
+    processor::fork_current_task()
 }
 
-pub fn sys_exec(path: *const u8) -> isize {
-    let satp = processor::get_current_task_satp();
-    let page_table = mm::PageTable::from_satp(satp);
-    match page_table.translate_c_str((path as usize).into()) {
-        Ok(path) => match processor::exec_in_tcb(&path) {
-            Ok(()) => 0,
-            Err(err) => {
-                println!("[PROC] sys exec failed: {:?}", err);
-                -1
-            }
-        },
-        Err(err) => {
-            println!("[PROC] translate path failed: {:?}", err);
-            -2
-        }
+/// Reads a NULL-terminated array of user string pointers, such as argv.
+fn read_c_str_array(ptr: *const usize) -> error::Result<Vec<String>> {
+    let mut strings = Vec::new();
+    if ptr.is_null() {
+        return Ok(strings);
     }
-}
 
-pub fn sys_wait(pid: isize, exit_code: *mut i32) -> isize {
-    let wait_child_arg = match WaitChildArg::from_pid(pid) {
-        Ok(wait_child_arg) => wait_child_arg,
-        Err(err) => {
-            println!("[PROC] build WaitChildArg failed: {:?}", err);
-            return -1;
+    processor::with_current_task_mem_space(|mem_space| loop {
+        let str_ptr = UserPtr::from(ptr.wrapping_add(strings.len())).read(mem_space)?;
+        if str_ptr == 0 {
+            return Ok(strings);
         }
+        if strings.len() == config::MAX_EXEC_ARGS {
+            return Err(error::KernelError::ArgumentListTooLong(format!(
+                "more than {} exec arguments",
+                config::MAX_EXEC_ARGS
+            )));
+        }
+
+        strings.push(mm::read_c_str(
+            mem_space,
+            str_ptr,
+            config::MAX_USER_STR_LEN,
+        )?);
+    })
+}
+
+pub fn sys_exec(path: *const u8, argv: *const usize, envp: *const usize) -> error::Result<usize> {
+    let path = resolve_path(AT_FDCWD, path)?;
+    let argv = read_c_str_array(argv)?;
+    let envp = read_c_str_array(envp)?;
+    // the syscall return lands in a0 of the new program, which is where
+    // `_start` expects the stack pointer
+    processor::exec_in_tcb(&path, &argv, &envp)
+}
+
+const WNOHANG: usize = 1;
+const PRIO_PROCESS: usize = 0;
+
+pub fn sys_wait(pid: isize, wstatus: *mut i32, options: usize) -> error::Result<usize> {
+    let wait_child_arg = WaitChildArg::from_pid(pid)?;
+    let Some(exited_child) = processor::wait_child_exit(wait_child_arg, options & WNOHANG != 0)?
+    else {
+        return Ok(0);
     };
 
-    match processor::wait_child_exit(wait_child_arg).expect("wait child must succeed") {
-        Some(result) => {
-            let satp = processor::get_current_task_satp();
-            match mm::PageTable::from_satp(satp).translate_write(exit_code, &result.exit_code) {
-                Ok(_) => result.pid as isize,
-                Err(err) => {
-                    println!("[PROC] write exit_code to user buf failed: {:?}", err);
-                    -2
-                }
-            }
-        }
-        None => 0,
+    let wstatus = UserPtr::from(wstatus);
+    if !wstatus.is_null() {
+        processor::with_current_task_mem_space(|mem_space| {
+            wstatus.write(mem_space, &exited_child.wait_status)
+        })?;
     }
+
+    Ok(exited_child.pid)
+}
+
+pub fn sys_getpid() -> error::Result<usize> {
+    Ok(processor::getpid())
 }
 
-pub fn sys_getpid() -> isize {
-    processor::getpid() as isize
+pub fn sys_gettid() -> error::Result<usize> {
+    Ok(processor::gettid())
+}
+
+pub fn sys_thread_create(entry: usize, arg: usize) -> error::Result<usize> {
+    processor::create_thread(entry, arg)
+}
+
+pub fn sys_thread_join(tid: usize, wstatus: *mut i32) -> error::Result<usize> {
+    let exited_thread = processor::wait_thread_exit(tid)?;
+
+    let wstatus = UserPtr::from(wstatus);
+    if !wstatus.is_null() {
+        processor::with_current_task_mem_space(|mem_space| {
+            wstatus.write(mem_space, &exited_thread.wait_status)
+        })?;
+    }
+
+    Ok(exited_thread.pid)
+}
+
+fn check_priority_target(which: usize) -> error::Result<()> {
+    if which != PRIO_PROCESS {
+        return Err(error::KernelError::InvalidArgument(format!(
+            "unsupported priority target: {which}"
+        )));
+    }
+
+    Ok(())
+}
+
+pub fn sys_setpriority(which: usize, who: usize, nice: isize) -> error::Result<usize> {
+    check_priority_target(which)?;
+    processor::set_task_nice(who, nice.clamp(i32::MIN as isize, i32::MAX as isize) as i32)?;
+
+    Ok(0)
+}
+
+/// Returns `20 - nice` like the raw Linux syscall, so a valid result is
+/// never negative.
+pub fn sys_getpriority(which: usize, who: usize) -> error::Result<usize> {
+    check_priority_target(which)?;
+    let nice = processor::get_task_nice(who)?;
+
+    Ok((20 - nice) as usize)
 }
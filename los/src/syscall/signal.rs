@@ -0,0 +1,86 @@
+use alloc::format;
+
+use crate::{
+    error,
+    mm::UserPtr,
+    task::{
+        processor,
+        signal::{self, SignalAction, SignalSet},
+    },
+};
+
+fn check_signal(sig: usize) -> error::Result<()> {
+    if !signal::is_valid(sig) {
+        return Err(error::KernelError::InvalidArgument(format!(
+            "invalid signal: {sig}"
+        )));
+    }
+
+    Ok(())
+}
+
+pub fn sys_kill(pid: isize, sig: usize) -> error::Result<usize> {
+    if pid <= 0 {
+        return Err(error::KernelError::InvalidArgument(format!(
+            "unsupported kill target: {pid}"
+        )));
+    }
+    if sig != 0 {
+        check_signal(sig)?;
+    }
+
+    processor::send_signal(pid as usize, sig)?;
+
+    Ok(0)
+}
+
+pub fn sys_sigaction(
+    sig: usize,
+    action: *const SignalAction,
+    old_action: *mut SignalAction,
+) -> error::Result<usize> {
+    check_signal(sig)?;
+
+    let action = UserPtr::from(action);
+    let action = if action.is_null() {
+        None
+    } else {
+        Some(processor::with_current_task_mem_space(|mem_space| {
+            action.read(mem_space)
+        })?)
+    };
+
+    let old = processor::set_current_task_signal_action(sig, action)?;
+
+    let old_action = UserPtr::from(old_action);
+    if !old_action.is_null() {
+        processor::with_current_task_mem_space(|mem_space| old_action.write(mem_space, &old))?;
+    }
+
+    Ok(0)
+}
+
+pub fn sys_sigprocmask(how: usize, set: *const u64, old_set: *mut u64) -> error::Result<usize> {
+    let set = UserPtr::from(set);
+    let set = if set.is_null() {
+        None
+    } else {
+        let bits = processor::with_current_task_mem_space(|mem_space| set.read(mem_space))?;
+        Some(SignalSet::from_bits(bits))
+    };
+
+    let old = processor::set_current_task_signal_mask(how, set)?;
+
+    let old_set = UserPtr::from(old_set);
+    if !old_set.is_null() {
+        processor::with_current_task_mem_space(|mem_space| old_set.write(mem_space, &old.bits()))?;
+    }
+
+    Ok(0)
+}
+
+/// Returns the restored `a0`, so the interrupted code sees its own register
+/// even when it looks like an error code.
+pub fn sys_sigreturn() -> error::Result<usize> {
+    processor::return_from_signal_handler()
+}
@@ -1,29 +1,38 @@
-use core::mem;
+use alloc::{format, string::ToString};
 
 use crate::{
-    mm, println,
+    error,
+    mm::UserPtr,
     task::processor,
-    timer::{self, TimeVal},
+    timer::{self, TimeSpec, TimeVal},
 };
 
-pub fn sys_gettimeofday(tp: *mut TimeVal, _tzp: usize) -> isize {
-    let satp = processor::get_current_task_satp();
-    match mm::PageTable::from_satp(satp)
-        .translate_bytes((tp as usize).into(), mem::size_of::<TimeVal>())
-    {
-        Ok(chunks) => {
-            let chunk = chunks.first().unwrap();
-            let tp = unsafe { &mut *(chunk.as_ptr() as *mut TimeVal) };
-            let t = timer::get_time();
+pub fn sys_gettimeofday(tp: *mut TimeVal, _tzp: usize) -> error::Result<usize> {
+    let tp = UserPtr::from(tp);
+    processor::with_current_task_mem_space(|mem_space| tp.write(mem_space, &timer::get_time()))?;
 
-            tp.sec = t.sec;
-            tp.usec = t.usec;
+    Ok(0)
+}
 
-            0
-        }
-        Err(err) => {
-            println!("[TIME] translate failed: {:?}", err);
-            -1
-        }
+pub fn sys_nanosleep(req: *const TimeSpec, rem: *mut TimeSpec) -> error::Result<usize> {
+    let req = UserPtr::from(req);
+    let duration = processor::with_current_task_mem_space(|mem_space| req.read(mem_space))?;
+    if !duration.is_valid() {
+        return Err(error::KernelError::InvalidArgument(format!(
+            "invalid sleep duration: {duration:?}"
+        )));
     }
+
+    let Some(left) = timer::sleep(duration) else {
+        return Ok(0);
+    };
+
+    let rem = UserPtr::from(rem);
+    if !rem.is_null() {
+        processor::with_current_task_mem_space(|mem_space| rem.write(mem_space, &left))?;
+    }
+
+    Err(error::KernelError::Interrupted(
+        "sleep interrupted by signal".to_string(),
+    ))
 }
@@ -2,7 +2,10 @@ mod loader;
 pub mod manager;
 pub mod pid;
 pub mod processor;
+mod scheduler;
+pub mod signal;
 mod tcb;
+pub mod wait_queue;
 
 use crate::println;
 
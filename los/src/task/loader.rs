@@ -1,5 +1,7 @@
 use alloc::{
+    borrow::Cow,
     collections::btree_map::BTreeMap,
+    format,
     string::{String, ToString},
     vec::Vec,
 };
@@ -8,13 +10,48 @@ use core::{
     ffi::{c_char, CStr},
     slice,
 };
+use lazy_static::lazy_static;
 
-pub struct AppLoader {
+use crate::{config, error, fs::disk};
+
+lazy_static! {
+    static ref INITRAMFS: AppLoader = AppLoader::load();
+}
+
+/// Reads the program at `path` from the root filesystem. Programs that are
+/// not there come from the ones linked into the kernel, so the system still
+/// boots without a disk.
+pub fn load_elf(path: &str) -> error::Result<Cow<'static, [u8]>> {
+    match disk::read_file(path, config::MAX_EXEC_FILE_SIZE) {
+        Ok(data) => return Ok(Cow::Owned(data)),
+        Err(error::KernelError::NotFound(_)) => {}
+        Err(err) => return Err(err),
+    }
+
+    INITRAMFS
+        .load_app_elf(path.trim_start_matches('/'))
+        .map(Cow::Borrowed)
+        .ok_or(error::KernelError::LoadAppELF(format!(
+            "load app ELF failed: {path}"
+        )))
+}
+
+/// Programs in the root directory and the initramfs.
+pub fn app_names() -> Vec<String> {
+    let mut names = disk::list_root();
+    names.extend(INITRAMFS.app_names());
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// The programs linked into the kernel by `toolbox user asm`.
+struct AppLoader {
     apps: BTreeMap<String, AppInfo>,
 }
 
 impl AppLoader {
-    pub fn load() -> Self {
+    fn load() -> Self {
         extern "C" {
             fn _app_data();
         }
@@ -56,7 +93,7 @@ impl AppLoader {
         }
     }
 
-    pub fn load_app_elf(&self, name: &str) -> Option<&'static [u8]> {
+    fn load_app_elf(&self, name: &str) -> Option<&'static [u8]> {
         let app_info = self.apps.get(name)?;
 
         let elf_data =
@@ -65,14 +102,14 @@ impl AppLoader {
         Some(elf_data)
     }
 
-    pub fn app_names(&self) -> Vec<String> {
+    fn app_names(&self) -> Vec<String> {
         self.apps.keys().cloned().collect()
     }
 }
 
 #[derive(Debug, Clone, Copy)]
-pub struct AppInfo {
-    pub name: &'static str,
-    pub elf_start: usize,
-    pub elf_size: usize,
+struct AppInfo {
+    name: &'static str,
+    elf_start: usize,
+    elf_size: usize,
 }
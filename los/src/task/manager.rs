@@ -1,15 +1,23 @@
 use super::{
     pid,
+    scheduler::{DefaultScheduler, Scheduler},
     tcb::{TaskContext, TaskControlBlock, TaskControlBlockWrapper, TaskStatus},
+    wait_queue::WaitQueue,
 };
 use crate::{
     config::INIT_PROC_NAME,
     error,
     mm::{self, KernelStack},
-    task::loader::AppLoader,
+    task::loader,
     trap::{trap_return, TrapContext},
 };
-use alloc::{collections::vec_deque::VecDeque, format, string::ToString, vec::Vec};
+use alloc::{
+    collections::btree_map::BTreeMap,
+    format,
+    string::{String, ToString},
+    sync::{Arc, Weak},
+    vec::Vec,
+};
 use core::arch::global_asm;
 use lazy_static::lazy_static;
 use spin::Mutex;
@@ -21,132 +29,208 @@ lazy_static! {
 }
 
 struct TaskManager {
-    runq: VecDeque<TaskControlBlockWrapper>,
+    scheduler: DefaultScheduler,
+    tasks: BTreeMap<usize, Weak<Mutex<TaskControlBlock>>>,
     init_proc_tcb: Option<TaskControlBlockWrapper>,
-    app_loader: AppLoader,
 }
 
 impl TaskManager {
     fn new() -> Self {
-        let app_loader = AppLoader::load();
-
         TaskManager {
-            runq: VecDeque::new(),
+            scheduler: DefaultScheduler::default(),
+            tasks: BTreeMap::new(),
             init_proc_tcb: None,
-            app_loader,
         }
     }
 
     fn push_to_runq(&mut self, tcb: TaskControlBlockWrapper) {
-        self.runq.push_back(tcb);
+        self.scheduler.push(tcb);
     }
 
     fn fetch_from_runq(&mut self) -> Option<TaskControlBlockWrapper> {
-        self.runq.pop_front()
+        self.scheduler.fetch()
     }
 
-    fn create_task(&self, name: &str) -> error::Result<TaskControlBlock> {
-        let elf_data = self
-            .app_loader
-            .load_app_elf(name)
-            .ok_or(error::KernelError::LoadAppELF(format!(
-                "load app ELF failed: {name}"
-            )))?;
-        let (mut mem_space, user_sp, entry) =
-            mm::build_app_mem_space(elf_data).expect("build app mem space must succeed");
+    fn add_task(&mut self, tcb: &TaskControlBlockWrapper) {
+        let pid = tcb.lock().pid.pid();
+        self.tasks.insert(pid, Arc::downgrade(tcb));
+    }
+}
 
+fn create_task(name: &str, elf_data: &[u8]) -> error::Result<TaskControlBlock> {
+    let (mem_space, user_sp, entry) = mm::build_app_mem_space(elf_data, &[name.to_string()], &[])?;
+
+    let pid = pid::alloc().ok_or(error::KernelError::AllocPid(
+        "allocate pid failed".to_string(),
+    ))?;
+    let kernel_stack =
+        KernelStack::map_in_kernel_memory_space(&pid).expect("map app kernel stack must succeed");
+    let kernel_stack_sp = kernel_stack.get_sp();
+
+    let mut trap_context = TrapContext::init(entry, user_sp, kernel_stack_sp);
+    trap_context.regs[10] = user_sp;
+    let trap_context_dest = mem_space.trap_context_mut_ptr(0);
+    unsafe { *trap_context_dest = trap_context };
+
+    Ok(TaskControlBlock::init(
+        name.to_string(),
+        pid,
+        trap_return as usize,
+        kernel_stack,
+        mem_space,
+    ))
+}
+
+fn fork_task(parent_tcb: TaskControlBlockWrapper) -> error::Result<TaskControlBlockWrapper> {
+    let mut forked_tcb = {
+        let parent_tcb = parent_tcb.lock();
         let pid = pid::alloc().ok_or(error::KernelError::AllocPid(
             "allocate pid failed".to_string(),
         ))?;
+        let name = parent_tcb.name.clone();
         let kernel_stack = KernelStack::map_in_kernel_memory_space(&pid)
             .expect("map app kernel stack must succeed");
-        let kernel_stack_sp = kernel_stack.get_sp();
+        let context = TaskContext::init(trap_return as usize, kernel_stack.get_sp());
+
+        let mem_space = parent_tcb
+            .mem_space()
+            .lock()
+            .fork(parent_tcb.thread_slot)
+            .map_err(|e| {
+                error::KernelError::CreateMemorySpace(format!("fork memory space failed: {e:?}"))
+            })?;
+        let fd_table = parent_tcb.fd_table.lock().clone();
+        let cwd = parent_tcb.cwd.lock().clone();
 
-        let trap_context = TrapContext::init(entry, user_sp, kernel_stack_sp);
-        let trap_context_dest = mem_space.trap_context_mut_ptr();
+        let current_trap_context = unsafe { (*parent_tcb.get_trap_context_ptr()).clone() };
+        let mut trap_context = TrapContext {
+            kernel_sp: kernel_stack.get_sp(),
+            ..current_trap_context
+        };
+        trap_context.regs[10] = 0;
+        let trap_context_dest = mem_space.trap_context_mut_ptr(parent_tcb.thread_slot);
         unsafe { *trap_context_dest = trap_context };
 
-        Ok(TaskControlBlock::init(
-            name.to_string(),
+        TaskControlBlock {
+            name,
+            tgid: pid.pid(),
             pid,
-            trap_return as usize,
+            thread_slot: parent_tcb.thread_slot,
+            context,
+            status: TaskStatus::Ready,
+            sched: parent_tcb.sched,
             kernel_stack,
-            mem_space,
-        ))
-    }
+            mem_space: Some(Arc::new(Mutex::new(mem_space))),
+            fd_table: Arc::new(Mutex::new(fd_table)),
+            cwd: Arc::new(Mutex::new(cwd)),
+            parent: None,
+            children: Vec::new(),
+            child_exit: Arc::new(WaitQueue::new()),
+            signals: parent_tcb.signals.fork(),
+            on_cpu: false,
+        }
+    };
 
-    fn fork_task(
-        &self,
-        parent_tcb: TaskControlBlockWrapper,
-    ) -> error::Result<TaskControlBlockWrapper> {
-        let mut forked_tcb = {
-            let parent_tcb = parent_tcb.lock();
-            let pid = pid::alloc().ok_or(error::KernelError::AllocPid(
-                "allocate pid failed".to_string(),
-            ))?;
-            let name = parent_tcb.name.clone();
-            let kernel_stack = KernelStack::map_in_kernel_memory_space(&pid)
-                .expect("map app kernel stack must succeed");
-            let context = TaskContext::init(trap_return as usize, kernel_stack.get_sp());
-
-            let mut mem_space = parent_tcb.mem_space.fork().map_err(|e| {
-                error::KernelError::Common(format!("fork memory space failed: {e:?}"))
-            })?;
+    forked_tcb.parent = Some(parent_tcb.clone());
+    let forked_tcb_wrapper = TaskControlBlockWrapper::from(forked_tcb);
+    parent_tcb.lock().children.push(forked_tcb_wrapper.clone());
 
-            let current_trap_context = unsafe { (*parent_tcb.get_trap_context_ptr()).clone() };
-            let mut trap_context = TrapContext {
-                kernel_sp: kernel_stack.get_sp(),
-                ..current_trap_context
-            };
-            trap_context.regs[10] = 0;
-            trap_context.sepc += 4;
-            let trap_context_dest = mem_space.trap_context_mut_ptr();
-            unsafe { *trap_context_dest = trap_context };
-
-            TaskControlBlock {
-                name,
-                pid,
-                context,
-                status: TaskStatus::Ready,
-                kernel_stack,
-                mem_space,
-                parent: None,
-                children: Vec::new(),
-            }
-        };
+    Ok(forked_tcb_wrapper)
+}
 
-        forked_tcb.parent = Some(parent_tcb.clone());
-        let forked_tcb_wrapper = TaskControlBlockWrapper::from(forked_tcb);
-        parent_tcb.lock().children.push(forked_tcb_wrapper.clone());
+fn create_thread_tcb(
+    creator_tcb: TaskControlBlockWrapper,
+    entry: usize,
+    arg: usize,
+) -> error::Result<TaskControlBlockWrapper> {
+    let (name, tgid, sched, signals, mem_space, fd_table, cwd) = {
+        let creator_tcb = creator_tcb.lock();
+        (
+            creator_tcb.name.clone(),
+            creator_tcb.tgid,
+            creator_tcb.sched,
+            creator_tcb.signals.fork(),
+            creator_tcb.mem_space().clone(),
+            creator_tcb.fd_table.clone(),
+            creator_tcb.cwd.clone(),
+        )
+    };
+    let leader_tcb = find_task_by_pid(tgid).ok_or(error::KernelError::TaskNotFound(format!(
+        "thread group leader not found: {tgid}"
+    )))?;
+
+    let pid = pid::alloc().ok_or(error::KernelError::AllocPid(
+        "allocate pid failed".to_string(),
+    ))?;
+    let kernel_stack = KernelStack::map_in_kernel_memory_space(&pid)?;
+    let context = TaskContext::init(trap_return as usize, kernel_stack.get_sp());
+
+    let thread_slot = {
+        let mut mem_space = mem_space.lock();
+        let (thread_slot, user_sp) = mem_space.alloc_thread_slot()?;
+
+        let mut trap_context = TrapContext::init(entry, user_sp, kernel_stack.get_sp());
+        trap_context.regs[10] = arg;
+        let trap_context_dest = mem_space.trap_context_mut_ptr(thread_slot);
+        unsafe { *trap_context_dest = trap_context };
 
-        Ok(forked_tcb_wrapper)
-    }
+        thread_slot
+    };
+
+    let thread_tcb = TaskControlBlockWrapper::from(TaskControlBlock {
+        name,
+        pid,
+        tgid,
+        thread_slot,
+        context,
+        status: TaskStatus::Ready,
+        sched,
+        kernel_stack,
+        mem_space: Some(mem_space),
+        fd_table,
+        cwd,
+        parent: Some(leader_tcb.clone()),
+        children: Vec::new(),
+        child_exit: Arc::new(WaitQueue::new()),
+        signals,
+        on_cpu: false,
+    });
+    leader_tcb.lock().children.push(thread_tcb.clone());
+
+    Ok(thread_tcb)
+}
 
-    fn load_elf_in_task(&self, path: &str, tcb: TaskControlBlockWrapper) -> error::Result<()> {
-        let elf_data = self
-            .app_loader
-            .load_app_elf(path)
-            .ok_or(error::KernelError::LoadAppELF(format!(
-                "load app ELF failed: {path}"
-            )))?;
-        let (mut mem_space, user_sp, entry) =
-            mm::build_app_mem_space(elf_data).expect("build app mem space must succeed");
+fn load_elf_in_tcb(
+    path: &str,
+    elf_data: &[u8],
+    argv: &[String],
+    envp: &[String],
+    tcb: TaskControlBlockWrapper,
+) -> error::Result<usize> {
+    let mut tcb = tcb.lock();
+    if tcb.is_thread() {
+        return Err(error::KernelError::Common(format!(
+            "exec from non-leader thread {} is not supported",
+            tcb.pid.pid()
+        )));
+    }
 
-        let mut tcb = tcb.lock();
+    let (mem_space, user_sp, entry) = mm::build_app_mem_space(elf_data, argv, envp)?;
 
-        let mut trap_context =
-            unsafe { &*tcb.mem_space.trap_context_mut_ptr::<TrapContext>() }.clone();
-        trap_context.set_user_sp(user_sp);
-        trap_context.set_entry(entry);
+    let mut trap_context = unsafe { &*tcb.get_trap_context_ptr() }.clone();
+    trap_context.set_user_sp(user_sp);
+    trap_context.set_entry(entry);
+    trap_context.regs[10] = user_sp;
 
-        let trap_context_dest = mem_space.trap_context_mut_ptr();
-        unsafe { *trap_context_dest = trap_context };
+    let trap_context_dest = mem_space.trap_context_mut_ptr(0);
+    unsafe { *trap_context_dest = trap_context };
 
-        tcb.mem_space = mem_space;
-        tcb.name = path.to_string();
+    tcb.mem_space = Some(Arc::new(Mutex::new(mem_space)));
+    tcb.thread_slot = 0;
+    tcb.signals.reset_handlers();
+    tcb.name = path.to_string();
 
-        Ok(())
-    }
+    Ok(user_sp)
 }
 
 pub fn switch_task(current: *mut TaskContext, next: *const TaskContext) {
@@ -161,7 +245,9 @@ pub fn create_init_proc_and_push_to_runq() -> error::Result<()> {
     let tcb = TaskControlBlockWrapper::from(create_tcb_by_app_name(INIT_PROC_NAME)?);
 
     push_to_runq(tcb.clone());
-    TASK_MANAGER.lock().init_proc_tcb = Some(tcb);
+    let mut task_manager = TASK_MANAGER.lock();
+    task_manager.add_task(&tcb);
+    task_manager.init_proc_tcb = Some(tcb);
 
     Ok(())
 }
@@ -175,19 +261,53 @@ pub fn fetch_from_runq() -> Option<TaskControlBlockWrapper> {
 }
 
 pub fn create_tcb_by_app_name(name: &str) -> error::Result<TaskControlBlock> {
-    TASK_MANAGER.lock().create_task(name)
+    // read the program before taking the lock, reading the disk may sleep
+    let elf_data = loader::load_elf(name)?;
+    create_task(name, &elf_data)
 }
 
 pub fn fork_tcb(tcb: TaskControlBlockWrapper) -> error::Result<TaskControlBlockWrapper> {
-    TASK_MANAGER.lock().fork_task(tcb)
+    // copying the address space takes a while, other harts need
+    // TASK_MANAGER for their run queues meanwhile, so it only publishes
+    let forked_tcb = fork_task(tcb)?;
+    TASK_MANAGER.lock().add_task(&forked_tcb);
+
+    Ok(forked_tcb)
+}
+
+pub fn create_thread(
+    tcb: TaskControlBlockWrapper,
+    entry: usize,
+    arg: usize,
+) -> error::Result<TaskControlBlockWrapper> {
+    let thread_tcb = create_thread_tcb(tcb, entry, arg)?;
+    TASK_MANAGER.lock().add_task(&thread_tcb);
+
+    Ok(thread_tcb)
+}
+
+pub fn find_task_by_pid(pid: usize) -> Option<TaskControlBlockWrapper> {
+    TASK_MANAGER.lock().tasks.get(&pid).and_then(Weak::upgrade)
+}
+
+pub fn remove_task(pid: usize) {
+    TASK_MANAGER.lock().tasks.remove(&pid);
 }
 
-pub fn load_elf_in_task(path: &str, tcb: TaskControlBlockWrapper) -> error::Result<()> {
-    TASK_MANAGER.lock().load_elf_in_task(path, tcb)
+/// Replaces the program of `tcb` and returns its initial stack pointer,
+/// which the new program also receives in a0.
+pub fn load_elf_in_task(
+    path: &str,
+    argv: &[String],
+    envp: &[String],
+    tcb: TaskControlBlockWrapper,
+) -> error::Result<usize> {
+    let elf_data = loader::load_elf(path)?;
+    load_elf_in_tcb(path, &elf_data, argv, envp, tcb)
 }
 
 pub fn list_apps() -> alloc::vec::Vec<alloc::string::String> {
-    TASK_MANAGER.lock().app_loader.app_names()
+    loader::app_names()
 }
 
 pub fn get_init_proc_tcb() -> TaskControlBlockWrapper {
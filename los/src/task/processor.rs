@@ -1,26 +1,47 @@
 use super::{
     manager::{self, fetch_from_runq, push_to_runq, switch_task},
-    tcb::{TaskContext, TaskControlBlockWrapper},
+    signal::{
+        SignalAction, SignalSet, SignalState, SIGBUS, SIGILL, SIGKILL, SIGSEGV, SIGTRAP, SIG_BLOCK,
+        SIG_SETMASK, SIG_UNBLOCK,
+    },
+    tcb::{TaskContext, TaskControlBlock, TaskControlBlockWrapper},
 };
-use crate::{error, task::tcb::TaskStatus, trap::TrapContext};
-use alloc::format;
-use lazy_static::lazy_static;
+use crate::{
+    backtrace,
+    config::MAX_HARTS,
+    error,
+    fs::FdTable,
+    hart,
+    mm::{address::VirtAddr, MemorySpace, PageFault},
+    task::tcb::TaskStatus,
+    timer,
+    trap::{self, TrapContext},
+};
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::mem;
 use spin::Mutex;
 
-lazy_static! {
-    static ref PROCESSOR: Mutex<Processor> = Mutex::new(Processor::new());
-}
+static PROCESSORS: [Mutex<Processor>; MAX_HARTS] =
+    [const { Mutex::new(Processor::new()) }; MAX_HARTS];
 
 struct Processor {
     current: Option<TaskControlBlockWrapper>,
     idle_task_context: TaskContext,
+    /// The task that just switched back to the idle loop. It is only handed
+    /// to other harts once its context has been saved.
+    switched_out: Option<TaskControlBlockWrapper>,
 }
 
 impl Processor {
-    fn new() -> Self {
+    const fn new() -> Self {
         Self {
             current: None,
             idle_task_context: TaskContext::EMPTY,
+            switched_out: None,
         }
     }
 
@@ -33,16 +54,35 @@ impl Processor {
     }
 }
 
+fn current_processor() -> &'static Mutex<Processor> {
+    &PROCESSORS[hart::hart_id()]
+}
+
 pub fn run_tasks() -> ! {
     loop {
+        let switched_out = current_processor().lock().switched_out.take();
+        if let Some(tcb) = switched_out {
+            let ready = {
+                let mut tcb = tcb.lock();
+                tcb.on_cpu = false;
+                tcb.status == TaskStatus::Ready
+            };
+            if ready {
+                push_to_runq(tcb);
+            }
+        }
+
+        timer::wake_sleepers();
+
         if let Some(next_tcb) = fetch_from_runq() {
             let (idle_task_context, next_task_context) = {
-                let mut processor = PROCESSOR.lock();
-                next_tcb.lock().update_task_status(TaskStatus::Running);
+                let mut processor = current_processor().lock();
 
                 let idle_task_context = &mut processor.idle_task_context as *mut TaskContext;
                 let next_task_context = {
                     let mut next_tcb = next_tcb.lock();
+                    next_tcb.update_task_status(TaskStatus::Running);
+                    next_tcb.on_cpu = true;
                     &mut next_tcb.context as *const TaskContext
                 };
 
@@ -53,82 +93,239 @@ pub fn run_tasks() -> ! {
             };
 
             switch_task(idle_task_context, next_task_context);
+        } else {
+            // the timer interrupt wakes the hart at the latest on the next tick
+            trap::wait_for_interrupt();
         }
     }
 }
 
 pub fn schedule(switched_task_context: *mut TaskContext) {
     let idle_task_context = {
-        let processor = PROCESSOR.lock();
+        let processor = current_processor().lock();
         &processor.idle_task_context as *const TaskContext
     };
 
     switch_task(switched_task_context, idle_task_context);
 }
 
+fn switch_out_current_task() -> *mut TaskContext {
+    let mut processor = current_processor().lock();
+    let tcb = processor.take_current().expect("current tcb must exist");
+
+    let task_context = {
+        let mut tcb = tcb.lock();
+        &mut tcb.context as *mut TaskContext
+    };
+    processor.switched_out = Some(tcb);
+
+    task_context
+}
+
 pub fn exit_current_task_and_schedule(exit_code: i32) -> ! {
-    let current_tcb = PROCESSOR
-        .lock()
-        .take_current()
-        .expect("current tcb must exist");
+    exit_current_task((exit_code & 0xff) << 8)
+}
 
-    {
-        let init_proc_tcb = manager::get_init_proc_tcb();
-        let mut current_tcb = current_tcb.lock();
+fn exit_current_task(wait_status: i32) -> ! {
+    exit_task(&current_tcb(), wait_status);
+
+    let task_context = switch_out_current_task();
+    schedule(task_context);
 
-        current_tcb.status = TaskStatus::Exited(exit_code);
+    unreachable!();
+}
+
+/// Turns `tcb` into a zombie. Its address space and open files are released
+/// right away and its children are handed to init, so only the record its
+/// parent reaps is left. A group leader takes its other threads down first.
+fn exit_task(tcb: &TaskControlBlockWrapper, wait_status: i32) {
+    if !tcb.lock().is_thread() {
+        exit_group_threads(tcb);
+    }
 
-        for child in current_tcb.children.iter() {
+    let (children, parent, fd_table, mem_space) = {
+        let mut tcb = tcb.lock();
+        tcb.status = TaskStatus::Exited(wait_status);
+
+        let mem_space = tcb.mem_space.take().expect("task must not exit twice");
+        if tcb.is_thread() {
+            mem_space
+                .lock()
+                .free_thread_slot(tcb.thread_slot)
+                .expect("free thread slot must succeed");
+        }
+        tcb.signals = SignalState::new();
+        (
+            mem::take(&mut tcb.children),
+            tcb.parent.clone(),
+            mem::take(&mut tcb.fd_table),
+            mem_space,
+        )
+    };
+
+    // closing files can wake other tasks, e.g. readers of a pipe, and the
+    // last thread of a process frees all its frames, so both are done
+    // without holding the task lock
+    drop(fd_table);
+    drop(mem_space);
+
+    if !children.is_empty() {
+        let init_proc_tcb = manager::get_init_proc_tcb();
+        for child in children {
             child.lock().parent = Some(init_proc_tcb.clone());
-            init_proc_tcb.lock().children.push(child.clone());
+            init_proc_tcb.lock().children.push(child);
         }
+        let child_exit = init_proc_tcb.lock().child_exit.clone();
+        child_exit.wake_all();
     }
 
-    let task_context = {
-        let mut tcb = current_tcb.lock();
-        &mut tcb.context as *mut TaskContext
+    if let Some(parent) = parent {
+        let child_exit = parent.lock().child_exit.clone();
+        child_exit.wake_all();
+    }
+}
+
+/// Kills the other threads of the group `leader` leads, waits until they
+/// have exited and reaps them. They still run on the address space and
+/// files the leader is about to release.
+fn exit_group_threads(leader: &TaskControlBlockWrapper) {
+    let (tgid, child_exit) = {
+        let leader = leader.lock();
+        (leader.pid.pid(), leader.child_exit.clone())
     };
-    drop(current_tcb);
 
-    schedule(task_context);
+    // threads created meanwhile are killed on the next wakeup
+    child_exit.wait_until_uninterruptible(|| {
+        let live_threads: Vec<_> = leader
+            .lock()
+            .children
+            .iter()
+            .filter(|v| {
+                let child = v.lock();
+                child.tgid == tgid && !matches!(child.status, TaskStatus::Exited(_))
+            })
+            .cloned()
+            .collect();
+        for thread in &live_threads {
+            signal_task(thread, SIGKILL);
+        }
 
-    unreachable!();
+        live_threads.is_empty()
+    });
+
+    let threads: Vec<_> = {
+        let mut leader = leader.lock();
+        let (threads, children) = mem::take(&mut leader.children)
+            .into_iter()
+            .partition(|v| v.lock().tgid == tgid);
+        leader.children = children;
+        threads
+    };
+    for thread in threads {
+        let tid = thread.lock().pid.pid();
+        manager::remove_task(tid);
+    }
 }
 
 pub fn suspend_current_task_and_schedule() {
-    let tcb = PROCESSOR
-        .lock()
-        .take_current()
-        .expect("current tcb must exist");
+    current_tcb().lock().status = TaskStatus::Ready;
 
-    tcb.lock().status = TaskStatus::Ready;
-    let task_context = {
+    let task_context = switch_out_current_task();
+    schedule(task_context);
+}
+
+/// Switches away from the current task, which must already be marked
+/// `Blocked`. It stays off the run queue until `wake_task` is called.
+pub fn block_current_task_and_schedule() {
+    let task_context = switch_out_current_task();
+    schedule(task_context);
+}
+
+pub fn wake_task(tcb: TaskControlBlockWrapper) {
+    let on_cpu = {
         let mut tcb = tcb.lock();
-        &mut tcb.context as *mut TaskContext
-    };
+        if tcb.status != TaskStatus::Blocked {
+            return;
+        }
 
-    push_to_runq(tcb.clone());
+        tcb.status = TaskStatus::Ready;
+        tcb.on_cpu
+    };
 
-    schedule(task_context);
+    // a task still on its hart is queued by that hart's idle loop once its
+    // context has been saved
+    if !on_cpu {
+        push_to_runq(tcb);
+    }
 }
 
 pub fn get_current_task_trap_context() -> Option<*mut TrapContext> {
-    PROCESSOR
+    current_processor()
         .lock()
         .current()
         .map(|tcb| tcb.lock().get_trap_context_ptr())
 }
 
+pub fn get_current_task_trap_context_va() -> VirtAddr {
+    current_processor()
+        .lock()
+        .current()
+        .map(|tcb| tcb.lock().trap_context_va())
+        .expect("current task trap context va must exist")
+}
+
 pub fn get_current_task_satp() -> usize {
-    PROCESSOR
+    current_processor()
         .lock()
         .current()
-        .map(|tcb| tcb.lock().mem_space.page_table().satp())
+        .map(|tcb| tcb.lock().mem_space().lock().page_table().satp())
         .expect("current task satp must exist")
 }
 
+/// Whether this hart runs a task, as opposed to boot code or the idle loop.
+pub fn has_current_task() -> bool {
+    current_processor().lock().current().is_some()
+}
+
+pub fn current_tcb() -> TaskControlBlockWrapper {
+    current_processor()
+        .lock()
+        .current()
+        .expect("current tcb must exist")
+        .clone()
+}
+
+pub fn with_current_task_mem_space<T>(f: impl FnOnce(&mut MemorySpace) -> T) -> T {
+    let mem_space = current_tcb().lock().mem_space().clone();
+    let mut mem_space = mem_space.lock();
+
+    f(&mut mem_space)
+}
+
+pub fn with_current_task_fd_table<T>(f: impl FnOnce(&mut FdTable) -> T) -> T {
+    let fd_table = current_tcb().lock().fd_table.clone();
+    let mut fd_table = fd_table.lock();
+
+    f(&mut fd_table)
+}
+
+pub fn current_task_cwd() -> String {
+    let cwd = current_tcb().lock().cwd.clone();
+    let path = cwd.lock().clone();
+    path
+}
+
+pub fn set_current_task_cwd(path: String) {
+    let cwd = current_tcb().lock().cwd.clone();
+    *cwd.lock() = path;
+}
+
+pub fn handle_current_task_page_fault(va: VirtAddr, fault: PageFault) -> error::Result<()> {
+    with_current_task_mem_space(|mem_space| mem_space.handle_page_fault(va, fault))
+}
+
 pub fn fork_current_task() -> error::Result<usize> {
-    let current_tcb = PROCESSOR
+    let current_tcb = current_processor()
         .lock()
         .current()
         .expect("current tcb must exist")
@@ -142,59 +339,263 @@ pub fn fork_current_task() -> error::Result<usize> {
     Ok(pid)
 }
 
-pub fn exec_in_tcb(path: &str) -> error::Result<()> {
-    let tcb = PROCESSOR
+pub fn create_thread(entry: usize, arg: usize) -> error::Result<usize> {
+    let thread_tcb = manager::create_thread(current_tcb(), entry, arg)?;
+    let tid = thread_tcb.lock().pid.pid();
+
+    push_to_runq(thread_tcb);
+
+    Ok(tid)
+}
+
+pub fn exec_in_tcb(path: &str, argv: &[String], envp: &[String]) -> error::Result<usize> {
+    let tcb = current_processor()
         .lock()
         .current()
         .expect("current tcb must exist")
         .clone();
 
-    manager::load_elf_in_task(path, tcb)
+    manager::load_elf_in_task(path, argv, envp, tcb)
 }
 
-pub fn wait_child_exit(arg: WaitChildArg) -> error::Result<Option<ExitStatus>> {
-    let exited_child = {
-        let tcb = PROCESSOR
-            .lock()
-            .current()
-            .expect("current tcb must exist")
-            .clone();
+pub fn wait_child_exit(arg: WaitChildArg, nohang: bool) -> error::Result<Option<ExitStatus>> {
+    let tcb = current_tcb();
+    let child_exit = tcb.lock().child_exit.clone();
+
+    let mut result = Ok(None);
+    let completed = child_exit.wait_until(|| {
+        result = reap_exited_child(&tcb, &arg);
+        nohang || !matches!(result, Ok(None))
+    });
+    if !completed {
+        return Err(error::KernelError::Interrupted(
+            "wait interrupted by signal".to_string(),
+        ));
+    }
+
+    if let Ok(Some(exit_status)) = &result {
+        manager::remove_task(exit_status.pid);
+    }
+
+    result
+}
+
+/// Waits for thread `tid` of the current thread group and reaps it. Threads
+/// are children of their group leader, so any thread of the group can join.
+pub fn wait_thread_exit(tid: usize) -> error::Result<ExitStatus> {
+    let tgid = current_tcb().lock().tgid;
+    let leader_tcb = find_task(tgid)?;
+    let child_exit = leader_tcb.lock().child_exit.clone();
+
+    let arg = WaitChildArg::Thread(tid);
+    let mut result = Ok(None);
+    let completed = child_exit.wait_until(|| {
+        result = reap_exited_child(&leader_tcb, &arg);
+        !matches!(result, Ok(None))
+    });
+    if !completed {
+        return Err(error::KernelError::Interrupted(
+            "join interrupted by signal".to_string(),
+        ));
+    }
+
+    let exit_status = result?.expect("joined thread must have exited");
+    manager::remove_task(exit_status.pid);
+
+    Ok(exit_status)
+}
+
+fn reap_exited_child(
+    tcb: &TaskControlBlockWrapper,
+    arg: &WaitChildArg,
+) -> error::Result<Option<ExitStatus>> {
+    let mut tcb = tcb.lock();
+    let pid = tcb.pid.pid();
+
+    // threads of the waiter's own group are only reaped by a join
+    let is_waited = |child: &TaskControlBlock| match arg {
+        WaitChildArg::Any => child.tgid != pid,
+        WaitChildArg::One(child_pid) => child.pid.pid() == *child_pid && child.tgid != pid,
+        WaitChildArg::Thread(tid) => child.pid.pid() == *tid && child.tgid == pid,
+    };
+
+    if !tcb.children.iter().any(|v| is_waited(&v.lock())) {
+        return Err(error::KernelError::NoChildTcb(format!(
+            "no child to wait: {arg:?}"
+        )));
+    }
+
+    let index = tcb.children.iter().position(|v| {
+        let child_tcb = v.lock();
+        is_waited(&child_tcb) && matches!(child_tcb.status, TaskStatus::Exited(_))
+    });
+
+    Ok(index.map(|v| {
+        let exited_child = tcb.children.remove(v);
+        let exited_child = exited_child.lock();
+
+        let wait_status = exited_child
+            .status
+            .get_wait_status()
+            .expect("get wait status must succeed");
+
+        ExitStatus {
+            pid: exited_child.pid.pid(),
+            wait_status,
+        }
+    }))
+}
+
+fn find_task(pid: usize) -> error::Result<TaskControlBlockWrapper> {
+    if pid == 0 {
+        return Ok(current_tcb());
+    }
+
+    manager::find_task_by_pid(pid).ok_or(error::KernelError::TaskNotFound(format!(
+        "task not found: {pid}"
+    )))
+}
+
+pub fn set_task_nice(pid: usize, nice: i32) -> error::Result<()> {
+    find_task(pid)?.lock().sched.set_nice(nice);
+
+    Ok(())
+}
+
+pub fn get_task_nice(pid: usize) -> error::Result<i32> {
+    Ok(find_task(pid)?.lock().sched.nice)
+}
+
+pub fn send_signal(pid: usize, sig: usize) -> error::Result<()> {
+    let tcb = manager::find_task_by_pid(pid).ok_or(error::KernelError::TaskNotFound(format!(
+        "task not found: {pid}"
+    )))?;
+    if sig != 0 {
+        signal_task(&tcb, sig);
+    }
+
+    Ok(())
+}
+
+/// Raises `sig` for a task that has not exited, waking it if it sleeps
+/// interruptibly and can take the signal.
+fn signal_task(tcb: &TaskControlBlockWrapper, sig: usize) {
+    let interrupt = {
         let mut tcb = tcb.lock();
+        if matches!(tcb.status, TaskStatus::Exited(_)) {
+            return;
+        }
+
+        tcb.signals.raise(sig);
+        tcb.signals.has_deliverable()
+    };
+
+    if interrupt {
+        wake_task(tcb.clone());
+    }
+}
 
-        let index = match arg {
-            WaitChildArg::Any => tcb.children.iter().position(|v| {
-                let child_tcb = v.lock();
-                matches!(child_tcb.status, TaskStatus::Exited(_))
-            }),
-            WaitChildArg::One(pid) => tcb.children.iter().position(|v| {
-                let child_tcb = v.lock();
-                child_tcb.pid.pid() == pid && matches!(child_tcb.status, TaskStatus::Exited(_))
-            }),
+pub fn raise_current_task_signal(sig: usize) {
+    current_tcb().lock().signals.raise(sig);
+}
+
+pub fn force_current_task_signal(sig: usize) {
+    current_tcb().lock().signals.force(sig);
+}
+
+/// Delivers pending signals of the current task on its way back to user
+/// space. Does not return if a signal terminates the task.
+pub fn handle_current_task_signals() {
+    let (terminated_by, fault_frame) = {
+        let tcb = current_tcb();
+        let mut tcb = tcb.lock();
+        let trap_context = unsafe { &mut *tcb.get_trap_context_ptr() };
+        let terminated_by = tcb.signals.deliver(trap_context);
+
+        // s0 is the frame pointer
+        let fault_frame = match terminated_by {
+            Some(SIGSEGV | SIGILL | SIGBUS | SIGTRAP) => Some((
+                trap_context.sepc,
+                trap_context.regs[8],
+                tcb.mem_space().clone(),
+            )),
+            _ => None,
         };
 
-        index.map(|v| tcb.children.remove(v))
+        (terminated_by, fault_frame)
     };
 
-    match exited_child {
-        Some(exited_child) => {
-            let exited_child = exited_child.lock();
+    if let Some((pc, fp, mem_space)) = fault_frame {
+        backtrace::print_user(pc, fp, &mut mem_space.lock());
+    }
+
+    if let Some(sig) = terminated_by {
+        exit_current_task(sig as i32);
+    }
+}
 
-            let exited_code = exited_child
-                .status
-                .get_exited_code()
-                .expect("get exited code must succeed");
+pub fn set_current_task_signal_action(
+    sig: usize,
+    action: Option<SignalAction>,
+) -> error::Result<SignalAction> {
+    let tcb = current_tcb();
+    let mut tcb = tcb.lock();
 
-            Ok(Some(ExitStatus {
-                pid: exited_child.pid.pid(),
-                exit_code: exited_code,
-            }))
-        }
-        None => Ok(None),
+    let old_action = tcb.signals.action(sig);
+    if let Some(action) = action {
+        tcb.signals.set_action(sig, action)?;
     }
+
+    Ok(old_action)
+}
+
+pub fn set_current_task_signal_mask(
+    how: usize,
+    set: Option<SignalSet>,
+) -> error::Result<SignalSet> {
+    let tcb = current_tcb();
+    let mut tcb = tcb.lock();
+
+    let old_blocked = tcb.signals.blocked();
+    if let Some(set) = set {
+        let blocked = match how {
+            SIG_BLOCK => old_blocked.union(set),
+            SIG_UNBLOCK => old_blocked.difference(set),
+            SIG_SETMASK => set,
+            _ => {
+                return Err(error::KernelError::Signal(format!(
+                    "invalid sigprocmask how: {how}"
+                )))
+            }
+        };
+        tcb.signals.set_blocked(blocked);
+    }
+
+    Ok(old_blocked)
+}
+
+/// Restores the context saved when the current signal handler was entered
+/// and returns the restored `a0`.
+pub fn return_from_signal_handler() -> error::Result<usize> {
+    let tcb = current_tcb();
+    let mut tcb = tcb.lock();
+
+    let trap_context = unsafe { &mut *tcb.get_trap_context_ptr() };
+    tcb.signals.restore(trap_context)?;
+
+    Ok(trap_context.regs[10])
 }
 
 pub fn getpid() -> usize {
-    PROCESSOR
+    current_processor()
+        .lock()
+        .current()
+        .map(|v| v.lock().tgid)
+        .expect("current tcb must exist")
+}
+
+pub fn gettid() -> usize {
+    current_processor()
         .lock()
         .current()
         .map(|v| v.lock().pid.pid())
@@ -205,6 +606,7 @@ pub fn getpid() -> usize {
 pub enum WaitChildArg {
     Any,
     One(usize),
+    Thread(usize),
 }
 
 impl WaitChildArg {
@@ -212,7 +614,11 @@ impl WaitChildArg {
         Ok(match pid {
             -1 => Self::Any,
             pid @ 1.. => Self::One(pid as usize),
-            _ => return Err(error::KernelError::Common(format!("invalid pid: {pid}"))),
+            _ => {
+                return Err(error::KernelError::InvalidArgument(format!(
+                    "invalid pid: {pid}"
+                )))
+            }
         })
     }
 }
@@ -220,5 +626,106 @@ impl WaitChildArg {
 #[derive(Debug)]
 pub struct ExitStatus {
     pub pid: usize,
-    pub exit_code: i32,
+    pub wait_status: i32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mm;
+
+    fn fork_exit_and_reap(parent: &TaskControlBlockWrapper) {
+        let child = manager::fork_tcb(parent.clone()).expect("fork must succeed");
+        exit_task(&child, 0);
+        drop(child);
+
+        let exit_status = reap_exited_child(parent, &WaitChildArg::Any)
+            .expect("child must exist")
+            .expect("child must have exited");
+        manager::remove_task(exit_status.pid);
+    }
+
+    #[test_case]
+    fn test_exit_releases_frames() {
+        let init_proc_tcb = manager::get_init_proc_tcb();
+
+        // the first cycle may leave kernel page table pages behind for the
+        // kernel stack, which later cycles reuse
+        fork_exit_and_reap(&init_proc_tcb);
+        let baseline = mm::free_frames_count();
+
+        for _ in 0..100 {
+            fork_exit_and_reap(&init_proc_tcb);
+        }
+
+        assert_eq!(mm::free_frames_count(), baseline);
+    }
+
+    #[test_case]
+    fn test_leader_exit_reaps_threads() {
+        let init_proc_tcb = manager::get_init_proc_tcb();
+        let leader = manager::fork_tcb(init_proc_tcb.clone()).expect("fork must succeed");
+        let thread = manager::create_thread(leader.clone(), 0, 0).expect("thread must be created");
+        let tid = thread.lock().pid.pid();
+
+        // the thread dies from the SIGKILL of its exiting leader
+        exit_task(&thread, SIGKILL as i32);
+        exit_task(&leader, 0);
+        drop(leader);
+
+        assert!(manager::find_task_by_pid(tid).is_none());
+        assert!(!init_proc_tcb
+            .lock()
+            .children
+            .iter()
+            .any(|v| v.lock().pid.pid() == tid));
+
+        let exit_status = reap_exited_child(&init_proc_tcb, &WaitChildArg::Any)
+            .expect("leader must exist")
+            .expect("leader must have exited");
+        manager::remove_task(exit_status.pid);
+    }
+
+    #[test_case]
+    fn test_exec_passes_argv() {
+        let init_proc_tcb = manager::get_init_proc_tcb();
+        let child = manager::fork_tcb(init_proc_tcb.clone()).expect("fork must succeed");
+
+        let argv = [
+            "hello_world".to_string(),
+            "1".to_string(),
+            "two".to_string(),
+        ];
+        let user_sp = manager::load_elf_in_task("hello_world", &argv, &[], child.clone())
+            .expect("exec must succeed");
+
+        {
+            let tcb = child.lock();
+            let trap_context = unsafe { &*tcb.get_trap_context_ptr() };
+            assert_eq!(trap_context.regs[2], user_sp);
+            assert_eq!(trap_context.regs[10], user_sp);
+
+            let mut mem_space = tcb.mem_space().lock();
+            let argc = mm::UserPtr::from(user_sp as *const usize)
+                .read(&mut mem_space)
+                .expect("read argc must succeed");
+            assert_eq!(argc, argv.len());
+            for (i, arg) in argv.iter().enumerate() {
+                let arg_ptr = mm::UserPtr::from((user_sp as *const usize).wrapping_add(i + 1))
+                    .read(&mut mem_space)
+                    .expect("read argv pointer must succeed");
+                let received =
+                    mm::read_c_str(&mut mem_space, arg_ptr, crate::config::MAX_USER_STR_LEN)
+                        .expect("read argv string must succeed");
+                assert_eq!(&received, arg);
+            }
+        }
+
+        exit_task(&child, 0);
+        drop(child);
+        let exit_status = reap_exited_child(&init_proc_tcb, &WaitChildArg::Any)
+            .expect("child must exist")
+            .expect("child must have exited");
+        manager::remove_task(exit_status.pid);
+    }
 }
@@ -0,0 +1,37 @@
+#[cfg(not(feature = "stride-scheduler"))]
+mod round_robin;
+#[cfg(feature = "stride-scheduler")]
+mod stride;
+
+use super::tcb::TaskControlBlockWrapper;
+
+#[cfg(not(feature = "stride-scheduler"))]
+pub type DefaultScheduler = round_robin::RoundRobinScheduler;
+#[cfg(feature = "stride-scheduler")]
+pub type DefaultScheduler = stride::StrideScheduler;
+
+pub const MIN_NICE: i32 = -20;
+pub const MAX_NICE: i32 = 19;
+
+pub trait Scheduler {
+    fn push(&mut self, tcb: TaskControlBlockWrapper);
+    fn fetch(&mut self) -> Option<TaskControlBlockWrapper>;
+}
+
+/// Per-task scheduling state, kept in the task control block.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SchedEntity {
+    pub nice: i32,
+    pub pass: u64,
+}
+
+impl SchedEntity {
+    pub fn set_nice(&mut self, nice: i32) {
+        self.nice = nice.clamp(MIN_NICE, MAX_NICE);
+    }
+
+    /// Maps nice `-20..=19` to priority `40..=1`.
+    pub fn priority(&self) -> u64 {
+        (20 - self.nice) as u64
+    }
+}
@@ -0,0 +1,19 @@
+use alloc::collections::vec_deque::VecDeque;
+
+use super::Scheduler;
+use crate::task::tcb::TaskControlBlockWrapper;
+
+#[derive(Default)]
+pub struct RoundRobinScheduler {
+    runq: VecDeque<TaskControlBlockWrapper>,
+}
+
+impl Scheduler for RoundRobinScheduler {
+    fn push(&mut self, tcb: TaskControlBlockWrapper) {
+        self.runq.push_back(tcb);
+    }
+
+    fn fetch(&mut self) -> Option<TaskControlBlockWrapper> {
+        self.runq.pop_front()
+    }
+}
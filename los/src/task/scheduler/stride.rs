@@ -0,0 +1,43 @@
+use alloc::collections::btree_map::BTreeMap;
+
+use super::Scheduler;
+use crate::task::tcb::TaskControlBlockWrapper;
+
+const BIG_STRIDE: u64 = 1 << 20;
+
+/// Always runs the ready task with the smallest pass, advancing it by a
+/// stride inversely proportional to its priority, so CPU time is shared in
+/// proportion to priority.
+#[derive(Default)]
+pub struct StrideScheduler {
+    runq: BTreeMap<(u64, u64), TaskControlBlockWrapper>,
+    /// Pass of the last fetched task. Tasks coming back from a long sleep
+    /// start here instead of monopolizing the hart to catch up.
+    current_pass: u64,
+    seq: u64,
+}
+
+impl Scheduler for StrideScheduler {
+    fn push(&mut self, tcb: TaskControlBlockWrapper) {
+        let pass = {
+            let mut tcb = tcb.lock();
+            tcb.sched.pass = tcb.sched.pass.max(self.current_pass);
+            tcb.sched.pass
+        };
+
+        self.seq += 1;
+        self.runq.insert((pass, self.seq), tcb);
+    }
+
+    fn fetch(&mut self) -> Option<TaskControlBlockWrapper> {
+        let (_, tcb) = self.runq.pop_first()?;
+
+        {
+            let mut tcb = tcb.lock();
+            self.current_pass = tcb.sched.pass;
+            tcb.sched.pass += BIG_STRIDE / tcb.sched.priority();
+        }
+
+        Some(tcb)
+    }
+}
@@ -0,0 +1,243 @@
+use crate::{error, trap::TrapContext};
+use alloc::{format, string::ToString};
+
+pub const SIGILL: usize = 4;
+pub const SIGTRAP: usize = 5;
+pub const SIGBUS: usize = 7;
+pub const SIGKILL: usize = 9;
+pub const SIGSEGV: usize = 11;
+pub const SIGPIPE: usize = 13;
+pub const SIGCHLD: usize = 17;
+pub const SIGCONT: usize = 18;
+pub const SIGSTOP: usize = 19;
+pub const SIGURG: usize = 23;
+pub const SIGWINCH: usize = 28;
+pub const MAX_SIGNAL: usize = 31;
+
+pub const SIG_DFL: usize = 0;
+pub const SIG_IGN: usize = 1;
+
+pub const SIG_BLOCK: usize = 0;
+pub const SIG_UNBLOCK: usize = 1;
+pub const SIG_SETMASK: usize = 2;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SignalSet(u64);
+
+impl SignalSet {
+    const ALL: u64 = ((1 << (MAX_SIGNAL + 1)) - 1) & !1;
+
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    pub const fn from_bits(bits: u64) -> Self {
+        Self(bits & Self::ALL)
+    }
+
+    pub const fn bits(&self) -> u64 {
+        self.0
+    }
+
+    pub fn contains(&self, sig: usize) -> bool {
+        self.0 & (1 << sig) != 0
+    }
+
+    pub fn add(&mut self, sig: usize) {
+        self.0 |= 1 << sig;
+    }
+
+    pub fn remove(&mut self, sig: usize) {
+        self.0 &= !(1 << sig);
+    }
+
+    pub fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    pub fn difference(self, other: Self) -> Self {
+        Self(self.0 & !other.0)
+    }
+}
+
+/// Layout shared with user space for `sigaction`. `restorer` is where the
+/// handler returns to and must end up calling `sigreturn`.
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+pub struct SignalAction {
+    pub handler: usize,
+    pub mask: u64,
+    pub restorer: usize,
+}
+
+enum DefaultAction {
+    Terminate,
+    Ignore,
+}
+
+fn default_action(sig: usize) -> DefaultAction {
+    match sig {
+        // tasks cannot be stopped yet, so SIGSTOP only has to be uncatchable
+        SIGCHLD | SIGCONT | SIGSTOP | SIGURG | SIGWINCH => DefaultAction::Ignore,
+        _ => DefaultAction::Terminate,
+    }
+}
+
+pub fn is_valid(sig: usize) -> bool {
+    (1..=MAX_SIGNAL).contains(&sig)
+}
+
+fn is_catchable(sig: usize) -> bool {
+    sig != SIGKILL && sig != SIGSTOP
+}
+
+#[derive(Debug)]
+pub struct SignalState {
+    pending: SignalSet,
+    blocked: SignalSet,
+    actions: [SignalAction; MAX_SIGNAL + 1],
+    /// Trap context and mask to restore on `sigreturn` while a handler runs.
+    saved: Option<(TrapContext, SignalSet)>,
+}
+
+impl SignalState {
+    pub fn new() -> Self {
+        Self {
+            pending: SignalSet::empty(),
+            blocked: SignalSet::empty(),
+            actions: [SignalAction::default(); MAX_SIGNAL + 1],
+            saved: None,
+        }
+    }
+
+    /// State of a forked task: handlers and mask are inherited, pending
+    /// signals are not.
+    pub fn fork(&self) -> Self {
+        Self {
+            pending: SignalSet::empty(),
+            blocked: self.blocked,
+            actions: self.actions,
+            saved: None,
+        }
+    }
+
+    /// Handlers point into the old image after exec, so they fall back to
+    /// the default action. Ignored signals stay ignored.
+    pub fn reset_handlers(&mut self) {
+        for action in self.actions.iter_mut() {
+            if action.handler != SIG_IGN {
+                *action = SignalAction::default();
+            }
+        }
+        self.saved = None;
+    }
+
+    pub fn action(&self, sig: usize) -> SignalAction {
+        self.actions[sig]
+    }
+
+    pub fn set_action(&mut self, sig: usize, action: SignalAction) -> error::Result<()> {
+        if !is_catchable(sig) {
+            return Err(error::KernelError::Signal(format!(
+                "action of signal {sig} cannot be changed"
+            )));
+        }
+
+        self.actions[sig] = action;
+
+        Ok(())
+    }
+
+    pub fn blocked(&self) -> SignalSet {
+        self.blocked
+    }
+
+    pub fn set_blocked(&mut self, mut blocked: SignalSet) {
+        blocked.remove(SIGKILL);
+        blocked.remove(SIGSTOP);
+        self.blocked = blocked;
+    }
+
+    pub fn raise(&mut self, sig: usize) {
+        self.pending.add(sig);
+    }
+
+    /// Raises a signal caused by the task itself, such as a fault. It cannot
+    /// be blocked, and if it cannot reach a handler the default action runs.
+    pub fn force(&mut self, sig: usize) {
+        self.pending.add(sig);
+        self.blocked.remove(sig);
+
+        let handler = self.actions[sig].handler;
+        if handler == SIG_IGN || (handler != SIG_DFL && self.saved.is_some()) {
+            self.actions[sig] = SignalAction::default();
+        }
+    }
+
+    /// Whether a pending signal would do anything when delivered, which is
+    /// what interrupts a blocked task.
+    pub fn has_deliverable(&self) -> bool {
+        (1..=MAX_SIGNAL).any(|sig| {
+            self.pending.difference(self.blocked).contains(sig)
+                && match self.actions[sig].handler {
+                    SIG_IGN => false,
+                    SIG_DFL => matches!(default_action(sig), DefaultAction::Terminate),
+                    _ => true,
+                }
+        })
+    }
+
+    /// Delivers pending signals before returning to user space. A handler is
+    /// entered by rewriting `trap_context`; returns the signal if the task
+    /// has to be terminated instead.
+    pub fn deliver(&mut self, trap_context: &mut TrapContext) -> Option<usize> {
+        for sig in 1..=MAX_SIGNAL {
+            if !self.pending.difference(self.blocked).contains(sig) {
+                continue;
+            }
+
+            let action = self.actions[sig];
+            match action.handler {
+                SIG_IGN => self.pending.remove(sig),
+                SIG_DFL => {
+                    self.pending.remove(sig);
+                    if let DefaultAction::Terminate = default_action(sig) {
+                        return Some(sig);
+                    }
+                }
+                handler => {
+                    // one handler at a time, the rest wait for sigreturn
+                    if self.saved.is_some() {
+                        continue;
+                    }
+
+                    self.pending.remove(sig);
+                    self.saved = Some((trap_context.clone(), self.blocked));
+
+                    let mut blocked = self.blocked.union(SignalSet::from_bits(action.mask));
+                    blocked.add(sig);
+                    self.set_blocked(blocked);
+
+                    trap_context.sepc = handler;
+                    trap_context.regs[1] = action.restorer;
+                    trap_context.regs[10] = sig;
+
+                    return None;
+                }
+            }
+        }
+
+        None
+    }
+
+    pub fn restore(&mut self, trap_context: &mut TrapContext) -> error::Result<()> {
+        let (saved_trap_context, blocked) = self.saved.take().ok_or(error::KernelError::Signal(
+            "sigreturn outside of a signal handler".to_string(),
+        ))?;
+
+        *trap_context = saved_trap_context;
+        self.blocked = blocked;
+
+        Ok(())
+    }
+}
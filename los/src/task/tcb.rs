@@ -1,6 +1,7 @@
-use super::pid::Pid;
+use super::{pid::Pid, scheduler::SchedEntity, signal::SignalState, wait_queue::WaitQueue};
 use crate::{
-    mm::{self, KernelStack, MemorySpace},
+    fs::FdTable,
+    mm::{self, address::VirtAddr, KernelStack, MemorySpace},
     trap::TrapContext,
 };
 use alloc::{string::String, sync::Arc, vec::Vec};
@@ -18,12 +19,28 @@ impl From<TaskControlBlock> for TaskControlBlockWrapper {
 pub struct TaskControlBlock {
     pub name: String,
     pub pid: Pid,
+    /// Pid of the thread group leader, which is the process id seen by user
+    /// space. Equal to `pid` for the main thread.
+    pub tgid: usize,
+    /// Selects this thread's trap context page and user stack in `mem_space`.
+    pub thread_slot: usize,
     pub kernel_stack: KernelStack,
-    pub mem_space: MemorySpace,
+    /// Released as soon as the task exits, see `mem_space()`.
+    pub mem_space: Option<Arc<Mutex<MemorySpace>>>,
+    /// Shared by the threads of a process, copied on fork.
+    pub fd_table: Arc<Mutex<FdTable>>,
+    /// Absolute working directory, shared by the threads of a process and
+    /// copied on fork.
+    pub cwd: Arc<Mutex<String>>,
     pub context: TaskContext,
     pub status: TaskStatus,
+    pub sched: SchedEntity,
     pub parent: Option<TaskControlBlockWrapper>,
     pub children: Vec<TaskControlBlockWrapper>,
+    pub child_exit: Arc<WaitQueue>,
+    pub signals: SignalState,
+    /// Whether the task's context is still live on a hart.
+    pub on_cpu: bool,
 }
 
 impl TaskControlBlock {
@@ -36,27 +53,44 @@ impl TaskControlBlock {
     ) -> Self {
         Self {
             name,
+            tgid: pid.pid(),
             pid,
+            thread_slot: 0,
             context: TaskContext::init(ra, kernel_stack.get_sp()),
             status: TaskStatus::Ready,
+            sched: SchedEntity::default(),
             kernel_stack,
-            mem_space,
+            mem_space: Some(Arc::new(Mutex::new(mem_space))),
+            fd_table: Arc::new(Mutex::new(FdTable::with_stdio())),
+            cwd: Arc::new(Mutex::new(String::from("/"))),
             parent: None,
             children: Vec::new(),
+            child_exit: Arc::new(WaitQueue::new()),
+            signals: SignalState::new(),
+            on_cpu: false,
         }
     }
 
+    /// Address space of the task, which only a task that has not exited
+    /// still has.
+    pub fn mem_space(&self) -> &Arc<Mutex<MemorySpace>> {
+        self.mem_space
+            .as_ref()
+            .expect("memory space of an exited task is released")
+    }
+
     pub fn get_trap_context_ptr(&self) -> *mut TrapContext {
-        let trap_context_va = mm::trap_context_va();
-        let mut trap_context_ppn = self
-            .mem_space
-            .page_table()
-            .translate_vpn(trap_context_va.floor_vpn())
-            .expect("translate must succeed")
-            .ppn();
-        let trap_context = unsafe { trap_context_ppn.get_mut::<TrapContext>() };
+        self.mem_space()
+            .lock()
+            .trap_context_mut_ptr(self.thread_slot)
+    }
+
+    pub fn trap_context_va(&self) -> VirtAddr {
+        mm::trap_context_va(self.thread_slot)
+    }
 
-        trap_context
+    pub fn is_thread(&self) -> bool {
+        self.pid.pid() != self.tgid
     }
 
     pub fn update_task_status(&mut self, status: TaskStatus) {
@@ -84,11 +118,15 @@ impl TaskContext {
 pub enum TaskStatus {
     Ready,
     Running,
+    Blocked,
+    /// Holds the wait status: the exit code in bits 8..16, or the number of
+    /// the signal that terminated the task. An exited task is a zombie that
+    /// only keeps its pid and kernel stack until it is reaped.
     Exited(i32),
 }
 
 impl TaskStatus {
-    pub fn get_exited_code(&self) -> Option<i32> {
+    pub fn get_wait_status(&self) -> Option<i32> {
         match self {
             TaskStatus::Exited(code) => Some(*code),
             _ => None,
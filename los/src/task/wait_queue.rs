@@ -0,0 +1,132 @@
+use alloc::collections::{btree_map::BTreeMap, vec_deque::VecDeque};
+use alloc::{sync::Arc, vec::Vec};
+use core::mem;
+use riscv::register::time;
+use spin::Mutex;
+
+use super::{
+    processor,
+    tcb::{TaskControlBlockWrapper, TaskStatus},
+};
+
+#[derive(Debug)]
+pub struct WaitQueue {
+    waiters: Mutex<VecDeque<TaskControlBlockWrapper>>,
+}
+
+impl WaitQueue {
+    pub const fn new() -> Self {
+        Self {
+            waiters: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Blocks the current task until `condition` holds. The condition is
+    /// checked with the queue locked, so a wakeup that happens between the
+    /// check and blocking is not lost. Returns `false` if a signal
+    /// interrupted the wait first.
+    pub fn wait_until(&self, condition: impl FnMut() -> bool) -> bool {
+        self.wait(condition, true)
+    }
+
+    /// Like `wait_until`, but signals do not end the wait. For waits that
+    /// always finish shortly, like device I/O.
+    pub fn wait_until_uninterruptible(&self, condition: impl FnMut() -> bool) {
+        self.wait(condition, false);
+    }
+
+    fn wait(&self, mut condition: impl FnMut() -> bool, interruptible: bool) -> bool {
+        loop {
+            {
+                let mut waiters = self.waiters.lock();
+                if condition() {
+                    return true;
+                }
+
+                let tcb = processor::current_tcb();
+                let mut tcb_inner = tcb.lock();
+                if interruptible && tcb_inner.signals.has_deliverable() {
+                    drop(tcb_inner);
+                    waiters.retain(|v| !Arc::ptr_eq(v, &tcb));
+                    return false;
+                }
+
+                tcb_inner.status = TaskStatus::Blocked;
+                drop(tcb_inner);
+                if !waiters.iter().any(|v| Arc::ptr_eq(v, &tcb)) {
+                    waiters.push_back(tcb);
+                }
+            }
+
+            processor::block_current_task_and_schedule();
+        }
+    }
+
+    pub fn wake_all(&self) {
+        let waiters = mem::take(&mut *self.waiters.lock());
+        for tcb in waiters {
+            processor::wake_task(tcb);
+        }
+    }
+}
+
+/// Tasks sleeping until a deadline measured in `time` ticks.
+pub struct SleepQueue {
+    sleepers: Mutex<BTreeMap<(usize, usize), TaskControlBlockWrapper>>,
+}
+
+impl SleepQueue {
+    pub const fn new() -> Self {
+        Self {
+            sleepers: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// Returns `false` if a signal woke the task before `deadline`.
+    pub fn sleep_until(&self, deadline: usize) -> bool {
+        let pid = {
+            let mut sleepers = self.sleepers.lock();
+            if time::read() >= deadline {
+                return true;
+            }
+
+            let tcb = processor::current_tcb();
+            let pid = {
+                let mut tcb = tcb.lock();
+                if tcb.signals.has_deliverable() {
+                    return false;
+                }
+
+                tcb.status = TaskStatus::Blocked;
+                tcb.pid.pid()
+            };
+            sleepers.insert((deadline, pid), tcb);
+
+            pid
+        };
+
+        processor::block_current_task_and_schedule();
+
+        // still queued if something other than the deadline woke us up
+        self.sleepers.lock().remove(&(deadline, pid)).is_none()
+    }
+
+    pub fn wake_expired(&self) {
+        let now = time::read();
+
+        let mut expired = Vec::new();
+        {
+            let mut sleepers = self.sleepers.lock();
+            while let Some(entry) = sleepers.first_entry() {
+                if entry.key().0 > now {
+                    break;
+                }
+                expired.push(entry.remove());
+            }
+        }
+
+        for tcb in expired {
+            processor::wake_task(tcb);
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{device_tree, sbi};
+use crate::{device_tree, sbi, task::wait_queue::SleepQueue};
 use core::cell::SyncUnsafeCell;
 use lazy_static::lazy_static;
 use riscv::register::time;
@@ -7,9 +7,12 @@ lazy_static! {
     static ref TICKS_PER_SEC: SyncUnsafeCell<usize> = SyncUnsafeCell::new(0);
 }
 
+static SLEEP_QUEUE: SleepQueue = SleepQueue::new();
+
 const MS_PER_TIME_SLICE: usize = 10;
 const MS_PER_SEC: usize = 1000;
 const US_PER_SEC: usize = MS_PER_SEC * 1000;
+const NS_PER_SEC: usize = US_PER_SEC * 1000;
 
 pub fn init() {
     unsafe {
@@ -19,6 +22,10 @@ pub fn init() {
     set_next_trigger()
 }
 
+pub fn init_hart() {
+    set_next_trigger()
+}
+
 pub fn get_time() -> TimeVal {
     let usec = (time::read() / (get_ticks_per_sec() / US_PER_SEC)) as u64;
     let sec = usec / US_PER_SEC as u64;
@@ -30,6 +37,31 @@ pub fn set_next_trigger() {
     sbi::set_timer(time::read() + get_ticks_per_sec() / MS_PER_SEC * MS_PER_TIME_SLICE);
 }
 
+/// Sleeps for `duration` and returns the time left if a signal cut the
+/// sleep short.
+pub fn sleep(duration: TimeSpec) -> Option<TimeSpec> {
+    let ticks_per_sec = get_ticks_per_sec() as u64;
+    let ticks = duration
+        .sec
+        .saturating_mul(ticks_per_sec)
+        .saturating_add(duration.nsec * ticks_per_sec / NS_PER_SEC as u64);
+    let deadline = (time::read() as u64).saturating_add(ticks) as usize;
+
+    if SLEEP_QUEUE.sleep_until(deadline) {
+        return None;
+    }
+
+    let left = deadline.saturating_sub(time::read()) as u64;
+    Some(TimeSpec {
+        sec: left / ticks_per_sec,
+        nsec: left % ticks_per_sec * NS_PER_SEC as u64 / ticks_per_sec,
+    })
+}
+
+pub fn wake_sleepers() {
+    SLEEP_QUEUE.wake_expired();
+}
+
 fn get_ticks_per_sec() -> usize {
     unsafe { *TICKS_PER_SEC.get() }
 }
@@ -40,3 +72,23 @@ pub struct TimeVal {
     pub sec: u64,
     pub usec: u64,
 }
+
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct TimeSpec {
+    pub sec: u64,
+    pub nsec: u64,
+}
+
+impl TimeSpec {
+    pub const fn from_millis(ms: u64) -> Self {
+        Self {
+            sec: ms / MS_PER_SEC as u64,
+            nsec: ms % MS_PER_SEC as u64 * (NS_PER_SEC / MS_PER_SEC) as u64,
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.nsec < NS_PER_SEC as u64
+    }
+}
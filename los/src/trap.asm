@@ -33,6 +33,8 @@ _s_trap_enter:
     ld t1, 35*8(sp)
     # trap_handler
     ld t2, 36*8(sp)
+    # kernel_tp
+    ld tp, 37*8(sp)
 
     csrw satp, t0
     sfence.vma
@@ -62,4 +64,83 @@ _s_trap_return:
     .endr
 
     ld sp, 2*8(sp)
-    sret
\ No newline at end of file
+    sret
+    .section .text
+    .align 2
+    .globl _kernel_trap_enter
+_kernel_trap_enter:
+    # sscratch is only live in U-mode, borrow it to free t0
+    csrw sscratch, t0
+
+    # a load or store page fault in the page sp points to means the stack
+    # overflowed into its guard page, pushing the context would fault again
+    csrr t0, scause
+    addi t0, t0, -13
+    beqz t0, 2f
+    addi t0, t0, -2
+    bnez t0, 3f
+2:
+    csrr t0, stval
+    xor t0, t0, sp
+    srli t0, t0, 12
+    bnez t0, 3f
+
+    # switch to this hart's trap stack, tp is restored after the offset
+    addi tp, tp, 1
+    slli tp, tp, {kernel_trap_stack_shift}
+    la t0, kernel_trap_stack_lower_bound
+    add t0, t0, tp
+    srli tp, tp, {kernel_trap_stack_shift}
+    addi tp, tp, -1
+    # swap sp and t0
+    xor sp, sp, t0
+    xor t0, sp, t0
+    xor sp, sp, t0
+    j 4f
+
+3:
+    mv t0, sp
+4:
+    # KernelTrapContext on the current kernel stack, t0 holds the
+    # interrupted sp
+    addi sp, sp, -34*8
+    sd t0, 2*8(sp)
+    csrr t0, sscratch
+
+    sd x1, 1*8(sp)
+    .set i, 3
+    .rept 29
+        SAVE_X %i
+        .set i, i+1
+    .endr
+
+    csrr t0, sstatus
+    sd t0, 32*8(sp)
+    csrr t0, sepc
+    sd t0, 33*8(sp)
+
+    mv a0, sp
+    call kernel_trap_handler
+
+    ld t0, 32*8(sp)
+    csrw sstatus, t0
+    ld t0, 33*8(sp)
+    csrw sepc, t0
+
+    ld x1, 1*8(sp)
+    .set i, 3
+    .rept 29
+        LOAD_X %i
+        .set i, i+1
+    .endr
+
+    ld sp, 2*8(sp)
+    sret
+
+    .section .bss.stack
+    .align 12
+    .globl kernel_trap_stack_lower_bound
+    .globl kernel_trap_stack_top
+kernel_trap_stack_lower_bound:
+    .space {kernel_trap_stack_size} * {max_harts}
+kernel_trap_stack_top:
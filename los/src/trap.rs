@@ -1,14 +1,21 @@
 use crate::{
-    mm::{self},
-    println, syscall,
-    task::processor,
+    backtrace, drivers, hart,
+    mm::{self, address::PAGE_SIZE, PageFault},
+    println, sbi, syscall,
+    task::{
+        processor,
+        signal::{SIGBUS, SIGILL, SIGSEGV, SIGTRAP},
+    },
     timer,
 };
-use core::arch::asm;
+use core::{
+    arch::asm,
+    sync::atomic::{AtomicBool, Ordering},
+};
 use riscv::register::{scause, sepc, sie, sstatus, stval, stvec};
 
 pub fn init() {
-    set_stvec_to_user_trap();
+    set_stvec_to_kernel_trap();
     init_timer();
 }
 
@@ -19,14 +26,85 @@ fn set_stvec_to_user_trap() {
 }
 
 fn set_stvec_to_kernel_trap() {
+    extern "C" {
+        fn _kernel_trap_enter();
+    }
+
     unsafe {
-        stvec::write(kernel_trap_entry as usize, stvec::TrapMode::Direct);
+        stvec::write(_kernel_trap_enter as usize, stvec::TrapMode::Direct);
     }
 }
 
+/// `scause` of a misaligned load, which the riscv crate does not decode.
+const SCAUSE_LOAD_MISALIGNED: usize = 4;
+
+/// Set by the first fatal kernel trap, so a fault while reporting it does
+/// not recurse.
+static KERNEL_TRAP_FATAL: AtomicBool = AtomicBool::new(false);
+
+/// Handles traps taken in S-mode. Interrupts only arrive while a hart waits
+/// in `wait_for_interrupt`, and any exception is a kernel bug that halts the
+/// system.
 #[no_mangle]
-fn kernel_trap_entry() {
-    panic!("trap in kernel mode")
+extern "C" fn kernel_trap_handler(context: &mut KernelTrapContext) {
+    let scause = scause::read();
+    let stval = stval::read();
+
+    match scause.cause() {
+        scause::Trap::Interrupt(scause::Interrupt::SupervisorTimer) => {
+            timer::set_next_trigger();
+            timer::wake_sleepers();
+        }
+        scause::Trap::Interrupt(scause::Interrupt::SupervisorExternal) => {
+            drivers::plic::handle_interrupt();
+        }
+        cause => {
+            if KERNEL_TRAP_FATAL.swap(true, Ordering::SeqCst) {
+                sbi::shutdown(true);
+            }
+
+            if is_stack_overflow(cause, stval, context.regs[2]) {
+                println!(
+                    "[TRAP] kernel stack overflow on hart {}, sp: {:#x}",
+                    hart::hart_id(),
+                    context.regs[2]
+                );
+            }
+            println!(
+                "[TRAP] kernel trap on hart {}: {:?} scause: {:#x} stval: {:#x} sepc: {:#x}",
+                hart::hart_id(),
+                cause,
+                scause.bits(),
+                stval,
+                context.sepc
+            );
+            backtrace::print(context.sepc, context.regs[8]);
+
+            sbi::shutdown(true);
+        }
+    }
+}
+
+/// A load or store page fault in the page `sp` points to, the stack grew
+/// into its guard page. `_kernel_trap_enter` makes the same check to move to
+/// the hart's trap stack.
+fn is_stack_overflow(cause: scause::Trap, stval: usize, sp: usize) -> bool {
+    matches!(
+        cause,
+        scause::Trap::Exception(
+            scause::Exception::LoadPageFault | scause::Exception::StorePageFault
+        )
+    ) && stval / PAGE_SIZE == sp / PAGE_SIZE
+}
+
+/// Sleeps until an interrupt arrives, handling it on the kernel trap vector
+/// before returning. Interrupts stay disabled in the kernel otherwise.
+pub fn wait_for_interrupt() {
+    unsafe {
+        sstatus::set_sie();
+        asm!("wfi");
+        sstatus::clear_sie();
+    }
 }
 
 pub fn user_trap_return_va() -> usize {
@@ -58,10 +136,12 @@ pub fn process_trap() -> ! {
         scause::Trap::Interrupt(intr) => match intr {
             scause::Interrupt::SupervisorTimer => {
                 timer::set_next_trigger();
+                timer::wake_sleepers();
                 processor::suspend_current_task_and_schedule()
             }
+            scause::Interrupt::SupervisorExternal => drivers::plic::handle_interrupt(),
             _ => {
-                unimplemented!("Interrupt handler not implemented: {:?}", intr);
+                println!("[TRAP] unexpected interrupt ignored: {:?}", intr);
             }
         },
         scause::Trap::Exception(ex) => match ex {
@@ -76,18 +156,22 @@ pub fn process_trap() -> ! {
                     trap_context,
                 );
 
-                processor::exit_current_task_and_schedule(-1)
+                processor::force_current_task_signal(SIGILL);
             }
             scause::Exception::UserEnvCall => {
+                // advance past ecall first, sigreturn replaces the whole context
+                trap_context.sepc += 4;
+
                 let ret = syscall::syscall(
                     trap_context.regs[17],
                     trap_context.regs[10],
                     trap_context.regs[11],
                     trap_context.regs[12],
+                    trap_context.regs[13],
+                    trap_context.regs[14],
+                    trap_context.regs[15],
                 );
 
-                trap_context.sepc += 4;
-
                 let trap_context = unsafe {
                     &mut *processor::get_current_task_trap_context()
                         .expect("current task trap context must exist")
@@ -102,16 +186,21 @@ pub fn process_trap() -> ! {
                     sepc::read(),
                     trap_context
                 );
-                processor::exit_current_task_and_schedule(-1)
+                processor::force_current_task_signal(SIGSEGV);
             }
             scause::Exception::StorePageFault => {
-                println!(
-                    "[TRAP] store page fault: {:#x} {:#x} {:?}",
-                    stval,
-                    sepc::read(),
-                    trap_context
-                );
-                processor::exit_current_task_and_schedule(-1)
+                if let Err(err) =
+                    processor::handle_current_task_page_fault(stval.into(), PageFault::Store)
+                {
+                    println!(
+                        "[TRAP] store page fault: {:#x} {:#x} {:?} {:?}",
+                        stval,
+                        sepc::read(),
+                        err,
+                        trap_context
+                    );
+                    processor::force_current_task_signal(SIGSEGV);
+                }
             }
             scause::Exception::InstructionFault => {
                 println!(
@@ -120,28 +209,70 @@ pub fn process_trap() -> ! {
                     sepc::read(),
                     trap_context
                 );
-                processor::exit_current_task_and_schedule(-1)
+                processor::force_current_task_signal(SIGSEGV);
             }
             scause::Exception::InstructionPageFault => {
+                if let Err(err) =
+                    processor::handle_current_task_page_fault(stval.into(), PageFault::Instruction)
+                {
+                    println!(
+                        "[TRAP] instruction page fault: {:#x} {:#x} {:?} {:?}",
+                        stval,
+                        sepc::read(),
+                        err,
+                        trap_context
+                    );
+                    processor::force_current_task_signal(SIGSEGV);
+                }
+            }
+            scause::Exception::LoadPageFault => {
+                if let Err(err) =
+                    processor::handle_current_task_page_fault(stval.into(), PageFault::Load)
+                {
+                    println!(
+                        "[TRAP] load page fault: {:#x} {:#x} {:?} {:?}",
+                        stval,
+                        sepc::read(),
+                        err,
+                        trap_context
+                    );
+                    processor::force_current_task_signal(SIGSEGV);
+                }
+            }
+            scause::Exception::Breakpoint => {
+                processor::force_current_task_signal(SIGTRAP);
+            }
+            scause::Exception::LoadFault => {
                 println!(
-                    "[TRAP] instruction page fault: {:#x} {:#x} {:?}",
+                    "[TRAP] load fault: {:#x} {:#x} {:?}",
                     stval,
                     sepc::read(),
                     trap_context
                 );
-                processor::exit_current_task_and_schedule(-1)
+                processor::force_current_task_signal(SIGSEGV);
             }
-            scause::Exception::LoadPageFault => {
+            scause::Exception::InstructionMisaligned | scause::Exception::StoreMisaligned => {
                 println!(
-                    "[TRAP] load page fault: {:#x} {:#x} {:?}",
+                    "[TRAP] misaligned access: {:?} {:#x} {:#x}",
+                    ex,
                     stval,
-                    sepc::read(),
-                    trap_context
+                    sepc::read()
                 );
-                processor::exit_current_task_and_schedule(-1)
+                processor::force_current_task_signal(SIGBUS);
+            }
+            _ if scause.bits() == SCAUSE_LOAD_MISALIGNED => {
+                println!("[TRAP] misaligned load: {:#x} {:#x}", stval, sepc::read());
+                processor::force_current_task_signal(SIGBUS);
             }
             _ => {
-                unimplemented!("Exception handler not implemented: {:?}", ex);
+                println!(
+                    "[TRAP] unexpected exception: {:?} scause: {:#x} stval: {:#x} sepc: {:#x}",
+                    ex,
+                    scause.bits(),
+                    stval,
+                    sepc::read()
+                );
+                processor::force_current_task_signal(SIGILL);
             }
         },
     }
@@ -151,13 +282,19 @@ pub fn process_trap() -> ! {
 
 #[no_mangle]
 pub fn trap_return() -> ! {
+    processor::handle_current_task_signals();
+
     set_stvec_to_user_trap();
 
     let return_va = user_trap_return_va();
 
-    let trap_context_va = mm::trap_context_va();
+    let trap_context_va = processor::get_current_task_trap_context_va();
     let trap_context_ptr: usize = trap_context_va.into();
 
+    let trap_context =
+        processor::get_current_task_trap_context().expect("current task trap context must exist");
+    unsafe { (*trap_context).kernel_tp = hart::hart_id() };
+
     let app_satp = processor::get_current_task_satp();
 
     unsafe {
@@ -172,6 +309,16 @@ pub fn trap_return() -> ! {
     }
 }
 
+/// Kernel state saved by `_kernel_trap_enter` on the interrupted stack, or on
+/// the hart's trap stack when that stack overflowed.
+#[repr(C)]
+#[derive(Debug)]
+pub struct KernelTrapContext {
+    pub regs: [usize; 32],
+    pub sstatus: usize,
+    pub sepc: usize,
+}
+
 #[repr(C)]
 #[derive(Debug, Clone)]
 pub struct TrapContext {
@@ -181,6 +328,7 @@ pub struct TrapContext {
     pub kernel_satp: usize,
     pub kernel_sp: usize,
     pub trap_handler: usize,
+    pub kernel_tp: usize,
 }
 
 impl TrapContext {
@@ -204,6 +352,7 @@ impl TrapContext {
             kernel_satp: mm::kernel_satp(),
             kernel_sp,
             trap_handler: process_trap as usize,
+            kernel_tp: hart::hart_id(),
         };
         ctx.set_user_sp(user_sp);
 
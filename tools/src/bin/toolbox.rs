@@ -1,6 +1,6 @@
 use anyhow::Context;
 use clap::{Args, Parser, Subcommand};
-use tools::user;
+use tools::{fs, kernel, user};
 
 #[derive(Parser)]
 #[command(version, about)]
@@ -13,6 +13,10 @@ struct Cli {
 enum Commands {
     #[command(subcommand)]
     User(UserCommands),
+    #[command(subcommand)]
+    Kernel(KernelCommands),
+    #[command(subcommand)]
+    Fs(FsCommands),
 }
 
 #[derive(Subcommand)]
@@ -21,6 +25,46 @@ enum UserCommands {
     Build(BuildArgs),
 }
 
+#[derive(Subcommand)]
+enum KernelCommands {
+    Symbols(SymbolsArgs),
+}
+
+#[derive(Subcommand)]
+enum FsCommands {
+    Mkfs(MkfsArgs),
+    Pack(PackArgs),
+}
+
+#[derive(Args)]
+struct MkfsArgs {
+    image_path: String,
+    #[command(flatten)]
+    image_args: ImageArgs,
+}
+
+#[derive(Args)]
+struct PackArgs {
+    dir: String,
+    image_path: String,
+    #[command(flatten)]
+    image_args: ImageArgs,
+}
+
+#[derive(Args)]
+struct ImageArgs {
+    #[arg(long, default_value_t = 32)]
+    size_mb: u32,
+    #[arg(long, default_value_t = 1)]
+    inode_bitmap_blocks: u32,
+}
+
+#[derive(Args)]
+struct SymbolsArgs {
+    kernel_elf_path: String,
+    symtab_path: String,
+}
+
 #[derive(Args)]
 struct AsmArgs {
     #[command(flatten)]
@@ -59,6 +103,31 @@ fn main() -> anyhow::Result<()> {
                     .context("user build failed")?;
             }
         },
+        Commands::Kernel(kernel_command) => match kernel_command {
+            KernelCommands::Symbols(arg) => {
+                kernel::symbols(&arg.kernel_elf_path, &arg.symtab_path)
+                    .context("kernel symbols failed")?;
+            }
+        },
+        Commands::Fs(fs_command) => match fs_command {
+            FsCommands::Mkfs(arg) => {
+                fs::mkfs(
+                    &arg.image_path,
+                    arg.image_args.size_mb,
+                    arg.image_args.inode_bitmap_blocks,
+                )
+                .context("fs mkfs failed")?;
+            }
+            FsCommands::Pack(arg) => {
+                fs::pack(
+                    &arg.dir,
+                    &arg.image_path,
+                    arg.image_args.size_mb,
+                    arg.image_args.inode_bitmap_blocks,
+                )
+                .context("fs pack failed")?;
+            }
+        },
     }
 
     Ok(())
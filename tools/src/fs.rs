@@ -0,0 +1,116 @@
+use anyhow::{anyhow, bail, Context};
+use los_fs::{
+    cache, device::BlockDevice, error::Error, fs::FileSystem, layout::InodeKind, vfs::Inode,
+    BLOCK_SIZE,
+};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+const ELF_MAGIC: &[u8] = b"\x7fELF";
+
+/// A disk image on the host.
+struct FileDevice(Mutex<File>);
+
+impl BlockDevice for FileDevice {
+    fn read_block(&self, id: usize, data: &mut [u8]) -> los_fs::error::Result<()> {
+        let mut file = self.0.lock().unwrap();
+        file.seek(SeekFrom::Start((id * BLOCK_SIZE) as u64))
+            .and_then(|_| file.read_exact(data))
+            .map_err(|e| Error::ReadBlock(format!("read block {id} failed: {e}")))
+    }
+
+    fn write_block(&self, id: usize, data: &[u8]) -> los_fs::error::Result<()> {
+        let mut file = self.0.lock().unwrap();
+        file.seek(SeekFrom::Start((id * BLOCK_SIZE) as u64))
+            .and_then(|_| file.write_all(data))
+            .map_err(|e| Error::WriteBlock(format!("write block {id} failed: {e}")))
+    }
+}
+
+fn fs_error(err: Error) -> anyhow::Error {
+    anyhow!("{err:?}")
+}
+
+/// Formats a new image and returns its root directory.
+fn create_image(image_path: &str, size_mb: u32, inode_bitmap_blocks: u32) -> anyhow::Result<Inode> {
+    let total_blocks = size_mb * (1 << 20) / BLOCK_SIZE as u32;
+
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(image_path)
+        .context("create image failed")?;
+    file.set_len(total_blocks as u64 * BLOCK_SIZE as u64)
+        .context("resize image failed")?;
+
+    let device: Arc<dyn BlockDevice> = Arc::new(FileDevice(Mutex::new(file)));
+    let fs = FileSystem::create(device, total_blocks, inode_bitmap_blocks).map_err(fs_error)?;
+
+    Ok(FileSystem::root_inode(&fs))
+}
+
+/// Writes an empty filesystem of `size_mb` MiB to `image_path`.
+pub fn mkfs(image_path: &str, size_mb: u32, inode_bitmap_blocks: u32) -> anyhow::Result<()> {
+    create_image(image_path, size_mb, inode_bitmap_blocks)?;
+    cache::sync_all().map_err(fs_error)?;
+
+    println!("mkfs {image_path}: {size_mb} MiB");
+    Ok(())
+}
+
+/// Writes a filesystem to `image_path` holding the ELF programs found in
+/// `dir`, in the root directory under their file names. Programs starting
+/// with `x_` are left out, as in `user asm`.
+pub fn pack(
+    dir: &str,
+    image_path: &str,
+    size_mb: u32,
+    inode_bitmap_blocks: u32,
+) -> anyhow::Result<()> {
+    let mut programs = Vec::new();
+    for entry in fs::read_dir(dir).with_context(|| format!("read dir {dir} failed"))? {
+        let path = entry.context("read dir entry failed")?.path();
+        let Some(name) = path.file_name().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        if !path.is_file() || name.starts_with("x_") || !is_elf(&path)? {
+            continue;
+        }
+
+        programs.push((name.to_string(), path));
+    }
+    programs.sort();
+    if programs.is_empty() {
+        bail!("no programs found in {dir}");
+    }
+
+    let root = create_image(image_path, size_mb, inode_bitmap_blocks)?;
+    for (name, path) in programs {
+        let data = fs::read(&path).with_context(|| format!("read {path:?} failed"))?;
+        let inode = root
+            .create(&name, InodeKind::File)
+            .map_err(fs_error)
+            .with_context(|| format!("create {name} failed"))?;
+        inode
+            .write_at(0, &data)
+            .map_err(fs_error)
+            .with_context(|| format!("write {name} failed"))?;
+
+        println!("pack {name}: {} bytes", data.len());
+    }
+    cache::sync_all().map_err(fs_error)?;
+
+    Ok(())
+}
+
+fn is_elf(path: &Path) -> anyhow::Result<bool> {
+    let mut magic = [0u8; 4];
+    let mut file = File::open(path).with_context(|| format!("open {path:?} failed"))?;
+    Ok(file.read_exact(&mut magic).is_ok() && magic == ELF_MAGIC)
+}
@@ -0,0 +1,55 @@
+use anyhow::Context;
+use object::{Object, ObjectSymbol, SymbolKind};
+use std::fs;
+
+/// Writes the function symbols of the kernel ELF as the table the kernel
+/// embeds to symbolize backtraces. All integers are little endian:
+///
+/// - `count: u64`
+/// - `count` entries sorted by address:
+///   `addr: u64, size: u64, name_offset: u32, name_len: u32`
+/// - the demangled names, `name_offset` is relative to the first one
+///
+/// The file is left untouched if it already holds the same table, so the
+/// kernel is not rebuilt for nothing.
+pub fn symbols(kernel_elf_path: &str, symtab_path: &str) -> anyhow::Result<()> {
+    let data = fs::read(kernel_elf_path).context("read kernel elf failed")?;
+    let file = object::File::parse(&*data).context("parse kernel elf failed")?;
+
+    let mut symbols: Vec<(u64, u64, String)> = file
+        .symbols()
+        .filter(|s| s.kind() == SymbolKind::Text && s.is_definition() && s.size() > 0)
+        .filter_map(|s| {
+            let name = s.name().ok()?;
+            Some((
+                s.address(),
+                s.size(),
+                format!("{:#}", rustc_demangle::demangle(name)),
+            ))
+        })
+        .collect();
+    symbols.sort();
+    symbols.dedup_by_key(|s| s.0);
+
+    let mut entries = Vec::new();
+    let mut names = Vec::new();
+    entries.extend_from_slice(&(symbols.len() as u64).to_le_bytes());
+    for (addr, size, name) in symbols.iter() {
+        entries.extend_from_slice(&addr.to_le_bytes());
+        entries.extend_from_slice(&size.to_le_bytes());
+        entries.extend_from_slice(&(names.len() as u32).to_le_bytes());
+        entries.extend_from_slice(&(name.len() as u32).to_le_bytes());
+        names.extend_from_slice(name.as_bytes());
+    }
+    entries.extend_from_slice(&names);
+
+    if fs::read(symtab_path).is_ok_and(|v| v == entries) {
+        println!("{} symbols unchanged", symbols.len());
+        return Ok(());
+    }
+
+    fs::write(symtab_path, entries).context("write symbol table failed")?;
+    println!("{} symbols written", symbols.len());
+
+    Ok(())
+}
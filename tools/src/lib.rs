@@ -1 +1,3 @@
+pub mod fs;
+pub mod kernel;
 pub mod user;
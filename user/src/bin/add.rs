@@ -1,13 +1,27 @@
 #![no_std]
 #![no_main]
 
-use user::{self, entry, println};
+use user::{self, args, entry, eprintln, println};
 
 entry!(main);
 
+/// Adds its two arguments, or 1 and 2 when run without any.
 fn main() -> i32 {
-    let a = 1;
-    let b = 2;
+    let mut args = args().skip(1);
+    let (a, b) = match (args.next(), args.next()) {
+        (Some(a), Some(b)) => match (a.parse(), b.parse()) {
+            (Ok(a), Ok(b)) => (a, b),
+            _ => {
+                eprintln!("add: invalid number");
+                return 1;
+            }
+        },
+        (None, _) => (1, 2),
+        _ => {
+            eprintln!("usage: add [a b]");
+            return 1;
+        }
+    };
     println!("{} + {} = {}", a, b, add(a, b));
 
     0
@@ -0,0 +1,23 @@
+#![no_std]
+#![no_main]
+
+use user::{
+    console::{STDIN, STDOUT},
+    entry, read, write,
+};
+
+entry!(main);
+
+/// Copies stdin to stdout until end of file, e.g. the end of a pipe.
+fn main() -> i32 {
+    let mut buf = [0u8; 256];
+    loop {
+        match read(STDIN, &mut buf) {
+            Ok(0) => return 0,
+            Ok(len) => {
+                write(STDOUT, &buf[..len]);
+            }
+            Err(_) => return 1,
+        }
+    }
+}
@@ -1,23 +1,36 @@
 #![no_std]
 #![no_main]
-use user::{entry, exec, fork, println, wait};
+use user::{entry, exec, fork, println, wait, Errno};
 
 entry!(main);
 
 fn main() -> i32 {
+    loop {
+        spawn_shell();
+
+        // reap the shell and every orphan handed to init until no child is left
+        loop {
+            match wait() {
+                Ok(wr) => match wr.signal {
+                    Some(sig) => println!("child {} killed by signal {}", wr.pid, sig),
+                    None => println!("child {} exited with error code: {}", wr.pid, wr.exit_code),
+                },
+                Err(err) if err.errno() == Some(Errno::EINTR) => continue,
+                Err(_) => break,
+            }
+        }
+    }
+}
+
+fn spawn_shell() {
     let shell = "lshell";
     match fork() {
         Ok(fork_proc) => match fork_proc {
             user::ForkProc::Child => {
-                exec(shell).expect("exec shell must succeed");
+                exec(shell, &[shell]).expect("exec shell must succeed");
             }
-            user::ForkProc::Parent(_pid) => loop {
-                let wr = wait().expect("wait must succeed");
-                println!("child {} exited with error code: {}", wr.pid, wr.exit_code);
-            },
+            user::ForkProc::Parent(_pid) => {}
         },
         Err(err) => panic!("fork failed: {:?}", err),
     }
-
-    0
 }
@@ -0,0 +1,50 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use user::{args, entry, eprintln, fs, println};
+
+entry!(main);
+
+/// Lists the directories given as arguments, or the working directory.
+/// Directory names end with `/`.
+fn main() -> i32 {
+    let mut dirs: Vec<&str> = args().skip(1).collect();
+    if dirs.is_empty() {
+        dirs.push(".");
+    }
+
+    let mut exit_code = 0;
+    for (i, &dir) in dirs.iter().enumerate() {
+        if dirs.len() > 1 {
+            if i > 0 {
+                println!("");
+            }
+            println!("{}:", dir);
+        }
+
+        if let Err(e) = list(dir) {
+            eprintln!("ls: {}: {}", dir, e);
+            exit_code = 1;
+        }
+    }
+
+    exit_code
+}
+
+fn list(dir: &str) -> user::Result<()> {
+    let mut entries = fs::read_dir(dir)?.collect::<user::Result<Vec<_>>>()?;
+    entries.sort_by(|a, b| a.name().cmp(b.name()));
+
+    for entry in entries {
+        if entry.is_dir() {
+            println!("{}/", entry.name());
+        } else {
+            println!("{}", entry.name());
+        }
+    }
+
+    Ok(())
+}
@@ -3,8 +3,18 @@
 
 extern crate alloc;
 
-use alloc::string::{String, ToString};
-use user::{console::Stdin, entry, exec, fork, getpid, print, println, waitpid};
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use user::{
+    close,
+    console::{Stdin, STDIN, STDOUT},
+    dup2, entry, eprintln, exec, exit, fork, fs, getpid, pipe, print, println, setpriority,
+    signal::{kill, SIGTERM},
+    try_wait, waitpid, ForkProc,
+};
 
 entry!(main);
 
@@ -13,6 +23,8 @@ const CR: u8 = 0x0d;
 const BS: u8 = 0x08;
 const DEL: u8 = 0x07f;
 
+const BACKGROUND_NICE: i32 = 10;
+
 fn main() -> i32 {
     println!("welcome to lshell");
     prompt();
@@ -25,27 +37,10 @@ fn main() -> i32 {
             CR | LF => {
                 println!("");
                 if !line.is_empty() {
-                    let program = line.trim().to_string();
+                    let command = line.trim().to_string();
                     line.clear();
 
-                    match fork().expect("fork must succeed") {
-                        user::ForkProc::Child => {
-                            if let Err(e) = exec(&program) {
-                                panic!("exec {:?} failed: {}", program, e);
-                            }
-                        }
-                        user::ForkProc::Parent(pid) => {
-                            let wr = waitpid(pid).expect("waitpid must succeed");
-                            assert_eq!(pid, wr.pid);
-
-                            if wr.exit_code != 0 {
-                                println!(
-                                    "subprocess {}({}) exited with {}",
-                                    program, pid, wr.exit_code
-                                )
-                            }
-                        }
-                    }
+                    run_command(&command);
                 }
                 prompt();
             }
@@ -65,6 +60,132 @@ fn main() -> i32 {
     }
 }
 
+/// Runs `a | b | ...`, each program reading the output of the previous one
+/// and getting the whitespace separated words of its part as arguments.
+/// A trailing `&` runs the whole pipeline in the background.
+fn run_command(command: &str) {
+    let background = command.ends_with('&');
+    let command = command.trim_end_matches('&').trim_end();
+
+    if let Some(args) = command.strip_prefix("kill ") {
+        builtin_kill(args);
+        return;
+    }
+    if command == "cd" || command.starts_with("cd ") {
+        builtin_cd(&command[2..]);
+        return;
+    }
+
+    let programs: Vec<&str> = command.split('|').map(str::trim).collect();
+    if programs.iter().any(|v| v.is_empty()) {
+        println!("invalid pipeline: {}", command);
+        return;
+    }
+
+    let mut children = Vec::new();
+    // read end of the pipe feeding the next program
+    let mut stdin_fd = None;
+    for (i, &program) in programs.iter().enumerate() {
+        let pipe_fds = if i + 1 < programs.len() {
+            Some(pipe().expect("pipe must succeed"))
+        } else {
+            None
+        };
+
+        match fork().expect("fork must succeed") {
+            ForkProc::Child => {
+                if background {
+                    setpriority(0, BACKGROUND_NICE).expect("setpriority must succeed");
+                }
+                if let Some(fd) = stdin_fd {
+                    dup2(fd, STDIN).expect("redirect stdin must succeed");
+                    close(fd).expect("close must succeed");
+                }
+                if let Some((read_fd, write_fd)) = pipe_fds {
+                    dup2(write_fd, STDOUT).expect("redirect stdout must succeed");
+                    close(read_fd).expect("close must succeed");
+                    close(write_fd).expect("close must succeed");
+                }
+                let args: Vec<&str> = program.split_whitespace().collect();
+                // programs named without a directory live in the root
+                let path = if args[0].contains('/') {
+                    args[0].to_string()
+                } else {
+                    format!("/{}", args[0])
+                };
+                if let Err(e) = exec(&path, &args) {
+                    eprintln!("lshell: {}: {}", args[0], e);
+                    exit(127);
+                }
+            }
+            ForkProc::Parent(pid) => children.push((pid, program)),
+        }
+
+        if let Some(fd) = stdin_fd.take() {
+            close(fd).expect("close must succeed");
+        }
+        if let Some((read_fd, write_fd)) = pipe_fds {
+            close(write_fd).expect("close must succeed");
+            stdin_fd = Some(read_fd);
+        }
+    }
+
+    for (pid, program) in children {
+        if background {
+            println!("[{}] {}", pid, program);
+            continue;
+        }
+
+        let wr = waitpid(pid).expect("waitpid must succeed");
+        assert_eq!(pid, wr.pid);
+
+        if let Some(sig) = wr.signal {
+            println!("subprocess {}({}) killed by signal {}", program, pid, sig)
+        } else if wr.exit_code != 0 {
+            println!(
+                "subprocess {}({}) exited with {}",
+                program, pid, wr.exit_code
+            )
+        }
+    }
+}
+
 fn prompt() {
+    reap_background_jobs();
     print!("[{}] >> ", getpid());
 }
+
+fn reap_background_jobs() {
+    while let Ok(Some(wr)) = try_wait() {
+        match wr.signal {
+            Some(sig) => println!("[{}] done, killed by signal {}", wr.pid, sig),
+            None => println!("[{}] done, exited with {}", wr.pid, wr.exit_code),
+        }
+    }
+}
+
+/// `kill <pid> [signal]`, sends SIGTERM unless a signal number is given.
+fn builtin_kill(args: &str) {
+    let mut args = args.split_whitespace();
+    let pid = args.next().and_then(|v| v.parse::<usize>().ok());
+    let sig = args
+        .next()
+        .map_or(Some(SIGTERM), |v| v.parse::<usize>().ok());
+
+    match (pid, sig) {
+        (Some(pid), Some(sig)) => {
+            if let Err(e) = kill(pid, sig) {
+                println!("kill {} failed: {}", pid, e);
+            }
+        }
+        _ => println!("usage: kill <pid> [signal]"),
+    }
+}
+
+/// `cd [dir]`, changes to the root directory without an argument.
+fn builtin_cd(args: &str) {
+    let dir = args.split_whitespace().next().unwrap_or("/");
+    if let Err(e) = fs::chdir(dir) {
+        println!("cd: {}: {}", dir, e);
+    }
+}
@@ -0,0 +1,48 @@
+#![no_std]
+#![no_main]
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use user::{
+    entry, fork, getpid, println,
+    signal::{kill, signal, SIGKILL, SIGSEGV, SIGUSR1},
+    sleep_ms, waitpid, ForkProc,
+};
+
+entry!(main);
+
+static RECEIVED: AtomicUsize = AtomicUsize::new(0);
+
+extern "C" fn on_signal(sig: usize) {
+    RECEIVED.store(sig, Ordering::SeqCst);
+}
+
+fn main() -> i32 {
+    signal(SIGUSR1, on_signal).expect("install handler must succeed");
+    kill(getpid(), SIGUSR1).expect("kill self must succeed");
+    assert_eq!(RECEIVED.load(Ordering::SeqCst), SIGUSR1);
+    println!("handled SIGUSR1");
+
+    let pid = match fork().expect("fork must succeed") {
+        ForkProc::Child => loop {
+            sleep_ms(10).ok();
+        },
+        ForkProc::Parent(pid) => pid,
+    };
+    kill(pid, SIGKILL).expect("kill child must succeed");
+    let wr = waitpid(pid).expect("waitpid must succeed");
+    assert_eq!(wr.signal, Some(SIGKILL));
+    println!("child {} killed by SIGKILL", pid);
+
+    let pid = match fork().expect("fork must succeed") {
+        ForkProc::Child => {
+            unsafe { (0x10 as *mut u8).write_volatile(0) };
+            unreachable!()
+        }
+        ForkProc::Parent(pid) => pid,
+    };
+    let wr = waitpid(pid).expect("waitpid must succeed");
+    assert_eq!(wr.signal, Some(SIGSEGV));
+    println!("child {} killed by SIGSEGV", pid);
+
+    0
+}
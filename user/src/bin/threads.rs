@@ -0,0 +1,64 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use user::{
+    self, entry, exit, fork, getpid, gettid, println, sched_yield, sleep_ms, thread, waitpid,
+    ForkProc,
+};
+
+entry!(main);
+
+const THREAD_COUNT: usize = 4;
+
+static SPINS: AtomicUsize = AtomicUsize::new(0);
+
+fn main() -> i32 {
+    println!("main thread: pid = {}, tid = {}", getpid(), gettid());
+
+    let handles: Vec<_> = (0..THREAD_COUNT)
+        .map(|i| {
+            thread::spawn(move || {
+                println!("thread {}: pid = {}, tid = {}", i, getpid(), gettid());
+                (1..=100).map(|v| v * (i + 1)).sum::<usize>()
+            })
+            .expect("spawn thread must succeed")
+        })
+        .collect();
+
+    for handle in handles {
+        let tid = handle.tid();
+        let sum = handle.join().expect("join thread must succeed");
+        println!("thread {} joined: sum = {}", tid, sum);
+    }
+
+    // the leader exits while its threads still run, which takes them down
+    // with the process
+    let pid = match fork().expect("fork must succeed") {
+        ForkProc::Child => {
+            thread::spawn(|| loop {
+                SPINS.fetch_add(1, Ordering::SeqCst);
+            })
+            .expect("spawn thread must succeed");
+            thread::spawn(|| loop {
+                sleep_ms(10).ok();
+            })
+            .expect("spawn thread must succeed");
+
+            while SPINS.load(Ordering::SeqCst) == 0 {
+                sched_yield();
+            }
+            exit(7)
+        }
+        ForkProc::Parent(pid) => pid,
+    };
+    let wr = waitpid(pid).expect("waitpid must succeed");
+    assert_eq!(wr.exit_code, 7);
+    assert_eq!(wr.signal, None);
+    println!("leader {} exited with its threads running", pid);
+
+    0
+}
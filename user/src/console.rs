@@ -1,9 +1,10 @@
 use core::fmt::Write;
 
-use crate::{read, sched_yield, write};
+use crate::{error::Error, read, write};
 
 pub const STDOUT: usize = 1;
 pub const STDIN: usize = 0;
+pub const STDERR: usize = 2;
 
 pub struct Stdout;
 
@@ -14,10 +15,23 @@ impl Write for Stdout {
     }
 }
 
+pub struct Stderr;
+
+impl Write for Stderr {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        write(STDERR, s.as_bytes());
+        Ok(())
+    }
+}
+
 pub fn print(args: core::fmt::Arguments) {
     Stdout.write_fmt(args).unwrap();
 }
 
+pub fn eprint(args: core::fmt::Arguments) {
+    Stderr.write_fmt(args).unwrap();
+}
+
 #[macro_export]
 macro_rules! print {
 	($fmt: literal $(, $($arg: tt)+)?) => {
@@ -32,6 +46,20 @@ macro_rules! println {
 	}
 }
 
+#[macro_export]
+macro_rules! eprint {
+	($fmt: literal $(, $($arg: tt)+)?) => {
+		$crate::console::eprint(format_args!($fmt $(, $($arg)+)?));
+	}
+}
+
+#[macro_export]
+macro_rules! eprintln {
+	($fmt: literal $(, $($arg: tt)+)?) => {
+		$crate::console::eprint(format_args!(concat!($fmt, "\n") $(, $($arg)+)?));
+	}
+}
+
 pub struct Stdin;
 
 impl Stdin {
@@ -42,15 +70,8 @@ impl Stdin {
     pub fn read_u8() -> crate::error::Result<u8> {
         let mut buf = [0u8; 1];
 
-        loop {
-            let len = Self::read(&mut buf)?;
-            if len == 0 {
-                sched_yield();
-            }
-
-            if len > 0 {
-                break;
-            }
+        if Self::read(&mut buf)? == 0 {
+            return Err(Error::UnexpectedEof);
         }
 
         Ok(buf[0])
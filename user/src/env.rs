@@ -0,0 +1,65 @@
+use core::{
+    ffi::{c_char, CStr},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Initial stack pointer, which points at argc, argv and envp.
+static START_SP: AtomicUsize = AtomicUsize::new(0);
+
+pub(crate) fn init(sp: usize) {
+    START_SP.store(sp, Ordering::Relaxed);
+}
+
+fn argv_ptr() -> *const usize {
+    let sp = START_SP.load(Ordering::Relaxed) as *const usize;
+    unsafe { sp.add(1) }
+}
+
+fn envp_ptr() -> *const usize {
+    let sp = START_SP.load(Ordering::Relaxed) as *const usize;
+    let argc = unsafe { *sp };
+    unsafe { argv_ptr().add(argc + 1) }
+}
+
+/// Iterator over a NULL-terminated array of C strings set up by exec.
+/// Strings that are not valid UTF-8 are skipped.
+#[derive(Debug, Clone)]
+pub struct StrArray {
+    ptr: *const usize,
+}
+
+impl Iterator for StrArray {
+    type Item = &'static str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let str_ptr = unsafe { *self.ptr };
+            if str_ptr == 0 {
+                return None;
+            }
+            self.ptr = unsafe { self.ptr.add(1) };
+
+            let cstr = unsafe { CStr::from_ptr(str_ptr as *const c_char) };
+            if let Ok(s) = cstr.to_str() {
+                return Some(s);
+            }
+        }
+    }
+}
+
+/// Arguments the program was started with, the program name first.
+pub fn args() -> StrArray {
+    StrArray { ptr: argv_ptr() }
+}
+
+/// Environment of the program as `KEY=VALUE` strings.
+pub fn env() -> StrArray {
+    StrArray { ptr: envp_ptr() }
+}
+
+pub fn getenv(key: &str) -> Option<&'static str> {
+    env().find_map(|var| {
+        var.strip_prefix(key)
+            .and_then(|rest| rest.strip_prefix('='))
+    })
+}
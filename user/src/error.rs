@@ -1,16 +1,169 @@
+/// Linux errno values, decoded from the negated value a failing syscall
+/// returns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(clippy::upper_case_acronyms)]
+pub enum Errno {
+    ENOENT,
+    ESRCH,
+    EINTR,
+    EIO,
+    E2BIG,
+    ENOEXEC,
+    EBADF,
+    ECHILD,
+    EAGAIN,
+    ENOMEM,
+    EFAULT,
+    EBUSY,
+    EEXIST,
+    ENOTDIR,
+    EISDIR,
+    EINVAL,
+    EMFILE,
+    EFBIG,
+    ENOSPC,
+    ESPIPE,
+    EPIPE,
+    ERANGE,
+    ENAMETOOLONG,
+    ENOSYS,
+    ENOTEMPTY,
+    Unknown(isize),
+}
+
+impl Errno {
+    pub fn from_code(code: isize) -> Self {
+        match code {
+            2 => Errno::ENOENT,
+            3 => Errno::ESRCH,
+            4 => Errno::EINTR,
+            5 => Errno::EIO,
+            7 => Errno::E2BIG,
+            8 => Errno::ENOEXEC,
+            9 => Errno::EBADF,
+            10 => Errno::ECHILD,
+            11 => Errno::EAGAIN,
+            12 => Errno::ENOMEM,
+            14 => Errno::EFAULT,
+            16 => Errno::EBUSY,
+            17 => Errno::EEXIST,
+            20 => Errno::ENOTDIR,
+            21 => Errno::EISDIR,
+            22 => Errno::EINVAL,
+            24 => Errno::EMFILE,
+            27 => Errno::EFBIG,
+            28 => Errno::ENOSPC,
+            29 => Errno::ESPIPE,
+            32 => Errno::EPIPE,
+            34 => Errno::ERANGE,
+            36 => Errno::ENAMETOOLONG,
+            38 => Errno::ENOSYS,
+            39 => Errno::ENOTEMPTY,
+            code => Errno::Unknown(code),
+        }
+    }
+
+    pub fn code(&self) -> isize {
+        match self {
+            Errno::ENOENT => 2,
+            Errno::ESRCH => 3,
+            Errno::EINTR => 4,
+            Errno::EIO => 5,
+            Errno::E2BIG => 7,
+            Errno::ENOEXEC => 8,
+            Errno::EBADF => 9,
+            Errno::ECHILD => 10,
+            Errno::EAGAIN => 11,
+            Errno::ENOMEM => 12,
+            Errno::EFAULT => 14,
+            Errno::EBUSY => 16,
+            Errno::EEXIST => 17,
+            Errno::ENOTDIR => 20,
+            Errno::EISDIR => 21,
+            Errno::EINVAL => 22,
+            Errno::EMFILE => 24,
+            Errno::EFBIG => 27,
+            Errno::ENOSPC => 28,
+            Errno::ESPIPE => 29,
+            Errno::EPIPE => 32,
+            Errno::ERANGE => 34,
+            Errno::ENAMETOOLONG => 36,
+            Errno::ENOSYS => 38,
+            Errno::ENOTEMPTY => 39,
+            Errno::Unknown(code) => *code,
+        }
+    }
+}
+
+impl core::fmt::Display for Errno {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let msg = match self {
+            Errno::ENOENT => "No such file or directory",
+            Errno::ESRCH => "No such process",
+            Errno::EINTR => "Interrupted system call",
+            Errno::EIO => "Input/output error",
+            Errno::E2BIG => "Argument list too long",
+            Errno::ENOEXEC => "Exec format error",
+            Errno::EBADF => "Bad file descriptor",
+            Errno::ECHILD => "No child processes",
+            Errno::EAGAIN => "Resource temporarily unavailable",
+            Errno::ENOMEM => "Cannot allocate memory",
+            Errno::EFAULT => "Bad address",
+            Errno::EBUSY => "Device or resource busy",
+            Errno::EEXIST => "File exists",
+            Errno::ENOTDIR => "Not a directory",
+            Errno::EISDIR => "Is a directory",
+            Errno::EINVAL => "Invalid argument",
+            Errno::EMFILE => "Too many open files",
+            Errno::EFBIG => "File too large",
+            Errno::ENOSPC => "No space left on device",
+            Errno::ESPIPE => "Illegal seek",
+            Errno::EPIPE => "Broken pipe",
+            Errno::ERANGE => "Numerical result out of range",
+            Errno::ENAMETOOLONG => "File name too long",
+            Errno::ENOSYS => "Function not implemented",
+            Errno::ENOTEMPTY => "Directory not empty",
+            Errno::Unknown(code) => return write!(f, "Unknown error {code}"),
+        };
+
+        write!(f, "{msg}")
+    }
+}
+
 #[derive(Debug)]
 pub enum Error {
-    Syscall(isize),
+    Syscall(Errno),
     CastToCStr,
     PathTooLong,
     UnexpectedEof,
+    ThreadPanicked,
+}
+
+impl Error {
+    /// Builds the error for a negative syscall return value.
+    pub(crate) fn from_syscall(ret: isize) -> Self {
+        Error::Syscall(Errno::from_code(-ret))
+    }
+
+    pub fn errno(&self) -> Option<Errno> {
+        match self {
+            Error::Syscall(errno) => Some(*errno),
+            _ => None,
+        }
+    }
 }
 
 // impl core::error::Error for Error {}
 
 impl core::fmt::Display for Error {
     fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-        write!(f, "Error: {self:?}")
+        match self {
+            Error::Syscall(errno) => write!(f, "{errno}"),
+            Error::CastToCStr => write!(f, "invalid C string"),
+            Error::PathTooLong => write!(f, "path too long"),
+            Error::UnexpectedEof => write!(f, "unexpected end of file"),
+            Error::ThreadPanicked => write!(f, "thread panicked"),
+        }
     }
 }
 
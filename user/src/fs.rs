@@ -0,0 +1,438 @@
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::fmt::{self, Debug, Display};
+
+use crate::{
+    close,
+    error::{Error, Result},
+    fstat, lseek, read, syscall, to_c_path, Stat, MAX_PATH_LEN, SEEK_CUR, SEEK_END, SEEK_SET,
+};
+
+pub const O_RDONLY: usize = 0;
+pub const O_WRONLY: usize = 1 << 0;
+pub const O_RDWR: usize = 1 << 1;
+pub const O_CREAT: usize = 0o100;
+pub const O_EXCL: usize = 0o200;
+pub const O_TRUNC: usize = 0o1000;
+pub const O_APPEND: usize = 0o2000;
+pub const O_DIRECTORY: usize = 0o200000;
+
+const AT_FDCWD: isize = -100;
+const AT_REMOVEDIR: usize = 0x200;
+
+const DT_DIR: u8 = 4;
+const DT_REG: u8 = 8;
+const DIRENT64_NAME_OFFSET: usize = 19;
+const READ_DIR_BUF_SIZE: usize = 512;
+
+/// A borrowed `/` separated path, like `str` but with path helpers.
+#[repr(transparent)]
+pub struct Path {
+    inner: str,
+}
+
+impl Path {
+    pub fn new<S: AsRef<str> + ?Sized>(s: &S) -> &Path {
+        // Path is a transparent wrapper around str
+        unsafe { &*(s.as_ref() as *const str as *const Path) }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.inner
+    }
+
+    pub fn is_absolute(&self) -> bool {
+        self.inner.starts_with('/')
+    }
+
+    /// The names in the path, without empty ones and `.`.
+    pub fn components(&self) -> impl Iterator<Item = &str> {
+        self.inner
+            .split('/')
+            .filter(|&name| !name.is_empty() && name != ".")
+    }
+
+    /// The path without its last name, `None` for the root or an empty
+    /// path. The parent of a single relative name is the empty path.
+    pub fn parent(&self) -> Option<&Path> {
+        let path = self.inner.trim_end_matches('/');
+        if path.is_empty() {
+            return None;
+        }
+
+        match path.rsplit_once('/') {
+            Some(("", _)) => Some(Path::new("/")),
+            Some((parent, _)) => Some(Path::new(parent)),
+            None => Some(Path::new("")),
+        }
+    }
+
+    /// The last name, unless it is `.` or `..`.
+    pub fn file_name(&self) -> Option<&str> {
+        match self.inner.trim_end_matches('/').rsplit('/').next() {
+            None | Some("") | Some(".") | Some("..") => None,
+            name => name,
+        }
+    }
+
+    /// Appends `path`, which replaces this path if it is absolute.
+    pub fn join<P: AsRef<Path> + ?Sized>(&self, path: &P) -> String {
+        let path = path.as_ref();
+        if path.is_absolute() || self.inner.is_empty() {
+            return path.inner.to_string();
+        }
+
+        let mut joined = self.inner.trim_end_matches('/').to_string();
+        joined.push('/');
+        joined.push_str(&path.inner);
+        joined
+    }
+}
+
+impl AsRef<Path> for Path {
+    fn as_ref(&self) -> &Path {
+        self
+    }
+}
+
+impl AsRef<Path> for str {
+    fn as_ref(&self) -> &Path {
+        Path::new(self)
+    }
+}
+
+impl AsRef<Path> for String {
+    fn as_ref(&self) -> &Path {
+        Path::new(self)
+    }
+}
+
+impl Debug for Path {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&self.inner, f)
+    }
+}
+
+impl Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.inner, f)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum SeekFrom {
+    Start(usize),
+    End(isize),
+    Current(isize),
+}
+
+/// Flags for opening a file, in the style of `std::fs::OpenOptions`.
+#[derive(Debug, Clone, Default)]
+pub struct OpenOptions {
+    read: bool,
+    write: bool,
+    append: bool,
+    truncate: bool,
+    create: bool,
+    create_new: bool,
+}
+
+impl OpenOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn read(&mut self, read: bool) -> &mut Self {
+        self.read = read;
+        self
+    }
+
+    pub fn write(&mut self, write: bool) -> &mut Self {
+        self.write = write;
+        self
+    }
+
+    /// Every write goes to the end of the file. Implies `write`.
+    pub fn append(&mut self, append: bool) -> &mut Self {
+        self.append = append;
+        self
+    }
+
+    pub fn truncate(&mut self, truncate: bool) -> &mut Self {
+        self.truncate = truncate;
+        self
+    }
+
+    pub fn create(&mut self, create: bool) -> &mut Self {
+        self.create = create;
+        self
+    }
+
+    /// Creates the file and fails if it already exists.
+    pub fn create_new(&mut self, create_new: bool) -> &mut Self {
+        self.create_new = create_new;
+        self
+    }
+
+    fn flags(&self) -> usize {
+        let mut flags = match (self.read, self.write || self.append) {
+            (true, true) => O_RDWR,
+            (false, true) => O_WRONLY,
+            _ => O_RDONLY,
+        };
+        if self.append {
+            flags |= O_APPEND;
+        }
+        if self.truncate {
+            flags |= O_TRUNC;
+        }
+        if self.create {
+            flags |= O_CREAT;
+        }
+        if self.create_new {
+            flags |= O_CREAT | O_EXCL;
+        }
+
+        flags
+    }
+
+    pub fn open<P: AsRef<Path> + ?Sized>(&self, path: &P) -> Result<File> {
+        open(path.as_ref().as_str(), self.flags())
+    }
+}
+
+fn open(path: &str, flags: usize) -> Result<File> {
+    let path = to_c_path(path)?;
+
+    let ret = syscall::sys_openat(AT_FDCWD, &path, flags, 0o644);
+    if ret < 0 {
+        return Err(Error::from_syscall(ret));
+    }
+
+    Ok(File { fd: ret as usize })
+}
+
+/// An open file, closed when dropped.
+#[derive(Debug)]
+pub struct File {
+    fd: usize,
+}
+
+impl File {
+    /// Opens `path` for reading.
+    pub fn open<P: AsRef<Path> + ?Sized>(path: &P) -> Result<File> {
+        OpenOptions::new().read(true).open(path)
+    }
+
+    /// Opens `path` for writing, creating it or truncating what it holds.
+    pub fn create<P: AsRef<Path> + ?Sized>(path: &P) -> Result<File> {
+        OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)
+    }
+
+    pub fn fd(&self) -> usize {
+        self.fd
+    }
+
+    pub fn read(&self, buf: &mut [u8]) -> Result<usize> {
+        read(self.fd, buf)
+    }
+
+    /// Reads until the end of the file, appending to `buf`.
+    pub fn read_to_end(&self, buf: &mut Vec<u8>) -> Result<usize> {
+        let mut chunk = [0u8; 256];
+        let mut total = 0;
+        loop {
+            match self.read(&mut chunk)? {
+                0 => return Ok(total),
+                len => {
+                    buf.extend_from_slice(&chunk[..len]);
+                    total += len;
+                }
+            }
+        }
+    }
+
+    pub fn write(&self, buf: &[u8]) -> Result<usize> {
+        let ret = syscall::sys_write(self.fd, buf);
+        if ret < 0 {
+            return Err(Error::from_syscall(ret));
+        }
+
+        Ok(ret as usize)
+    }
+
+    /// Moves the offset and returns it, counted from the start.
+    pub fn seek(&self, pos: SeekFrom) -> Result<usize> {
+        match pos {
+            SeekFrom::Start(offset) => lseek(self.fd, offset as isize, SEEK_SET),
+            SeekFrom::End(offset) => lseek(self.fd, offset, SEEK_END),
+            SeekFrom::Current(offset) => lseek(self.fd, offset, SEEK_CUR),
+        }
+    }
+
+    pub fn metadata(&self) -> Result<Stat> {
+        fstat(self.fd)
+    }
+}
+
+impl fmt::Write for File {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut bytes = s.as_bytes();
+        while !bytes.is_empty() {
+            match File::write(self, bytes) {
+                Ok(0) | Err(_) => return Err(fmt::Error),
+                Ok(len) => bytes = &bytes[len..],
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for File {
+    fn drop(&mut self) {
+        close(self.fd).ok();
+    }
+}
+
+/// An entry of a directory read by `read_dir`.
+#[derive(Debug, Clone)]
+pub struct DirEntry {
+    ino: u64,
+    d_type: u8,
+    name: String,
+}
+
+impl DirEntry {
+    pub fn ino(&self) -> u64 {
+        self.ino
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.d_type == DT_DIR
+    }
+
+    pub fn is_file(&self) -> bool {
+        self.d_type == DT_REG
+    }
+}
+
+/// Iterator over the entries of a directory.
+#[derive(Debug)]
+pub struct ReadDir {
+    dir: File,
+    buf: Vec<u8>,
+    pos: usize,
+    len: usize,
+}
+
+impl Iterator for ReadDir {
+    type Item = Result<DirEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos == self.len {
+            let ret = syscall::sys_getdents64(self.dir.fd, &mut self.buf);
+            if ret < 0 {
+                return Some(Err(Error::from_syscall(ret)));
+            }
+            if ret == 0 {
+                return None;
+            }
+
+            self.pos = 0;
+            self.len = ret as usize;
+        }
+
+        // linux_dirent64: d_ino u64, d_off i64, d_reclen u16, d_type u8 and
+        // the NUL-terminated name, padded to 8 bytes
+        let record = &self.buf[self.pos..self.len];
+        let ino = u64::from_le_bytes(record[0..8].try_into().unwrap());
+        let reclen = u16::from_le_bytes(record[16..18].try_into().unwrap()) as usize;
+        let d_type = record[18];
+        let name = &record[DIRENT64_NAME_OFFSET..reclen];
+        let name_len = name.iter().position(|&c| c == 0).unwrap_or(name.len());
+        self.pos += reclen;
+
+        Some(Ok(DirEntry {
+            ino,
+            d_type,
+            name: String::from_utf8_lossy(&name[..name_len]).into_owned(),
+        }))
+    }
+}
+
+pub fn read_dir<P: AsRef<Path> + ?Sized>(path: &P) -> Result<ReadDir> {
+    Ok(ReadDir {
+        dir: open(path.as_ref().as_str(), O_RDONLY | O_DIRECTORY)?,
+        buf: vec![0; READ_DIR_BUF_SIZE],
+        pos: 0,
+        len: 0,
+    })
+}
+
+pub fn create_dir<P: AsRef<Path> + ?Sized>(path: &P) -> Result<()> {
+    let path = to_c_path(path.as_ref().as_str())?;
+
+    let ret = syscall::sys_mkdirat(AT_FDCWD, &path, 0o755);
+    if ret < 0 {
+        return Err(Error::from_syscall(ret));
+    }
+
+    Ok(())
+}
+
+fn unlink(path: &str, flags: usize) -> Result<()> {
+    let path = to_c_path(path)?;
+
+    let ret = syscall::sys_unlinkat(AT_FDCWD, &path, flags);
+    if ret < 0 {
+        return Err(Error::from_syscall(ret));
+    }
+
+    Ok(())
+}
+
+/// Removes a file, which must not be open anywhere.
+pub fn remove_file<P: AsRef<Path> + ?Sized>(path: &P) -> Result<()> {
+    unlink(path.as_ref().as_str(), 0)
+}
+
+/// Removes an empty directory.
+pub fn remove_dir<P: AsRef<Path> + ?Sized>(path: &P) -> Result<()> {
+    unlink(path.as_ref().as_str(), AT_REMOVEDIR)
+}
+
+pub fn chdir<P: AsRef<Path> + ?Sized>(path: &P) -> Result<()> {
+    let path = to_c_path(path.as_ref().as_str())?;
+
+    let ret = syscall::sys_chdir(&path);
+    if ret < 0 {
+        return Err(Error::from_syscall(ret));
+    }
+
+    Ok(())
+}
+
+pub fn getcwd() -> Result<String> {
+    let mut buf = [0u8; MAX_PATH_LEN];
+
+    let ret = syscall::sys_getcwd(&mut buf);
+    if ret < 0 {
+        return Err(Error::from_syscall(ret));
+    }
+
+    // the returned length counts the NUL
+    let len = ret as usize - 1;
+    Ok(String::from_utf8_lossy(&buf[..len]).into_owned())
+}
@@ -1,22 +1,34 @@
-use buddy_system_allocator::LockedHeap;
+use buddy_system_allocator::{Heap, LockedHeapWithRescue};
+use core::alloc::Layout;
 
-const HEAP_SIZE: usize = 1 << 16;
+use crate::sbrk;
+
+const HEAP_GROW_SIZE: usize = 1 << 16;
 
 #[global_allocator]
-static HEAP_ALLOCATOR: LockedHeap<32> = LockedHeap::empty();
+static HEAP_ALLOCATOR: LockedHeapWithRescue<32> = LockedHeapWithRescue::new(grow_heap);
 
 #[alloc_error_handler]
 pub fn handle_alloc_error(layout: core::alloc::Layout) -> ! {
     panic!("Heap allocation error, layot = {:?}", layout);
 }
 
-static mut HEAP_SPACE: [u8; HEAP_SIZE] = [0; HEAP_SIZE];
+fn grow_heap(heap: &mut Heap<32>, layout: &Layout) {
+    // the buddy allocator needs an aligned block, twice the size always contains one
+    let size = layout.size().max(layout.align()).next_power_of_two() * 2;
+    let size = size.max(HEAP_GROW_SIZE);
+
+    if let Ok(start) = sbrk(size as isize) {
+        unsafe { heap.add_to_heap(start, start + size) };
+    }
+}
 
 pub fn init() {
-    unsafe {
-        #[allow(static_mut_refs)]
-        HEAP_ALLOCATOR
-            .lock()
-            .init(HEAP_SPACE.as_ptr() as usize, HEAP_SPACE.len())
-    };
+    if let Ok(start) = sbrk(HEAP_GROW_SIZE as isize) {
+        unsafe {
+            HEAP_ALLOCATOR
+                .lock()
+                .add_to_heap(start, start + HEAP_GROW_SIZE)
+        };
+    }
 }
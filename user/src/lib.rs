@@ -2,26 +2,47 @@
 #![no_main]
 #![feature(alloc_error_handler)]
 
+extern crate alloc;
+
 pub mod console;
+mod env;
 mod error;
+pub mod fs;
 mod heap;
+pub mod signal;
 mod syscall;
+pub mod thread;
 
-use core::{ffi::CStr, panic::PanicInfo};
-use error::{Error, Result};
+use alloc::{ffi::CString, vec::Vec};
+use core::panic::PanicInfo;
+pub use env::{args, env, getenv, StrArray};
+pub use error::{Errno, Error, Result};
 use syscall::sys_getpid;
 
 const MAX_PATH_LEN: usize = 128;
 
+pub const PROT_READ: usize = 1 << 0;
+pub const PROT_WRITE: usize = 1 << 1;
+pub const PROT_EXEC: usize = 1 << 2;
+
+pub const MAP_PRIVATE: usize = 1 << 1;
+pub const MAP_FIXED: usize = 1 << 4;
+pub const MAP_ANONYMOUS: usize = 1 << 5;
+
+pub const WNOHANG: usize = 1;
+
+const PRIO_PROCESS: usize = 0;
+
 #[no_mangle]
 #[link_section = ".text.entry"]
-pub extern "C" fn _start() -> ! {
+pub extern "C" fn _start(sp: usize) -> ! {
     extern "C" {
         fn __main() -> i32;
     }
 
     clear_bss();
     heap::init();
+    env::init(sp);
     exit(unsafe { __main() });
 }
 
@@ -38,7 +59,7 @@ fn clear_bss() {
 
 #[panic_handler]
 fn panic_handler(panic_info: &PanicInfo) -> ! {
-    println!("APP PANIC: {}", panic_info);
+    eprintln!("APP PANIC: {}", panic_info);
 
     exit(1)
 }
@@ -59,7 +80,7 @@ pub fn read(fd: usize, buf: &mut [u8]) -> Result<usize> {
     let ret = syscall::sys_read(fd, buf);
 
     if ret < 0 {
-        Err(Error::Syscall(ret))
+        Err(Error::from_syscall(ret))
     } else {
         Ok(ret as usize)
     }
@@ -69,6 +90,108 @@ pub fn write(fd: usize, buf: &[u8]) -> isize {
     syscall::sys_write(fd, buf)
 }
 
+pub fn close(fd: usize) -> Result<()> {
+    let ret = syscall::sys_close(fd);
+    if ret < 0 {
+        return Err(Error::from_syscall(ret));
+    }
+
+    Ok(())
+}
+
+pub fn dup(fd: usize) -> Result<usize> {
+    let ret = syscall::sys_dup(fd);
+    if ret < 0 {
+        return Err(Error::from_syscall(ret));
+    }
+
+    Ok(ret as usize)
+}
+
+/// Makes `new_fd` refer to the file open at `old_fd`, closing `new_fd`
+/// first if needed.
+pub fn dup2(old_fd: usize, new_fd: usize) -> Result<usize> {
+    if old_fd == new_fd {
+        fstat(old_fd)?;
+        return Ok(new_fd);
+    }
+
+    let ret = syscall::sys_dup3(old_fd, new_fd, 0);
+    if ret < 0 {
+        return Err(Error::from_syscall(ret));
+    }
+
+    Ok(ret as usize)
+}
+
+/// Creates a pipe and returns its read end and write end.
+pub fn pipe() -> Result<(usize, usize)> {
+    let mut fds = [0i32; 2];
+
+    let ret = syscall::sys_pipe2(&mut fds, 0);
+    if ret < 0 {
+        return Err(Error::from_syscall(ret));
+    }
+
+    Ok((fds[0] as usize, fds[1] as usize))
+}
+
+pub const S_IFMT: u32 = 0o170000;
+pub const S_IFIFO: u32 = 0o010000;
+pub const S_IFCHR: u32 = 0o020000;
+pub const S_IFDIR: u32 = 0o040000;
+pub const S_IFREG: u32 = 0o100000;
+
+/// `struct stat` of riscv64 Linux. There are no owners or timestamps, those
+/// fields stay 0.
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+pub struct Stat {
+    pub dev: u64,
+    pub ino: u64,
+    pub mode: u32,
+    pub nlink: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub rdev: u64,
+    _pad0: u64,
+    pub size: i64,
+    pub blksize: i32,
+    _pad1: i32,
+    pub blocks: i64,
+    pub atime_sec: i64,
+    pub atime_nsec: u64,
+    pub mtime_sec: i64,
+    pub mtime_nsec: u64,
+    pub ctime_sec: i64,
+    pub ctime_nsec: u64,
+    _unused: [u32; 2],
+}
+
+pub fn fstat(fd: usize) -> Result<Stat> {
+    let mut stat = Stat::default();
+
+    let ret = syscall::sys_fstat(fd, &mut stat);
+    if ret < 0 {
+        return Err(Error::from_syscall(ret));
+    }
+
+    Ok(stat)
+}
+
+pub const SEEK_SET: usize = 0;
+pub const SEEK_CUR: usize = 1;
+pub const SEEK_END: usize = 2;
+
+pub fn lseek(fd: usize, offset: isize, whence: usize) -> Result<usize> {
+    let ret = syscall::sys_lseek(fd, offset, whence);
+    if ret < 0 {
+        return Err(Error::from_syscall(ret));
+    }
+
+    Ok(ret as usize)
+}
+
 pub fn exit(exit_code: i32) -> ! {
     syscall::sys_exit(exit_code as usize)
 }
@@ -84,12 +207,35 @@ pub struct TimeVal {
     pub usec: u64,
 }
 
+#[derive(Debug, Clone, Copy)]
+#[repr(C)]
+pub struct TimeSpec {
+    pub sec: u64,
+    pub nsec: u64,
+}
+
+pub fn nanosleep(req: &TimeSpec) -> Result<()> {
+    let ret = syscall::sys_nanosleep(req, core::ptr::null_mut());
+    if ret != 0 {
+        return Err(Error::from_syscall(ret));
+    }
+
+    Ok(())
+}
+
+pub fn sleep_ms(ms: u64) -> Result<()> {
+    nanosleep(&TimeSpec {
+        sec: ms / 1000,
+        nsec: ms % 1000 * 1_000_000,
+    })
+}
+
 pub fn gettimeofday() -> Result<TimeVal> {
     let mut t = TimeVal { sec: 0, usec: 0 };
 
     let ret = syscall::sys_gettimeofday(&mut t, 0);
     if ret != 0 {
-        return Err(Error::Syscall(ret));
+        return Err(Error::from_syscall(ret));
     }
 
     Ok(t)
@@ -103,7 +249,7 @@ pub enum ForkProc {
 pub fn fork() -> Result<ForkProc> {
     let ret = syscall::sys_fork();
     if ret < 0 {
-        return Err(Error::Syscall(ret));
+        return Err(Error::from_syscall(ret));
     }
 
     if ret == 0 {
@@ -113,21 +259,51 @@ pub fn fork() -> Result<ForkProc> {
     }
 }
 
-pub fn exec(path: &str) -> Result<()> {
+/// Runs `path` with `args`, keeping the current environment. By convention
+/// `args` starts with the program name.
+pub fn exec(path: &str, args: &[&str]) -> Result<()> {
+    let envp: Vec<&str> = env().collect();
+    execve(path, args, &envp)
+}
+
+/// NUL-terminated copies of `strings` and the NULL-terminated pointer array
+/// referring to them. The copies have to outlive the pointers.
+fn to_c_str_array(strings: &[&str]) -> Result<(Vec<Vec<u8>>, Vec<usize>)> {
+    let mut c_strings = Vec::with_capacity(strings.len());
+    for s in strings {
+        if s.contains('\0') {
+            return Err(Error::CastToCStr);
+        }
+
+        let mut c_string = Vec::with_capacity(s.len() + 1);
+        c_string.extend_from_slice(s.as_bytes());
+        c_string.push(0);
+        c_strings.push(c_string);
+    }
+
+    let mut ptrs: Vec<usize> = c_strings.iter().map(|v| v.as_ptr() as usize).collect();
+    ptrs.push(0);
+
+    Ok((c_strings, ptrs))
+}
+
+/// NUL-terminated copy of `path` for a syscall.
+fn to_c_path(path: &str) -> Result<CString> {
     if path.len() + 1 > MAX_PATH_LEN {
         return Err(Error::PathTooLong);
     }
 
-    let bytes = path.as_bytes();
-    let mut buf = [0u8; MAX_PATH_LEN];
-    buf.fill(0);
-    buf[..bytes.len()].copy_from_slice(bytes);
+    CString::new(path).map_err(|_| Error::CastToCStr)
+}
 
-    let cstr = CStr::from_bytes_until_nul(&buf).map_err(|_| Error::CastToCStr)?;
+pub fn execve(path: &str, args: &[&str], envp: &[&str]) -> Result<()> {
+    let path = to_c_path(path)?;
+    let (_args, argv_ptrs) = to_c_str_array(args)?;
+    let (_envp, envp_ptrs) = to_c_str_array(envp)?;
 
-    let ret = syscall::sys_exec(cstr);
+    let ret = syscall::sys_exec(&path, &argv_ptrs, &envp_ptrs);
     if ret < 0 {
-        return Err(Error::Syscall(ret));
+        return Err(Error::from_syscall(ret));
     }
 
     Ok(())
@@ -137,44 +313,132 @@ pub fn exec(path: &str) -> Result<()> {
 pub struct ExitStatus {
     pub pid: usize,
     pub exit_code: i32,
+    /// The signal that terminated the process, `exit_code` is 0 then.
+    pub signal: Option<usize>,
 }
 
-pub fn wait() -> Result<ExitStatus> {
-    let mut exit_code = 0;
-
-    loop {
-        let ret = syscall::sys_wait(-1, &mut exit_code);
-        if ret < 0 {
-            return Err(Error::Syscall(ret));
-        } else if ret == 0 {
-            sched_yield();
-        } else {
-            let pid = ret as usize;
-            return Ok(ExitStatus { pid, exit_code });
+impl ExitStatus {
+    fn from_wait_status(pid: usize, wstatus: i32) -> Self {
+        let signal = (wstatus & 0x7f) as usize;
+        if signal != 0 {
+            return Self {
+                pid,
+                exit_code: 0,
+                signal: Some(signal),
+            };
+        }
+
+        Self {
+            pid,
+            exit_code: (wstatus >> 8) & 0xff,
+            signal: None,
         }
     }
 }
 
+pub fn wait() -> Result<ExitStatus> {
+    let mut wstatus = 0;
+
+    let ret = syscall::sys_wait(-1, &mut wstatus, 0);
+    if ret < 0 {
+        return Err(Error::from_syscall(ret));
+    }
+
+    Ok(ExitStatus::from_wait_status(ret as usize, wstatus))
+}
+
 pub fn waitpid(pid: usize) -> Result<ExitStatus> {
-    let mut exit_code = 0;
-
-    loop {
-        let ret = syscall::sys_wait(pid as isize, &mut exit_code);
-        if ret < 0 {
-            return Err(Error::Syscall(ret));
-        } else if ret == 0 {
-            sched_yield();
-        } else {
-            let ret_pid: usize = ret as usize;
-            assert_eq!(pid, ret_pid);
-            return Ok(ExitStatus {
-                pid: ret_pid,
-                exit_code,
-            });
-        }
+    let mut wstatus = 0;
+
+    let ret = syscall::sys_wait(pid as isize, &mut wstatus, 0);
+    if ret < 0 {
+        return Err(Error::from_syscall(ret));
+    }
+
+    let ret_pid = ret as usize;
+    assert_eq!(pid, ret_pid);
+    Ok(ExitStatus::from_wait_status(ret_pid, wstatus))
+}
+
+pub fn try_wait() -> Result<Option<ExitStatus>> {
+    let mut wstatus = 0;
+
+    let ret = syscall::sys_wait(-1, &mut wstatus, WNOHANG);
+    if ret < 0 {
+        return Err(Error::from_syscall(ret));
+    }
+
+    if ret == 0 {
+        return Ok(None);
+    }
+
+    Ok(Some(ExitStatus::from_wait_status(ret as usize, wstatus)))
+}
+
+/// Sets the nice value (`-20..=19`, lower runs more) of `pid`, or of the
+/// calling process when `pid` is 0.
+pub fn setpriority(pid: usize, nice: i32) -> Result<()> {
+    let ret = syscall::sys_setpriority(PRIO_PROCESS, pid, nice);
+    if ret < 0 {
+        return Err(Error::from_syscall(ret));
+    }
+
+    Ok(())
+}
+
+pub fn getpriority(pid: usize) -> Result<i32> {
+    let ret = syscall::sys_getpriority(PRIO_PROCESS, pid);
+    if ret < 0 {
+        return Err(Error::from_syscall(ret));
     }
+
+    Ok(20 - ret as i32)
 }
 
 pub fn getpid() -> usize {
     sys_getpid()
 }
+
+pub fn gettid() -> usize {
+    syscall::sys_gettid()
+}
+
+pub fn brk(addr: usize) -> usize {
+    syscall::sys_brk(addr)
+}
+
+pub fn sbrk(increment: isize) -> Result<usize> {
+    let ret = syscall::sys_sbrk(increment);
+    if ret < 0 {
+        return Err(Error::from_syscall(ret));
+    }
+
+    Ok(ret as usize)
+}
+
+pub fn mmap(addr: usize, len: usize, prot: usize, flags: usize) -> Result<usize> {
+    let ret = syscall::sys_mmap(addr, len, prot, flags | MAP_PRIVATE | MAP_ANONYMOUS);
+    if ret < 0 {
+        return Err(Error::from_syscall(ret));
+    }
+
+    Ok(ret as usize)
+}
+
+pub fn munmap(addr: usize, len: usize) -> Result<()> {
+    let ret = syscall::sys_munmap(addr, len);
+    if ret < 0 {
+        return Err(Error::from_syscall(ret));
+    }
+
+    Ok(())
+}
+
+pub fn mprotect(addr: usize, len: usize, prot: usize) -> Result<()> {
+    let ret = syscall::sys_mprotect(addr, len, prot);
+    if ret < 0 {
+        return Err(Error::from_syscall(ret));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,96 @@
+use crate::{
+    error::{Error, Result},
+    syscall,
+};
+
+pub const SIGHUP: usize = 1;
+pub const SIGINT: usize = 2;
+pub const SIGQUIT: usize = 3;
+pub const SIGILL: usize = 4;
+pub const SIGTRAP: usize = 5;
+pub const SIGABRT: usize = 6;
+pub const SIGBUS: usize = 7;
+pub const SIGKILL: usize = 9;
+pub const SIGUSR1: usize = 10;
+pub const SIGSEGV: usize = 11;
+pub const SIGUSR2: usize = 12;
+pub const SIGALRM: usize = 14;
+pub const SIGTERM: usize = 15;
+pub const SIGCHLD: usize = 17;
+
+pub const SIG_DFL: usize = 0;
+pub const SIG_IGN: usize = 1;
+
+pub const SIG_BLOCK: usize = 0;
+pub const SIG_UNBLOCK: usize = 1;
+pub const SIG_SETMASK: usize = 2;
+
+pub type SignalHandler = extern "C" fn(sig: usize);
+
+#[derive(Debug, Clone, Copy, Default)]
+#[repr(C)]
+pub struct SignalAction {
+    pub handler: usize,
+    pub mask: u64,
+    pub restorer: usize,
+}
+
+impl SignalAction {
+    /// Runs `handler` with `mask` blocked on top of the current mask.
+    pub fn new(handler: SignalHandler, mask: u64) -> Self {
+        Self {
+            handler: handler as usize,
+            mask,
+            restorer: restore as usize,
+        }
+    }
+}
+
+/// Signal handlers return here, the kernel then resumes the interrupted code.
+extern "C" fn restore() -> ! {
+    syscall::sys_sigreturn();
+
+    unreachable!()
+}
+
+pub const fn sigmask(sig: usize) -> u64 {
+    1 << sig
+}
+
+pub fn kill(pid: usize, sig: usize) -> Result<()> {
+    let ret = syscall::sys_kill(pid, sig);
+    if ret < 0 {
+        return Err(Error::from_syscall(ret));
+    }
+
+    Ok(())
+}
+
+/// Installs `action` for `sig` and returns the previous one.
+pub fn sigaction(sig: usize, action: &SignalAction) -> Result<SignalAction> {
+    let mut old_action = SignalAction::default();
+
+    let ret = syscall::sys_sigaction(sig, action, &mut old_action);
+    if ret < 0 {
+        return Err(Error::from_syscall(ret));
+    }
+
+    Ok(old_action)
+}
+
+pub fn signal(sig: usize, handler: SignalHandler) -> Result<SignalAction> {
+    sigaction(sig, &SignalAction::new(handler, 0))
+}
+
+/// Changes the blocked signal mask as selected by `how` and returns the
+/// previous mask.
+pub fn sigprocmask(how: usize, set: u64) -> Result<u64> {
+    let mut old_set = 0;
+
+    let ret = syscall::sys_sigprocmask(how, &set, &mut old_set);
+    if ret < 0 {
+        return Err(Error::from_syscall(ret));
+    }
+
+    Ok(old_set)
+}
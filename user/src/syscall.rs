@@ -1,17 +1,95 @@
 use core::{arch::asm, ffi::CStr};
 
-use crate::TimeVal;
+use crate::{signal::SignalAction, Stat, TimeSpec, TimeVal};
 
+pub const SYS_GETCWD: usize = 17;
+pub const SYS_DUP: usize = 23;
+pub const SYS_DUP3: usize = 24;
+pub const SYS_MKDIRAT: usize = 34;
+pub const SYS_UNLINKAT: usize = 35;
+pub const SYS_CHDIR: usize = 49;
+pub const SYS_OPENAT: usize = 56;
+pub const SYS_CLOSE: usize = 57;
+pub const SYS_PIPE2: usize = 59;
+pub const SYS_GETDENTS64: usize = 61;
+pub const SYS_LSEEK: usize = 62;
 pub const SYS_READ: usize = 63;
 pub const SYS_WRITE: usize = 64;
+pub const SYS_FSTAT: usize = 80;
 pub const SYS_EXIT: usize = 93;
+pub const SYS_NANOSLEEP: usize = 101;
 pub const SYS_SCHED_YIELD: usize = 124;
+pub const SYS_KILL: usize = 129;
+pub const SYS_SIGACTION: usize = 134;
+pub const SYS_SIGPROCMASK: usize = 135;
+pub const SYS_SIGRETURN: usize = 139;
+pub const SYS_SETPRIORITY: usize = 140;
+pub const SYS_GETPRIORITY: usize = 141;
 pub const SYS_GETTIMEOFDAY: usize = 169;
 pub const SYS_GETPID: usize = 172;
+pub const SYS_GETTID: usize = 178;
+pub const SYS_BRK: usize = 214;
+pub const SYS_MUNMAP: usize = 215;
 pub const SYS_FORK: usize = 220;
 pub const SYS_EXEC: usize = 221;
+pub const SYS_MMAP: usize = 222;
+pub const SYS_MPROTECT: usize = 226;
 pub const SYS_WAITPID: usize = 260;
 
+pub const SYS_SBRK: usize = 1000;
+pub const SYS_THREAD_CREATE: usize = 1001;
+pub const SYS_THREAD_JOIN: usize = 1002;
+
+pub fn sys_getcwd(buf: &mut [u8]) -> isize {
+    syscall_2(SYS_GETCWD, buf.as_mut_ptr() as usize, buf.len())
+}
+
+pub fn sys_dup(fd: usize) -> isize {
+    syscall_1(SYS_DUP, fd)
+}
+
+pub fn sys_dup3(old_fd: usize, new_fd: usize, flags: usize) -> isize {
+    syscall_3(SYS_DUP3, old_fd, new_fd, flags)
+}
+
+pub fn sys_mkdirat(dirfd: isize, path: &CStr, mode: usize) -> isize {
+    syscall_3(SYS_MKDIRAT, dirfd as usize, path.as_ptr() as usize, mode)
+}
+
+pub fn sys_unlinkat(dirfd: isize, path: &CStr, flags: usize) -> isize {
+    syscall_3(SYS_UNLINKAT, dirfd as usize, path.as_ptr() as usize, flags)
+}
+
+pub fn sys_chdir(path: &CStr) -> isize {
+    syscall_1(SYS_CHDIR, path.as_ptr() as usize)
+}
+
+pub fn sys_openat(dirfd: isize, path: &CStr, flags: usize, mode: usize) -> isize {
+    syscall_4(
+        SYS_OPENAT,
+        dirfd as usize,
+        path.as_ptr() as usize,
+        flags,
+        mode,
+    )
+}
+
+pub fn sys_close(fd: usize) -> isize {
+    syscall_1(SYS_CLOSE, fd)
+}
+
+pub fn sys_pipe2(fds: &mut [i32; 2], flags: usize) -> isize {
+    syscall_2(SYS_PIPE2, fds as *mut [i32; 2] as usize, flags)
+}
+
+pub fn sys_getdents64(fd: usize, buf: &mut [u8]) -> isize {
+    syscall_3(SYS_GETDENTS64, fd, buf.as_mut_ptr() as usize, buf.len())
+}
+
+pub fn sys_lseek(fd: usize, offset: isize, whence: usize) -> isize {
+    syscall_3(SYS_LSEEK, fd, offset as usize, whence)
+}
+
 pub fn sys_read(fd: usize, buf: &mut [u8]) -> isize {
     syscall_3(SYS_READ, fd, buf.as_ptr() as usize, buf.len())
 }
@@ -20,16 +98,58 @@ pub fn sys_write(fd: usize, buf: &[u8]) -> isize {
     syscall_3(SYS_WRITE, fd, buf.as_ptr() as usize, buf.len())
 }
 
+pub fn sys_fstat(fd: usize, stat: &mut Stat) -> isize {
+    syscall_2(SYS_FSTAT, fd, stat as *mut Stat as usize)
+}
+
 pub fn sys_exit(exit_code: usize) -> ! {
     syscall_1(SYS_EXIT, exit_code);
 
     unreachable!()
 }
 
+pub fn sys_nanosleep(req: &TimeSpec, rem: *mut TimeSpec) -> isize {
+    syscall_2(SYS_NANOSLEEP, req as *const TimeSpec as usize, rem as usize)
+}
+
 pub fn sys_sched_yield() -> isize {
     syscall_0(SYS_SCHED_YIELD)
 }
 
+pub fn sys_kill(pid: usize, sig: usize) -> isize {
+    syscall_2(SYS_KILL, pid, sig)
+}
+
+pub fn sys_sigaction(sig: usize, action: &SignalAction, old_action: &mut SignalAction) -> isize {
+    syscall_3(
+        SYS_SIGACTION,
+        sig,
+        action as *const SignalAction as usize,
+        old_action as *mut SignalAction as usize,
+    )
+}
+
+pub fn sys_sigprocmask(how: usize, set: &u64, old_set: &mut u64) -> isize {
+    syscall_3(
+        SYS_SIGPROCMASK,
+        how,
+        set as *const u64 as usize,
+        old_set as *mut u64 as usize,
+    )
+}
+
+pub fn sys_sigreturn() -> isize {
+    syscall_0(SYS_SIGRETURN)
+}
+
+pub fn sys_setpriority(which: usize, who: usize, nice: i32) -> isize {
+    syscall_3(SYS_SETPRIORITY, which, who, nice as isize as usize)
+}
+
+pub fn sys_getpriority(which: usize, who: usize) -> isize {
+    syscall_2(SYS_GETPRIORITY, which, who)
+}
+
 pub fn sys_gettimeofday(tp: *mut TimeVal, tzp: usize) -> isize {
     syscall_2(SYS_GETTIMEOFDAY, tp as usize, tzp)
 }
@@ -38,18 +158,60 @@ pub fn sys_fork() -> isize {
     syscall_0(SYS_FORK)
 }
 
-pub fn sys_exec(path: &CStr) -> isize {
-    syscall_1(SYS_EXEC, path.as_ptr() as usize)
+pub fn sys_exec(path: &CStr, argv: &[usize], envp: &[usize]) -> isize {
+    syscall_3(
+        SYS_EXEC,
+        path.as_ptr() as usize,
+        argv.as_ptr() as usize,
+        envp.as_ptr() as usize,
+    )
 }
 
-pub fn sys_wait(pid: isize, exit_code: &mut i32) -> isize {
-    syscall_2(SYS_WAITPID, pid as usize, exit_code as *mut i32 as usize)
+pub fn sys_wait(pid: isize, wstatus: &mut i32, options: usize) -> isize {
+    syscall_3(
+        SYS_WAITPID,
+        pid as usize,
+        wstatus as *mut i32 as usize,
+        options,
+    )
 }
 
 pub fn sys_getpid() -> usize {
     syscall_0(SYS_GETPID) as usize
 }
 
+pub fn sys_gettid() -> usize {
+    syscall_0(SYS_GETTID) as usize
+}
+
+pub fn sys_thread_create(entry: usize, arg: usize) -> isize {
+    syscall_2(SYS_THREAD_CREATE, entry, arg)
+}
+
+pub fn sys_thread_join(tid: usize, wstatus: &mut i32) -> isize {
+    syscall_2(SYS_THREAD_JOIN, tid, wstatus as *mut i32 as usize)
+}
+
+pub fn sys_brk(addr: usize) -> usize {
+    syscall_1(SYS_BRK, addr) as usize
+}
+
+pub fn sys_sbrk(increment: isize) -> isize {
+    syscall_1(SYS_SBRK, increment as usize)
+}
+
+pub fn sys_mmap(addr: usize, len: usize, prot: usize, flags: usize) -> isize {
+    syscall_6(SYS_MMAP, addr, len, prot, flags, -1isize as usize, 0)
+}
+
+pub fn sys_munmap(addr: usize, len: usize) -> isize {
+    syscall_2(SYS_MUNMAP, addr, len)
+}
+
+pub fn sys_mprotect(addr: usize, len: usize, prot: usize) -> isize {
+    syscall_3(SYS_MPROTECT, addr, len, prot)
+}
+
 #[allow(dead_code)]
 fn syscall_0(id: usize) -> isize {
     let mut ret: isize;
@@ -107,3 +269,45 @@ fn syscall_3(id: usize, arg0: usize, arg1: usize, arg2: usize) -> isize {
 
     ret
 }
+
+fn syscall_4(id: usize, arg0: usize, arg1: usize, arg2: usize, arg3: usize) -> isize {
+    let mut ret: isize;
+    unsafe {
+        asm!(
+            "ecall",
+            in("a7") id,
+            inlateout("a0") arg0 => ret,
+            in("a1") arg1,
+            in("a2") arg2,
+            in("a3") arg3,
+        );
+    }
+
+    ret
+}
+
+fn syscall_6(
+    id: usize,
+    arg0: usize,
+    arg1: usize,
+    arg2: usize,
+    arg3: usize,
+    arg4: usize,
+    arg5: usize,
+) -> isize {
+    let mut ret: isize;
+    unsafe {
+        asm!(
+            "ecall",
+            in("a7") id,
+            inlateout("a0") arg0 => ret,
+            in("a1") arg1,
+            in("a2") arg2,
+            in("a3") arg3,
+            in("a4") arg4,
+            in("a5") arg5,
+        );
+    }
+
+    ret
+}
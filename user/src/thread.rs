@@ -0,0 +1,76 @@
+use alloc::{boxed::Box, sync::Arc};
+use core::cell::UnsafeCell;
+
+use crate::{
+    error::{Error, Result},
+    exit, syscall,
+};
+
+type ThreadMain = Box<dyn FnOnce()>;
+
+struct Packet<T> {
+    result: UnsafeCell<Option<T>>,
+}
+
+// only the spawned thread writes the result, and only before it exits
+unsafe impl<T: Send> Sync for Packet<T> {}
+
+pub struct JoinHandle<T> {
+    tid: usize,
+    packet: Arc<Packet<T>>,
+}
+
+impl<T> JoinHandle<T> {
+    pub fn tid(&self) -> usize {
+        self.tid
+    }
+
+    /// Waits for the thread to exit and returns its result, or
+    /// `Error::ThreadPanicked` if it exited without finishing `f`.
+    pub fn join(self) -> Result<T> {
+        let mut wstatus = 0;
+
+        let ret = syscall::sys_thread_join(self.tid, &mut wstatus);
+        if ret < 0 {
+            return Err(Error::from_syscall(ret));
+        }
+
+        unsafe { (*self.packet.result.get()).take() }.ok_or(Error::ThreadPanicked)
+    }
+}
+
+/// Runs `f` on a new thread sharing the address space of the caller.
+pub fn spawn<F, T>(f: F) -> Result<JoinHandle<T>>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let packet = Arc::new(Packet {
+        result: UnsafeCell::new(None),
+    });
+    let their_packet = packet.clone();
+
+    let main: ThreadMain = Box::new(move || {
+        let result = f();
+        unsafe { *their_packet.result.get() = Some(result) };
+    });
+    let arg = Box::into_raw(Box::new(main));
+
+    let ret = syscall::sys_thread_create(thread_start as usize, arg as usize);
+    if ret < 0 {
+        drop(unsafe { Box::from_raw(arg) });
+        return Err(Error::from_syscall(ret));
+    }
+
+    Ok(JoinHandle {
+        tid: ret as usize,
+        packet,
+    })
+}
+
+extern "C" fn thread_start(arg: usize) -> ! {
+    let main = unsafe { Box::from_raw(arg as *mut ThreadMain) };
+    main();
+
+    exit(0)
+}